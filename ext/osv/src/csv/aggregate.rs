@@ -0,0 +1,286 @@
+use super::builder::ReaderError;
+use super::columnar::{is_empty, ColumnType};
+use super::record::Field;
+use super::transform::field_text;
+use super::types::Cast;
+use ahash::RandomState;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// A function summarizing the values of a column in each group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+    Min,
+    Max,
+    Mean,
+}
+
+impl Aggregate {
+    /// The option naming the columns this function is computed for.
+    pub fn name(self) -> &'static str {
+        match self {
+            Aggregate::Sum => "sum",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+            Aggregate::Mean => "mean",
+        }
+    }
+}
+
+/// The query run by `OSV.aggregate`, by column name.
+#[derive(Debug, Clone, Default)]
+pub struct Aggregates {
+    /// The columns rows are grouped by. Every row is in the same group when empty.
+    pub group_by: Vec<String>,
+    /// Whether the rows of each group are counted.
+    pub count: bool,
+    /// The columns each function is computed for.
+    pub columns: Vec<(Aggregate, Vec<String>)>,
+}
+
+impl Aggregates {
+    /// Resolves the column names against the headers of the input. A repeated
+    /// header stands for its first column.
+    pub fn resolve(&self, headers: &[String]) -> Result<Aggregator, ReaderError> {
+        let position = |name: &String| {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| ReaderError::UnknownColumn(name.clone()))
+        };
+
+        let mut measures: Vec<usize> = Vec::new();
+        let mut outputs = Vec::with_capacity(self.columns.len());
+        for (aggregate, names) in &self.columns {
+            let mut columns = Vec::with_capacity(names.len());
+            for name in names {
+                let field = position(name)?;
+                let measure = match measures.iter().position(|&i| i == field) {
+                    Some(measure) => measure,
+                    None => {
+                        measures.push(field);
+                        measures.len() - 1
+                    }
+                };
+                columns.push(measure);
+            }
+            outputs.push((*aggregate, columns));
+        }
+
+        Ok(Aggregator {
+            group_by: self
+                .group_by
+                .iter()
+                .map(position)
+                .collect::<Result<_, _>>()?,
+            count: self.count,
+            measures,
+            outputs,
+            headers: headers.to_vec(),
+            index: HashMap::default(),
+            groups: Vec::new(),
+            key: Vec::new(),
+        })
+    }
+}
+
+/// The key of a group: the value of each group column, `None` for nil.
+pub type GroupKey = Vec<Option<String>>;
+
+/// The result of a group: its number of rows when counted, and the value of each
+/// function for each of its columns, in the order they were asked for. A column
+/// without values in the group has no value.
+#[derive(Debug, Clone)]
+pub struct GroupResult {
+    pub count: Option<usize>,
+    pub values: Vec<(Aggregate, Vec<Option<Cast>>)>,
+}
+
+/// Groups rows and summarizes their values as they are pushed to it.
+pub struct Aggregator {
+    group_by: Vec<usize>,
+    count: bool,
+    /// The positions of the fields summarized, each once.
+    measures: Vec<usize>,
+    /// The functions asked for, with the measures they summarize.
+    outputs: Vec<(Aggregate, Vec<usize>)>,
+    headers: Vec<String>,
+    /// The position of each group in `groups`, which keeps them in the order
+    /// they were first seen.
+    index: HashMap<GroupKey, usize, RandomState>,
+    groups: Vec<(GroupKey, Group)>,
+    /// The key of the last row, reused to spare an allocation per row.
+    key: GroupKey,
+}
+
+impl Aggregator {
+    /// Adds a row to its group. Fields a short row lacks are nil, and nil and
+    /// empty values are left out of the functions.
+    pub fn push(&mut self, row: &[Option<Field<'_>>]) -> Result<(), ReaderError> {
+        self.key.resize(self.group_by.len(), None);
+        for (slot, &i) in self.key.iter_mut().zip(&self.group_by) {
+            match row.get(i).and_then(Option::as_ref) {
+                None => *slot = None,
+                Some(field) => {
+                    let text = field_text(Some(field));
+                    match slot {
+                        Some(value) => {
+                            value.clear();
+                            value.push_str(&text);
+                        }
+                        None => *slot = Some(text.into_owned()),
+                    }
+                }
+            }
+        }
+
+        let group = match self.index.get(&self.key) {
+            Some(&group) => group,
+            None => {
+                self.index.insert(self.key.clone(), self.groups.len());
+                self.groups
+                    .push((self.key.clone(), Group::new(self.measures.len())));
+                self.groups.len() - 1
+            }
+        };
+        let (_, group) = &mut self.groups[group];
+
+        group.rows += 1;
+        for (accumulator, &i) in group.accumulators.iter_mut().zip(&self.measures) {
+            let Some(field) = row.get(i).and_then(Option::as_ref) else {
+                continue;
+            };
+            if is_empty(field) {
+                continue;
+            }
+            let value = ColumnType::Integer
+                .cast(field)
+                .or_else(|| ColumnType::Float.cast(field))
+                .ok_or_else(|| ReaderError::ColumnType {
+                    column: self.headers[i].clone(),
+                    value: field_text(Some(field)).into_owned(),
+                    expected: "number",
+                })?;
+            accumulator.add(value);
+        }
+        Ok(())
+    }
+
+    /// Returns the result of every group, in the order they were first seen.
+    /// Without group columns, there is a single group even when no row was read.
+    pub fn finish(mut self) -> Vec<(GroupKey, GroupResult)> {
+        if self.group_by.is_empty() && self.groups.is_empty() {
+            self.groups
+                .push((Vec::new(), Group::new(self.measures.len())));
+        }
+
+        let outputs = self.outputs;
+        let count = self.count;
+        self.groups
+            .into_iter()
+            .map(|(key, group)| {
+                let values = outputs
+                    .iter()
+                    .map(|(aggregate, measures)| {
+                        let values = measures
+                            .iter()
+                            .map(|&measure| group.accumulators[measure].value(*aggregate))
+                            .collect();
+                        (*aggregate, values)
+                    })
+                    .collect();
+                let result = GroupResult {
+                    count: count.then_some(group.rows),
+                    values,
+                };
+                (key, result)
+            })
+            .collect()
+    }
+}
+
+struct Group {
+    rows: usize,
+    accumulators: Vec<Accumulator>,
+}
+
+impl Group {
+    fn new(measures: usize) -> Self {
+        Self {
+            rows: 0,
+            accumulators: vec![Accumulator::default(); measures],
+        }
+    }
+}
+
+/// What is kept of the values of a column in a group.
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    values: usize,
+    /// The sum while every value is an integer and it fits in 64 bits.
+    integer_sum: Option<i64>,
+    float_sum: f64,
+    min: Option<Cast>,
+    max: Option<Cast>,
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self {
+            values: 0,
+            integer_sum: Some(0),
+            float_sum: 0.0,
+            min: None,
+            max: None,
+        }
+    }
+}
+
+impl Accumulator {
+    #[inline]
+    fn add(&mut self, value: Cast) {
+        match value {
+            Cast::Integer(value) => {
+                self.integer_sum = self.integer_sum.and_then(|sum| sum.checked_add(value));
+                self.float_sum += value as f64;
+            }
+            Cast::Number(value) => {
+                self.integer_sum = None;
+                self.float_sum += value;
+            }
+            _ => return,
+        }
+        self.values += 1;
+
+        let below_min = match &self.min {
+            Some(min) => value.partial_cmp(min) == Some(Ordering::Less),
+            None => true,
+        };
+        if below_min {
+            self.min = Some(value);
+        }
+        let above_max = match &self.max {
+            Some(max) => value.partial_cmp(max) == Some(Ordering::Greater),
+            None => true,
+        };
+        if above_max {
+            self.max = Some(value);
+        }
+    }
+
+    fn value(&self, aggregate: Aggregate) -> Option<Cast> {
+        if self.values == 0 {
+            return None;
+        }
+        match aggregate {
+            Aggregate::Sum => Some(match self.integer_sum {
+                Some(sum) => Cast::Integer(sum),
+                None => Cast::Number(self.float_sum),
+            }),
+            Aggregate::Min => self.min,
+            Aggregate::Max => self.max,
+            Aggregate::Mean => Some(Cast::Number(self.float_sum / self.values as f64)),
+        }
+    }
+}
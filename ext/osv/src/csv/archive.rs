@@ -0,0 +1,261 @@
+use super::builder::ReaderError;
+use super::record_reader::READ_BUFFER_SIZE;
+use flate2::bufread::{DeflateDecoder, GzDecoder};
+use glob::Pattern;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use tar::{Archive, Entry};
+use zip::{CompressionMethod, ZipArchive};
+
+/// The archive formats whose members can be read directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Recognises an archive by the extension of its path.
+    pub(crate) fn of(path: &str) -> Option<Self> {
+        if path.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if path.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Selects the member of an archive to read, given the `entry:` option.
+struct EntryMatcher {
+    pattern: Option<Pattern>,
+    /// Whether the pattern names whole paths inside the archive, or only file names.
+    full_path: bool,
+}
+
+impl EntryMatcher {
+    fn new(path: &str, entry: Option<&str>) -> Result<Self, ReaderError> {
+        let pattern = entry
+            .map(Pattern::new)
+            .transpose()
+            .map_err(|e| archive_error(path, format!("invalid entry pattern: {e}")))?;
+        Ok(Self {
+            pattern,
+            full_path: entry.is_some_and(|entry| entry.contains('/')),
+        })
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        let Some(pattern) = &self.pattern else {
+            return !is_macos_metadata(name);
+        };
+        let name = name.strip_prefix("./").unwrap_or(name);
+        if self.full_path {
+            pattern.matches(name)
+        } else {
+            pattern.matches(name.rsplit('/').next().unwrap_or(name))
+        }
+    }
+}
+
+/// Whether a member holds the metadata macOS adds to the archives it creates:
+/// anything under `__MACOSX/`, and `._name` files beside the files they describe.
+fn is_macos_metadata(name: &str) -> bool {
+    let name = name.strip_prefix("./").unwrap_or(name);
+    name.starts_with("__MACOSX/")
+        || name
+            .rsplit('/')
+            .next()
+            .is_some_and(|file| file.starts_with("._"))
+}
+
+/// Opens the member of the archive at `path` selected by `entry`, streaming it
+/// without extracting anything to disk.
+///
+/// `entry` is a member name or a glob pattern, matched against file names unless
+/// it contains a `/`. The first matching member is read. Without an `entry`, the
+/// archive must hold a single file, not counting macOS metadata.
+pub(crate) fn open_member(
+    path: &str,
+    kind: ArchiveKind,
+    entry: Option<&str>,
+) -> Result<Box<dyn Read>, ReaderError> {
+    let matcher = EntryMatcher::new(path, entry)?;
+    let file = File::open(path)?;
+    match kind {
+        ArchiveKind::Zip => open_zip_member(path, file, &matcher),
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            // A tar archive can only be read front to back, so making sure it holds
+            // a single file takes a pass over it before the file is read
+            if matcher.pattern.is_none() {
+                let names = list_tar_files(path, tar_stream(kind, File::open(path)?))?;
+                if names.len() > 1 {
+                    return Err(several_files_error(path, &names));
+                }
+            }
+            open_tar_member(path, tar_stream(kind, file), &matcher)
+        }
+    }
+}
+
+/// Reads the tar stream held in `file`, decompressing it if needed.
+fn tar_stream(kind: ArchiveKind, file: File) -> Box<dyn Read> {
+    match kind {
+        ArchiveKind::TarGz => Box::new(GzDecoder::new(BufReader::with_capacity(
+            READ_BUFFER_SIZE,
+            file,
+        ))),
+        _ => Box::new(BufReader::new(file)),
+    }
+}
+
+fn archive_error(path: &str, message: impl Into<String>) -> ReaderError {
+    ReaderError::Archive {
+        path: path.to_string(),
+        message: message.into(),
+    }
+}
+
+fn no_member_error(path: &str, entry: &EntryMatcher) -> ReaderError {
+    match &entry.pattern {
+        Some(pattern) => archive_error(path, format!("no entry matches {:?}", pattern.as_str())),
+        None => archive_error(path, "the archive contains no files"),
+    }
+}
+
+fn several_files_error(path: &str, names: &[String]) -> ReaderError {
+    archive_error(
+        path,
+        format!(
+            "the archive contains several files ({}), pick one with `entry:`",
+            names.join(", ")
+        ),
+    )
+}
+
+/// Finds the member in the zip's central directory, then reads its data straight
+/// from the file, inflating it if needed.
+fn open_zip_member(
+    path: &str,
+    file: File,
+    entry: &EntryMatcher,
+) -> Result<Box<dyn Read>, ReaderError> {
+    let mut archive = ZipArchive::new(file).map_err(|e| archive_error(path, e.to_string()))?;
+
+    let mut found = None;
+    for index in 0..archive.len() {
+        let member = archive
+            .by_index_raw(index)
+            .map_err(|e| archive_error(path, e.to_string()))?;
+        if member.is_dir() || !entry.matches(member.name()) {
+            continue;
+        }
+        if found.is_some() {
+            if entry.pattern.is_none() {
+                drop(member);
+                let names = archive
+                    .file_names()
+                    .filter(|name| !name.ends_with('/') && !is_macos_metadata(name))
+                    .map(str::to_string)
+                    .collect::<Vec<_>>();
+                return Err(several_files_error(path, &names));
+            }
+            break;
+        }
+        if member.encrypted() {
+            return Err(archive_error(
+                path,
+                format!("{} is encrypted", member.name()),
+            ));
+        }
+        found = Some((
+            member.name().to_string(),
+            member.compression(),
+            member.data_start(),
+            member.compressed_size(),
+        ));
+    }
+    let Some((name, compression, start, size)) = found else {
+        return Err(no_member_error(path, entry));
+    };
+
+    let mut file = archive.into_inner();
+    file.seek(SeekFrom::Start(start))?;
+    let data = file.take(size);
+    match compression {
+        CompressionMethod::Stored => Ok(Box::new(data)),
+        CompressionMethod::Deflated => Ok(Box::new(DeflateDecoder::new(BufReader::with_capacity(
+            READ_BUFFER_SIZE,
+            data,
+        )))),
+        method => Err(archive_error(
+            path,
+            format!("{name} is compressed with unsupported method {method}"),
+        )),
+    }
+}
+
+/// Walks the tar entries until the member is found, and hands out a reader
+/// limited to the member's data.
+fn open_tar_member(
+    path: &str,
+    stream: Box<dyn Read>,
+    entry: &EntryMatcher,
+) -> Result<Box<dyn Read>, ReaderError> {
+    let mut archive = Archive::new(stream);
+    let mut size = None;
+    for member in archive
+        .entries()
+        .map_err(|e| archive_error(path, e.to_string()))?
+    {
+        let member = member.map_err(|e| archive_error(path, e.to_string()))?;
+        if is_tar_file(&member) && entry.matches(&tar_name(path, &member)?) {
+            size = Some(member.size());
+            break;
+        }
+    }
+    let Some(size) = size else {
+        return Err(no_member_error(path, entry));
+    };
+    // The data of an entry is only read or skipped when it's used, so the stream
+    // is left at the start of the member's data
+    Ok(Box::new(archive.into_inner().take(size)))
+}
+
+/// Returns the names of the files in a tar archive, leaving out macOS metadata.
+fn list_tar_files(path: &str, stream: impl Read) -> Result<Vec<String>, ReaderError> {
+    let mut archive = Archive::new(stream);
+    let mut names = Vec::new();
+    for member in archive
+        .entries()
+        .map_err(|e| archive_error(path, e.to_string()))?
+    {
+        let member = member.map_err(|e| archive_error(path, e.to_string()))?;
+        if !is_tar_file(&member) {
+            continue;
+        }
+        let name = tar_name(path, &member)?;
+        if !is_macos_metadata(&name) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+fn is_tar_file<R: Read>(member: &Entry<'_, R>) -> bool {
+    let kind = member.header().entry_type();
+    kind.is_file() || kind.is_contiguous()
+}
+
+/// The path of a tar entry, with GNU long names and PAX headers applied.
+fn tar_name<R: Read>(path: &str, member: &Entry<'_, R>) -> Result<String, ReaderError> {
+    Ok(member
+        .path()
+        .map_err(|e| archive_error(path, e.to_string()))?
+        .to_string_lossy()
+        .into_owned())
+}
@@ -0,0 +1,63 @@
+use arrow_array::ffi::{to_ffi, FFI_ArrowArray, FFI_ArrowSchema};
+use arrow_array::{Array, RecordBatch, StructArray};
+use magnus::{Error, Ruby};
+
+/// A record batch read by `OSV.read_arrow`, handed to Arrow libraries through the
+/// Arrow C data interface without copying its buffers.
+#[magnus::wrap(class = "OSV::ArrowBatch", free_immediately, size)]
+pub struct ArrowBatch(RecordBatch);
+
+impl ArrowBatch {
+    pub fn new(batch: RecordBatch) -> Self {
+        Self(batch)
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.0.num_rows()
+    }
+
+    pub fn num_columns(&self) -> usize {
+        self.0.num_columns()
+    }
+
+    pub fn column_names(&self) -> Vec<String> {
+        self.0
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect()
+    }
+
+    /// Exports the batch as a struct array into the `ArrowArray` and `ArrowSchema`
+    /// structs at the given addresses, which the caller allocated and now owns:
+    /// the consumer releases them, or moves them into its own arrays. Their
+    /// previous content is overwritten, not released.
+    ///
+    /// The buffers are shared, not copied, and outlive the batch until released.
+    ///
+    /// The addresses are trusted: anything but memory fit for the two structs is
+    /// overwritten all the same. The method is private to Ruby for that reason,
+    /// and only `ArrowBatch#to_arrow` calls it, with structs it allocates itself.
+    pub fn export_to_c(&self, array_address: usize, schema_address: usize) -> Result<(), Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        if array_address == 0 || schema_address == 0 {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                "export_to_c expects the addresses of an ArrowArray and an ArrowSchema",
+            ));
+        }
+
+        let array = StructArray::from(self.0.clone());
+        let (array, schema) = to_ffi(&array.to_data())
+            .map_err(|e| Error::new(ruby.exception_runtime_error(), e.to_string()))?;
+
+        // SAFETY: the caller hands over memory fit for an `ArrowArray` and an
+        // `ArrowSchema`, as consumers of the C data interface do
+        unsafe {
+            std::ptr::write(array_address as *mut FFI_ArrowArray, array);
+            std::ptr::write(schema_address as *mut FFI_ArrowSchema, schema);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use super::builder::ReaderError;
+use super::parser::CsvRecordType;
+use super::quoting::{QuoteTracker, QuotedFields};
+use crate::gvl::without_gvl;
+use std::io::Read;
+
+/// Number of records tokenized each time the GVL is released.
+const RECORD_BATCH_SIZE: usize = 1024;
+
+/// Records read ahead from a native input with the GVL released.
+///
+/// Record buffers are reused between batches: a record handed out is swapped with
+/// the caller's buffer, which then gets refilled on the next pass.
+pub(crate) struct RecordBatch {
+    records: Vec<CsvRecordType>,
+    /// Which fields of each record were quoted, when the input tracks it.
+    quoted: Vec<QuotedFields>,
+    /// Byte offset just past each record, including its terminator.
+    ends: Vec<u64>,
+    len: usize,
+    next: usize,
+    /// An error hit while filling the batch, returned once the records read before it are used up.
+    error: Option<ReaderError>,
+    eof: bool,
+}
+
+impl RecordBatch {
+    pub(crate) fn new(lossy: bool) -> Self {
+        let records = (0..RECORD_BATCH_SIZE)
+            .map(|_| {
+                if lossy {
+                    CsvRecordType::Byte(csv::ByteRecord::new())
+                } else {
+                    CsvRecordType::String(csv::StringRecord::new())
+                }
+            })
+            .collect();
+        Self {
+            records,
+            quoted: vec![QuotedFields::default(); RECORD_BATCH_SIZE],
+            ends: vec![0; RECORD_BATCH_SIZE],
+            len: 0,
+            next: 0,
+            error: None,
+            eof: false,
+        }
+    }
+
+    /// Moves the next record into `record`, refilling the batch when it runs out.
+    #[inline]
+    pub(crate) fn read_record<R: Read>(
+        &mut self,
+        reader: &mut csv::Reader<QuoteTracker<R>>,
+        record: &mut CsvRecordType,
+        quoted: &mut QuotedFields,
+    ) -> Result<bool, ReaderError> {
+        if self.next == self.len {
+            if let Some(err) = self.error.take() {
+                return Err(err);
+            }
+            if self.eof {
+                return Ok(false);
+            }
+            self.fill(reader);
+            if self.len == 0 {
+                return self.error.take().map_or(Ok(false), Err);
+            }
+        }
+
+        std::mem::swap(record, &mut self.records[self.next]);
+        std::mem::swap(quoted, &mut self.quoted[self.next]);
+        self.next += 1;
+        Ok(true)
+    }
+
+    /// Returns the byte offset just past the record most recently handed out.
+    #[inline]
+    pub(crate) fn last_end(&self) -> u64 {
+        self.ends[self.next - 1]
+    }
+
+    fn fill<R: Read>(&mut self, reader: &mut csv::Reader<QuoteTracker<R>>) {
+        let records = &mut self.records;
+        let quoted = &mut self.quoted;
+        let ends = &mut self.ends;
+        let (len, outcome) = without_gvl(|| {
+            let slots = records
+                .iter_mut()
+                .zip(quoted.iter_mut())
+                .zip(ends.iter_mut());
+            for (index, ((record, quoted), end)) in slots.enumerate() {
+                let read = match record {
+                    CsvRecordType::String(record) => reader.read_record(record),
+                    CsvRecordType::Byte(record) => reader.read_byte_record(record),
+                };
+                match read {
+                    Ok(true) => {
+                        *end = reader.position().byte();
+                        reader.get_mut().next_record(quoted);
+                    }
+                    Ok(false) => return (index, Ok(false)),
+                    Err(err) => return (index, Err(err)),
+                }
+            }
+            (records.len(), Ok(true))
+        });
+
+        self.len = len;
+        self.next = 0;
+        match outcome {
+            Ok(has_more) => self.eof = !has_more,
+            Err(err) => self.error = Some(err.into()),
+        }
+    }
+}
@@ -1,13 +1,26 @@
 use super::{
+    batch::RecordBatch,
+    converters::ColumnConverters,
+    files::{FileChain, FileOptions},
     header_cache::{CacheError, StringCache},
+    parallel::{ChunkFormat, Dialect, ParallelOptions, ParallelReader},
     parser::RecordParser,
-    record_reader::{RecordReader, READ_BUFFER_SIZE},
-    ruby_reader::RubyReader,
+    quoting::QuoteTracker,
+    record_reader::{RecordReader, RecordSource, READ_BUFFER_SIZE},
+    ruby_reader::{InputKind, RubyReader},
+    schema::{Schema, Validation},
+    selection::{RowSelection, Sample},
+    transform::RowFilter,
+    unique::Unique,
+    values::{DuplicateHeaders, ExtraFields, FieldValues, MissingValues, NilValues},
+};
+use crate::gvl::without_gvl;
+use magnus::{
+    value::ReprValue, Error as MagnusError, Module, RArray, RClass, RString, Ruby, Value,
 };
-use magnus::{Error as MagnusError, RString, Ruby, Value};
 use std::{
     borrow::Cow,
-    io::{self, BufReader},
+    io::{self, BufReader, Read},
     marker::PhantomData,
 };
 
@@ -20,6 +33,8 @@ pub enum ReaderError {
     FileDescriptor(String),
     #[error("Invalid file descriptor: {0}")]
     InvalidFileDescriptor(i32),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
     #[error("Failed to open file: {0}")]
     FileOpen(#[from] io::Error),
     #[error("Failed to intern headers: {0}")]
@@ -32,6 +47,41 @@ pub enum ReaderError {
     CsvParse(#[from] csv::Error),
     #[error("Invalid UTF-8: {0}")]
     InvalidUtf8(String),
+    #[error("found record with {found} fields, but the previous record has {expected} fields")]
+    UnequalLengths { expected: usize, found: usize },
+    #[error("Headers of {path} ({found}) don't match the headers of the first file ({expected})")]
+    HeaderMismatch {
+        path: String,
+        expected: String,
+        found: String,
+    },
+    #[error("Failed to read archive {path}: {message}")]
+    Archive { path: String, message: String },
+    #[error("Unknown column: {0}")]
+    UnknownColumn(String),
+    #[error("Duplicate header: {0}")]
+    DuplicateHeader(String),
+    #[error("Column already exists: {0}")]
+    ColumnExists(String),
+    #[error("found record with {found} fields, but the header has {expected} fields")]
+    ExtraFields { expected: usize, found: usize },
+    #[error("Value {value:?} of column {column} isn't of type {expected}")]
+    ColumnType {
+        column: String,
+        value: String,
+        expected: &'static str,
+    },
+    #[error("Value {value:?} of column {column} in row {row} isn't of type {expected}")]
+    BatchValue {
+        row: usize,
+        column: String,
+        value: String,
+        expected: &'static str,
+    },
+    #[error("Failed to write output: {0}")]
+    Write(String),
+    #[error("Parallel reader failed: {0}")]
+    Parallel(String),
     #[error("Ruby error: {0}")]
     Ruby(String),
 }
@@ -56,6 +106,13 @@ impl From<ReaderError> for MagnusError {
             ReaderError::InvalidUtf8(utf8_err) => {
                 MagnusError::new(ruby.exception_encoding_error(), utf8_err.to_string())
             }
+            ReaderError::InvalidInput(message) => {
+                MagnusError::new(ruby.exception_type_error(), message)
+            }
+            ReaderError::UnknownColumn(_) | ReaderError::ColumnExists(_) => {
+                MagnusError::new(ruby.exception_arg_error(), err.to_string())
+            }
+            ReaderError::Write(_) => MagnusError::new(ruby.exception_io_error(), err.to_string()),
             _ => MagnusError::new(ruby.exception_runtime_error(), err.to_string()),
         }
     }
@@ -76,6 +133,27 @@ pub struct RecordReaderBuilder<'a, 'r, T: RecordParser<'a>> {
     trim: csv::Trim,
     ignore_null_bytes: bool,
     lossy: bool,
+    offset: usize,
+    limit: Option<usize>,
+    sample: Option<Sample>,
+    parallel: Option<ParallelOptions>,
+    in_memory: Option<&'a [u8]>,
+    buffer_size: usize,
+    fileno: bool,
+    glob: bool,
+    union_headers: bool,
+    source_column: Option<String>,
+    entry: Option<String>,
+    converters: Option<ColumnConverters>,
+    nil_values: Option<NilValues>,
+    missing_values: Option<MissingValues>,
+    filled_column: Option<String>,
+    extra_fields: ExtraFields,
+    duplicate_headers: DuplicateHeaders,
+    schema: Option<Schema>,
+    max_errors: Option<usize>,
+    row_filter: Option<RowFilter>,
+    unique: Option<Unique>,
     _phantom: PhantomData<T>,
     _phantom_a: PhantomData<&'a ()>,
 }
@@ -94,6 +172,27 @@ impl<'a, 'r, T: RecordParser<'a>> RecordReaderBuilder<'a, 'r, T> {
             trim: csv::Trim::None,
             ignore_null_bytes: false,
             lossy: false,
+            offset: 0,
+            limit: None,
+            sample: None,
+            parallel: None,
+            in_memory: None,
+            buffer_size: READ_BUFFER_SIZE,
+            fileno: false,
+            glob: false,
+            union_headers: false,
+            source_column: None,
+            entry: None,
+            converters: None,
+            nil_values: None,
+            missing_values: None,
+            filled_column: None,
+            extra_fields: ExtraFields::Drop,
+            duplicate_headers: DuplicateHeaders::Last,
+            schema: None,
+            max_errors: None,
+            row_filter: None,
+            unique: None,
             _phantom: PhantomData,
             _phantom_a: PhantomData,
         }
@@ -153,27 +252,237 @@ impl<'a, 'r, T: RecordParser<'a>> RecordReaderBuilder<'a, 'r, T> {
         self
     }
 
+    /// Sets the number of data rows to skip before any are returned.
+    #[must_use]
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the maximum number of rows to return. Reading stops once it is reached.
+    #[must_use]
+    pub fn limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Sets how rows are sampled after `offset` has been applied.
+    #[must_use]
+    pub fn sample(mut self, sample: Option<Sample>) -> Self {
+        self.sample = sample;
+        self
+    }
+
+    /// Reads local, uncompressed files on worker threads. Other inputs can't be split
+    /// into byte ranges, so they are still read on the calling thread.
+    #[must_use]
+    pub fn parallel(mut self, parallel: Option<ParallelOptions>) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Reads from `in_memory`, the contents of `to_read` already in memory (a
+    /// memory-mapped file or a pinned Ruby string), instead of opening `to_read`.
+    /// Fields are borrowed from it rather than copied. Ignored when the input is
+    /// read in parallel.
+    #[must_use]
+    pub fn in_memory(mut self, in_memory: Option<&'a [u8]>) -> Self {
+        self.in_memory = in_memory;
+        self
+    }
+
+    /// Sets the size in bytes of the buffers input is read through, and so the
+    /// largest chunk requested from an IO object's `read` at a time.
+    #[must_use]
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Reads `File` objects straight from their file descriptor rather than
+    /// through Ruby. Files with custom reading methods are still read through Ruby.
+    #[must_use]
+    pub fn fileno(mut self, fileno: bool) -> Self {
+        self.fileno = fileno;
+        self
+    }
+
+    /// Treats `to_read` as a pattern matching the files to read, in sorted order.
+    #[must_use]
+    pub fn glob(mut self, glob: bool) -> Self {
+        self.glob = glob;
+        self
+    }
+
+    /// When reading several files, merges their headers by name instead of
+    /// requiring every file to have the same headers as the first.
+    #[must_use]
+    pub fn union_headers(mut self, union_headers: bool) -> Self {
+        self.union_headers = union_headers;
+        self
+    }
+
+    /// When reading several files, adds a column with this name holding the path
+    /// of the file each row came from.
+    #[must_use]
+    pub fn source_column(mut self, source_column: Option<String>) -> Self {
+        self.source_column = source_column;
+        self
+    }
+
+    /// Selects the member to read from zip and tar archives, by name or glob pattern.
+    #[must_use]
+    pub fn entry(mut self, entry: Option<String>) -> Self {
+        self.entry = entry;
+        self
+    }
+
+    /// Sets the converters run over the values of each named column.
+    #[must_use]
+    pub fn converters(mut self, converters: Option<ColumnConverters>) -> Self {
+        self.converters = converters;
+        self
+    }
+
+    /// Sets the values read as nil when they appear unquoted, for every column and
+    /// for some columns in particular.
+    #[must_use]
+    pub fn nil_values(mut self, nil_values: Option<NilValues>) -> Self {
+        self.nil_values = nil_values;
+        self
+    }
+
+    /// Sets the values given to the fields missing from short rows in flexible mode,
+    /// for every column and for some columns in particular.
+    #[must_use]
+    pub fn missing_values(mut self, missing_values: Option<MissingValues>) -> Self {
+        self.missing_values = missing_values;
+        self
+    }
+
+    /// Adds a column with this name holding the names of the fields each row was
+    /// missing, and that were filled in with their default or nil.
+    #[must_use]
+    pub fn filled_column(mut self, filled_column: Option<String>) -> Self {
+        self.filled_column = filled_column;
+        self
+    }
+
+    /// Sets what happens to the fields of long rows past the last header, in
+    /// flexible mode.
+    #[must_use]
+    pub fn extra_fields(mut self, extra_fields: ExtraFields) -> Self {
+        self.extra_fields = extra_fields;
+        self
+    }
+
+    /// Sets how Hash rows deal with several columns having the same header.
+    #[must_use]
+    pub fn duplicate_headers(mut self, duplicate_headers: DuplicateHeaders) -> Self {
+        self.duplicate_headers = duplicate_headers;
+        self
+    }
+
+    /// Checks the header and every row against a schema. Rows breaking it are
+    /// skipped, and what was found is kept in a report. Values of columns with a
+    /// type are cast to it.
+    #[must_use]
+    pub fn schema(mut self, schema: Option<Schema>) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Caps the number of problems a validation report lists.
+    #[must_use]
+    pub fn max_errors(mut self, max_errors: Option<usize>) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Skips the rows that don't meet these conditions, before `offset`, `limit`
+    /// and sampling apply.
+    #[must_use]
+    pub fn row_filter(mut self, row_filter: Option<RowFilter>) -> Self {
+        self.row_filter = row_filter;
+        self
+    }
+
+    /// Skips the rows repeating the key of another, keeping the first or last of
+    /// each key, before `offset`, `limit` and sampling apply.
+    #[must_use]
+    pub fn unique(mut self, unique: Option<Unique>) -> Self {
+        self.unique = unique;
+        self
+    }
+
     /// Builds the RecordReader with the configured options.
     pub fn build(self) -> Result<RecordReader<'a, 'r, T>, ReaderError> {
-        let readable = RubyReader::try_from(self.to_read)?;
-
-        let flexible = self.flexible;
-        let reader = BufReader::with_capacity(READ_BUFFER_SIZE, readable);
+        let dialect = Dialect {
+            delimiter: self.delimiter,
+            quote_char: self.quote_char,
+            flexible: self.flexible,
+            trim: self.trim,
+            track_quotes: self.nil_values.is_some(),
+        };
 
-        let mut reader = csv::ReaderBuilder::new()
-            .has_headers(self.has_headers)
-            .delimiter(self.delimiter)
-            .quote(self.quote_char)
-            .flexible(flexible)
-            .trim(self.trim)
-            .from_reader(reader);
+        let (mut headers, source) = match (self.input_files()?, self.in_memory) {
+            (Some(paths), _) => {
+                let options = FileOptions {
+                    dialect,
+                    has_headers: self.has_headers,
+                    lossy: self.lossy,
+                    ignore_null_bytes: self.ignore_null_bytes,
+                    buffer_size: self.buffer_size,
+                    union_headers: self.union_headers,
+                    source_column: self.source_column.clone(),
+                    entry: self.entry.clone(),
+                };
+                let files = FileChain::new(self.ruby, paths, options)?;
+                (files.headers(), RecordSource::Files(files))
+            }
+            (None, Some(data)) if self.parallel.is_none() => {
+                let mut reader = self.csv_reader(&dialect, data);
+                let headers = self.read_headers(&mut reader)?;
+                let source = RecordSource::Memory {
+                    reader,
+                    batch: RecordBatch::new(self.lossy),
+                    data,
+                };
+                (headers, source)
+            }
+            _ => self.open_source(dialect)?,
+        };
 
-        let mut headers =
-            RecordReader::<T>::get_headers(self.ruby, &mut reader, self.has_headers, self.lossy)?;
+        // Suffixing renames headers, so this comes before anything looks columns up by name
+        let duplicates = self.duplicate_headers.resolve(&mut headers)?;
+        let types = self
+            .schema
+            .as_ref()
+            .and_then(|schema| schema.types(&headers));
+        let validation = self
+            .schema
+            .map(|schema| Validation::new(schema, &headers, self.max_errors));
 
-        if self.ignore_null_bytes {
-            headers = headers.iter().map(|h| h.replace("\0", "")).collect();
-        }
+        let converters = self
+            .converters
+            .as_ref()
+            .map(|converters| converters.resolve(&headers))
+            .transpose()?;
+        let filter = self
+            .row_filter
+            .as_ref()
+            .map(|row_filter| row_filter.resolve(&headers))
+            .transpose()?;
+        let unique = self
+            .unique
+            .as_ref()
+            .map(|unique| unique.resolve(&headers))
+            .transpose()?;
+        let nils = self
+            .nil_values
+            .as_ref()
+            .map(|nil_values| nil_values.resolve(&headers))
+            .transpose()?;
 
         let static_headers = if T::uses_headers() {
             StringCache::intern_many(&headers)?
@@ -192,13 +501,167 @@ impl<'a, 'r, T: RecordParser<'a>> RecordReaderBuilder<'a, 'r, T> {
             .transpose()?
             .map(Cow::Borrowed);
 
+        // Like the key extra fields are collected under, the filled column can't
+        // take the place of a header
+        if let Some(column) = self
+            .filled_column
+            .as_ref()
+            .filter(|column| headers.contains(column))
+        {
+            return Err(ReaderError::ColumnExists(column.clone()));
+        }
+
+        // Defaults are interned like the null string, so filling a field never copies.
+        // Rows reporting the fields they miss are padded to full width even without any
+        let defaults = self
+            .missing_values
+            .clone()
+            .or_else(|| {
+                self.filled_column
+                    .as_ref()
+                    .map(|_| MissingValues::default())
+            })
+            .map(|missing_values| {
+                missing_values.resolve(&headers, |value| {
+                    RString::new(value)
+                        .to_interned_str()
+                        .as_str()
+                        .map(Cow::Borrowed)
+                        .map_err(|e| ReaderError::InvalidFlexibleDefault(format!("{:?}", e)))
+                })
+            })
+            .transpose()?;
+
         Ok(RecordReader::new(
             self.ruby,
-            reader,
+            source,
             static_headers,
-            null_string,
-            self.ignore_null_bytes,
+            FieldValues {
+                null_string,
+                nils,
+                converters,
+                types,
+                defaults,
+                extras: self.extra_fields.resolve(&headers)?,
+                duplicates,
+                columns: headers.len(),
+                ignore_null_bytes: self.ignore_null_bytes,
+            },
             self.lossy,
-        ))
+            self.buffer_size.min(READ_BUFFER_SIZE),
+            RowSelection::new(self.offset, self.limit, self.sample),
+        )
+        .name_columns(headers)
+        .report_filled(self.filled_column)
+        .validate(validation)
+        .filter_rows(filter)
+        .deduplicate(unique))
+    }
+
+    /// Returns the paths to read when the input is made of several files: an Array
+    /// of paths, or the files matching a pattern when `glob` is set.
+    fn input_files(&self) -> Result<Option<Vec<String>>, ReaderError> {
+        if self.glob {
+            let pattern = RubyReader::path(self.to_read)?;
+            let dir: RClass = self.ruby.class_object().const_get("Dir")?;
+            let mut paths: Vec<String> = dir.funcall("glob", (pattern.as_str(),))?;
+            if paths.is_empty() {
+                return Err(ReaderError::InvalidInput(format!(
+                    "no files match {pattern}"
+                )));
+            }
+            paths.sort();
+            return Ok(Some(paths));
+        }
+
+        if InputKind::of(self.to_read)? != InputKind::Files {
+            return Ok(None);
+        }
+        let files = RArray::from_value(self.to_read)
+            .ok_or_else(|| ReaderError::InvalidInput("expected an Array of files".to_string()))?;
+        files
+            .to_vec::<Value>()?
+            .into_iter()
+            .map(|file| match InputKind::of(file)? {
+                InputKind::Path => RubyReader::path(file),
+                _ => Err(ReaderError::InvalidInput(format!(
+                    "expected every file to be a path (String or Pathname), got {}",
+                    unsafe { file.classname() }
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some)
+    }
+
+    /// Opens `to_read` and picks how its records are read: in parallel for local
+    /// files when requested, in batches without the GVL for other native inputs,
+    /// and record by record otherwise.
+    fn open_source(
+        &self,
+        dialect: Dialect,
+    ) -> Result<(Vec<String>, RecordSource<'a>), ReaderError> {
+        let readable = match self
+            .fileno
+            .then(|| RubyReader::from_file_descriptor(self.to_read))
+            .flatten()
+        {
+            Some(readable) => readable,
+            None => RubyReader::new(self.to_read, self.entry.as_deref(), self.buffer_size)?,
+        };
+        let native = readable.is_native();
+        let reader = BufReader::with_capacity(self.buffer_size, readable);
+        let mut reader = self.csv_reader(&dialect, reader);
+
+        let headers = self.read_headers(&mut reader)?;
+
+        let parallel = self.parallel.and_then(|options| {
+            RubyReader::local_file_path(&self.to_read).map(|path| (options, path))
+        });
+        let source = match parallel {
+            Some((options, path)) => {
+                let data_start = if self.has_headers {
+                    reader.position().byte()
+                } else {
+                    0
+                };
+                let format = ChunkFormat {
+                    dialect,
+                    lossy: self.lossy,
+                    expected_len: (!self.flexible).then_some(headers.len()),
+                };
+                RecordSource::Parallel(without_gvl(|| {
+                    ParallelReader::spawn(&path, data_start, options, format)
+                })?)
+            }
+            None if native => RecordSource::Native {
+                reader,
+                batch: RecordBatch::new(self.lossy),
+            },
+            None => RecordSource::Stream(reader),
+        };
+
+        Ok((headers, source))
+    }
+
+    /// Creates a csv reader over `input`. Its internal buffer matches
+    /// `buffer_size`, so reads are passed straight through to `input` at that size.
+    fn csv_reader<R: Read>(&self, dialect: &Dialect, input: R) -> csv::Reader<QuoteTracker<R>> {
+        let mut builder = dialect.reader_builder(self.has_headers);
+        builder.buffer_capacity(self.buffer_size);
+        builder.from_reader(dialect.tracker(input))
+    }
+
+    fn read_headers(
+        &self,
+        reader: &mut csv::Reader<QuoteTracker<impl Read>>,
+    ) -> Result<Vec<String>, ReaderError> {
+        let mut headers =
+            RecordReader::<T>::get_headers(self.ruby, reader, self.has_headers, self.lossy)?;
+
+        if self.ignore_null_bytes {
+            headers = headers.iter().map(|h| h.replace("\0", "")).collect();
+        }
+
+        Ok(headers)
     }
 }
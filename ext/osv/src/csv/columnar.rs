@@ -0,0 +1,468 @@
+use super::builder::ReaderError;
+use super::record::Field;
+use super::ruby_writer::RubyWriter;
+use super::transform::field_text;
+use super::types::{days_from_civil, parse_integer, parse_number, Cast, FieldType};
+use arrow_array::builder::{
+    BooleanBuilder, Date32Builder, Float64Builder, Int64Builder, StringBuilder,
+    TimestampMicrosecondBuilder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::sync::Arc;
+
+/// The type of a column written to Parquet or Arrow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    /// UTF-8 text. Values cast by a table schema are written as they display.
+    String,
+    /// A 64-bit signed integer.
+    Integer,
+    /// A 64-bit float. Integers are widened to it.
+    Float,
+    /// `true`, `True` or `TRUE`, or `false`, `False` or `FALSE`.
+    Boolean,
+    /// An ISO 8601 date, written as days since the Unix epoch.
+    Date,
+    /// An ISO 8601 date and time, written as microseconds since the Unix epoch in UTC.
+    Datetime,
+}
+
+/// The types tried, in order, when inferring the type of a column.
+pub(crate) const INFERRED: [ColumnType; 5] = [
+    ColumnType::Boolean,
+    ColumnType::Integer,
+    ColumnType::Float,
+    ColumnType::Date,
+    ColumnType::Datetime,
+];
+
+impl ColumnType {
+    /// Returns the type named `name`, as given to `types:`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "string" => Some(ColumnType::String),
+            "integer" => Some(ColumnType::Integer),
+            "float" => Some(ColumnType::Float),
+            "boolean" => Some(ColumnType::Boolean),
+            "date" => Some(ColumnType::Date),
+            "datetime" => Some(ColumnType::Datetime),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ColumnType::String => "string",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Date => "date",
+            ColumnType::Datetime => "datetime",
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnType::String => DataType::Utf8,
+            ColumnType::Integer => DataType::Int64,
+            ColumnType::Float => DataType::Float64,
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Date => DataType::Date32,
+            ColumnType::Datetime => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        }
+    }
+
+    /// Reads `field` as a value of this type, returning `None` if it isn't one.
+    /// Any field can be written as a string, so string columns never ask.
+    pub(crate) fn cast(self, field: &Field<'_>) -> Option<Cast> {
+        let text = match field {
+            Field::Str(text) => text.0.as_ref(),
+            Field::Cast(cast) => {
+                return match (self, *cast) {
+                    (ColumnType::Float, Cast::Integer(value)) => Some(Cast::Number(value as f64)),
+                    (ColumnType::Integer, Cast::Integer(_))
+                    | (ColumnType::Float, Cast::Number(_))
+                    | (ColumnType::Boolean, Cast::Boolean(_))
+                    | (ColumnType::Date, Cast::Date { .. })
+                    | (ColumnType::Datetime, Cast::Datetime { .. }) => Some(*cast),
+                    _ => None,
+                }
+            }
+        };
+        match self {
+            ColumnType::String => None,
+            ColumnType::Integer => parse_integer(text).map(Cast::Integer),
+            ColumnType::Float => parse_number(text).map(Cast::Number),
+            ColumnType::Boolean => match text {
+                "true" | "True" | "TRUE" => Some(Cast::Boolean(true)),
+                "false" | "False" | "FALSE" => Some(Cast::Boolean(false)),
+                _ => None,
+            },
+            ColumnType::Date => FieldType::Date.cast(text),
+            ColumnType::Datetime => FieldType::Datetime.cast(text),
+        }
+    }
+}
+
+/// Types declared per column name, as given to `types:`.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnTypes(pub Vec<(String, ColumnType)>);
+
+impl ColumnTypes {
+    /// Resolves the column names against the headers of the input, and infers
+    /// the type of the other columns from `rows`: the first of boolean, integer,
+    /// float, date and datetime that all their values are, or string.
+    ///
+    /// Nil and empty values say nothing of a column's type, and are written as null.
+    pub fn resolve(
+        &self,
+        headers: &[String],
+        rows: &[Vec<Option<Field<'_>>>],
+    ) -> Result<Vec<ColumnType>, ReaderError> {
+        let mut types = vec![None; headers.len()];
+        for (name, column_type) in &self.0 {
+            let mut found = false;
+            for (i, header) in headers.iter().enumerate() {
+                if header == name {
+                    types[i] = Some(*column_type);
+                    found = true;
+                }
+            }
+            if !found {
+                return Err(ReaderError::UnknownColumn(name.clone()));
+            }
+        }
+
+        Ok(types
+            .into_iter()
+            .enumerate()
+            .map(|(i, column_type)| column_type.unwrap_or_else(|| infer(rows, i)))
+            .collect())
+    }
+}
+
+fn infer(rows: &[Vec<Option<Field<'_>>>], column: usize) -> ColumnType {
+    let mut candidates = INFERRED.to_vec();
+    let mut seen = false;
+    for field in rows.iter().filter_map(|row| row.get(column)?.as_ref()) {
+        if is_empty(field) {
+            continue;
+        }
+        seen = true;
+        candidates.retain(|candidate| candidate.cast(field).is_some());
+        if candidates.is_empty() {
+            break;
+        }
+    }
+    match candidates.first() {
+        Some(&column_type) if seen => column_type,
+        _ => ColumnType::String,
+    }
+}
+
+#[inline]
+pub(crate) fn is_empty(field: &Field<'_>) -> bool {
+    matches!(field, Field::Str(text) if text.0.is_empty())
+}
+
+/// Gathers the values of a column until they are written as an array.
+enum ColumnBuilder {
+    String(StringBuilder),
+    Integer(Int64Builder),
+    Float(Float64Builder),
+    Boolean(BooleanBuilder),
+    Date(Date32Builder),
+    Datetime(TimestampMicrosecondBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(column_type: ColumnType, capacity: usize) -> Self {
+        match column_type {
+            ColumnType::String => ColumnBuilder::String(StringBuilder::new()),
+            ColumnType::Integer => ColumnBuilder::Integer(Int64Builder::with_capacity(capacity)),
+            ColumnType::Float => ColumnBuilder::Float(Float64Builder::with_capacity(capacity)),
+            ColumnType::Boolean => ColumnBuilder::Boolean(BooleanBuilder::with_capacity(capacity)),
+            ColumnType::Date => ColumnBuilder::Date(Date32Builder::with_capacity(capacity)),
+            ColumnType::Datetime => ColumnBuilder::Datetime(
+                TimestampMicrosecondBuilder::with_capacity(capacity).with_timezone("UTC"),
+            ),
+        }
+    }
+
+    fn column_type(&self) -> ColumnType {
+        match self {
+            ColumnBuilder::String(_) => ColumnType::String,
+            ColumnBuilder::Integer(_) => ColumnType::Integer,
+            ColumnBuilder::Float(_) => ColumnType::Float,
+            ColumnBuilder::Boolean(_) => ColumnType::Boolean,
+            ColumnBuilder::Date(_) => ColumnType::Date,
+            ColumnBuilder::Datetime(_) => ColumnType::Datetime,
+        }
+    }
+
+    /// Appends `field`, returning false if it isn't a value of the column's type.
+    #[inline]
+    fn append(&mut self, field: Option<&Field<'_>>) -> bool {
+        if let ColumnBuilder::String(builder) = self {
+            match field {
+                None => builder.append_null(),
+                Some(Field::Str(text)) => builder.append_value(&text.0),
+                Some(Field::Cast(cast)) => builder.append_value(cast.to_string()),
+            }
+            return true;
+        }
+
+        let cast = match field {
+            Some(field) if !is_empty(field) => match self.column_type().cast(field) {
+                Some(cast) => Some(cast),
+                None => return false,
+            },
+            _ => None,
+        };
+        match self {
+            ColumnBuilder::String(_) => {}
+            ColumnBuilder::Integer(builder) => builder.append_option(match cast {
+                Some(Cast::Integer(value)) => Some(value),
+                _ => None,
+            }),
+            ColumnBuilder::Float(builder) => builder.append_option(match cast {
+                Some(Cast::Number(value)) => Some(value),
+                _ => None,
+            }),
+            ColumnBuilder::Boolean(builder) => builder.append_option(match cast {
+                Some(Cast::Boolean(value)) => Some(value),
+                _ => None,
+            }),
+            ColumnBuilder::Date(builder) => builder.append_option(match cast {
+                Some(Cast::Date { year, month, day }) => {
+                    Some(days_from_civil(year, month, day) as i32)
+                }
+                _ => None,
+            }),
+            ColumnBuilder::Datetime(builder) => builder.append_option(match cast {
+                Some(Cast::Datetime { seconds, nanos, .. }) => {
+                    Some(seconds * 1_000_000 + i64::from(nanos / 1000))
+                }
+                _ => None,
+            }),
+        }
+        true
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::String(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Integer(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Float(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Boolean(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Date(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Datetime(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+/// The columnar file format written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnarFormat {
+    /// Parquet, with its column chunks compressed with `compression`.
+    Parquet { compression: ParquetCompression },
+    /// The Arrow IPC file format, also known as Feather version 2.
+    Arrow,
+}
+
+/// How the column chunks of a Parquet file are compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParquetCompression {
+    #[default]
+    Snappy,
+    Zstd,
+    None,
+}
+
+impl ParquetCompression {
+    /// Returns the compression named `name`, as given to `compression:`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "snappy" => Some(ParquetCompression::Snappy),
+            "zstd" => Some(ParquetCompression::Zstd),
+            "none" => Some(ParquetCompression::None),
+            _ => None,
+        }
+    }
+}
+
+/// Gathers rows into record batches of `batch_size` rows, typing each column
+/// as given.
+pub struct BatchBuilder {
+    schema: SchemaRef,
+    columns: Vec<String>,
+    builders: Vec<ColumnBuilder>,
+    batch_size: usize,
+    /// The rows of the batch being gathered.
+    rows: usize,
+    /// Every row pushed so far, to name the one whose value doesn't fit.
+    pushed: usize,
+}
+
+impl BatchBuilder {
+    pub fn new(columns: &[String], types: &[ColumnType], batch_size: usize) -> Self {
+        let schema = Arc::new(ArrowSchema::new(
+            columns
+                .iter()
+                .zip(types)
+                .map(|(name, column_type)| ArrowField::new(name, column_type.data_type(), true))
+                .collect::<Vec<_>>(),
+        ));
+
+        Self {
+            schema,
+            columns: columns.to_vec(),
+            builders: types
+                .iter()
+                .map(|&column_type| ColumnBuilder::new(column_type, batch_size))
+                .collect(),
+            batch_size,
+            rows: 0,
+            pushed: 0,
+        }
+    }
+
+    pub fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Adds a row, returning the record batch it completes once `batch_size`
+    /// rows are gathered. Fields past the last column are left out, and missing
+    /// ones are null. A value that isn't of its column's type fails, naming the
+    /// row, counting from 1, and the column.
+    pub fn push(&mut self, row: &[Option<Field<'_>>]) -> Result<Option<RecordBatch>, ReaderError> {
+        self.pushed += 1;
+        for (i, builder) in self.builders.iter_mut().enumerate() {
+            let field = row.get(i).and_then(Option::as_ref);
+            if !builder.append(field) {
+                return Err(ReaderError::BatchValue {
+                    row: self.pushed,
+                    column: self.columns[i].clone(),
+                    value: field_text(field).into_owned(),
+                    expected: builder.column_type().name(),
+                });
+            }
+        }
+        self.rows += 1;
+        if self.rows == self.batch_size {
+            return self.finish();
+        }
+        Ok(None)
+    }
+
+    /// Returns the record batch of the rows gathered so far, if there are any.
+    pub fn finish(&mut self) -> Result<Option<RecordBatch>, ReaderError> {
+        if self.rows == 0 {
+            return Ok(None);
+        }
+        let arrays = self
+            .builders
+            .iter_mut()
+            .map(ColumnBuilder::finish)
+            .collect();
+        self.rows = 0;
+        RecordBatch::try_new(self.schema.clone(), arrays)
+            .map(Some)
+            .map_err(|e| ReaderError::Write(e.to_string()))
+    }
+}
+
+enum Sink {
+    Parquet(ArrowWriter<RubyWriter>),
+    Arrow(FileWriter<RubyWriter>),
+}
+
+/// Writes rows to a Parquet or Arrow file, gathering `batch_size` of them into
+/// each record batch.
+pub struct ColumnarWriter {
+    batches: BatchBuilder,
+    sink: Sink,
+}
+
+impl ColumnarWriter {
+    pub fn new(
+        output: RubyWriter,
+        format: ColumnarFormat,
+        columns: &[String],
+        types: &[ColumnType],
+        batch_size: usize,
+    ) -> Result<Self, ReaderError> {
+        let batches = BatchBuilder::new(columns, types, batch_size);
+        let schema = batches.schema().clone();
+
+        let sink = match format {
+            ColumnarFormat::Parquet { compression } => {
+                let compression = match compression {
+                    ParquetCompression::Snappy => Compression::SNAPPY,
+                    ParquetCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+                    ParquetCompression::None => Compression::UNCOMPRESSED,
+                };
+                let properties = WriterProperties::builder()
+                    .set_compression(compression)
+                    .build();
+                Sink::Parquet(
+                    ArrowWriter::try_new(output, schema, Some(properties))
+                        .map_err(|e| ReaderError::Write(e.to_string()))?,
+                )
+            }
+            ColumnarFormat::Arrow => Sink::Arrow(
+                FileWriter::try_new(output, &schema)
+                    .map_err(|e| ReaderError::Write(e.to_string()))?,
+            ),
+        };
+
+        Ok(Self { batches, sink })
+    }
+
+    /// Adds a row, writing a record batch once `batch_size` rows are gathered.
+    pub fn push(&mut self, row: &[Option<Field<'_>>]) -> Result<(), ReaderError> {
+        match self.batches.push(row)? {
+            Some(batch) => self.write(&batch),
+            None => Ok(()),
+        }
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), ReaderError> {
+        match &mut self.sink {
+            Sink::Parquet(writer) => writer
+                .write(batch)
+                .map_err(|e| ReaderError::Write(e.to_string())),
+            Sink::Arrow(writer) => writer
+                .write(batch)
+                .map_err(|e| ReaderError::Write(e.to_string())),
+        }
+    }
+
+    /// Writes the rows left and the footer of the file.
+    pub fn finish(mut self) -> Result<(), ReaderError> {
+        if let Some(batch) = self.batches.finish()? {
+            self.write(&batch)?;
+        }
+        let output = match self.sink {
+            Sink::Parquet(writer) => writer
+                .into_inner()
+                .map_err(|e| ReaderError::Write(e.to_string()))?,
+            Sink::Arrow(mut writer) => {
+                writer
+                    .finish()
+                    .map_err(|e| ReaderError::Write(e.to_string()))?;
+                writer
+                    .into_inner()
+                    .map_err(|e| ReaderError::Write(e.to_string()))?
+            }
+        };
+        output
+            .finish()
+            .map_err(|e| ReaderError::Write(e.to_string()))
+    }
+}
@@ -0,0 +1,181 @@
+use super::builder::ReaderError;
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+
+/// A transformation applied to the value of a field before it is handed to Ruby.
+#[derive(Debug, Clone)]
+pub enum Converter {
+    /// Removes leading and trailing whitespace.
+    Strip,
+    /// Strips the value and collapses every run of whitespace inside it into a single space.
+    Squish,
+    /// Converts the value to lowercase.
+    Downcase,
+    /// Replaces every match of `regex` with `replacement`.
+    Gsub { regex: Regex, replacement: String },
+}
+
+/// The flags of a Ruby `Regexp`, which have no direct equivalent in its source.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RegexFlags {
+    pub ignore_case: bool,
+    pub extended: bool,
+    pub multiline: bool,
+}
+
+impl RegexFlags {
+    /// Builds a regex from the source of a Ruby `Regexp` with these flags.
+    pub fn build(self, pattern: &str) -> Result<Regex, String> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(self.ignore_case)
+            .ignore_whitespace(self.extended)
+            // Ruby's `m` flag lets `.` match newlines, while `^` and `$` always match
+            // at line boundaries
+            .dot_matches_new_line(self.multiline)
+            .multi_line(true)
+            .build()
+            .map_err(|e| format!("unsupported regular expression /{pattern}/: {e}"))
+    }
+}
+
+impl Converter {
+    /// Builds a `gsub` converter from the source and flags of a Ruby `Regexp` and a
+    /// Ruby replacement string, which may refer to groups as `\1` or `\k<name>`.
+    pub fn gsub(pattern: &str, flags: RegexFlags, replacement: &str) -> Result<Self, String> {
+        Ok(Converter::Gsub {
+            regex: flags.build(pattern)?,
+            replacement: ruby_replacement(replacement),
+        })
+    }
+
+    /// Builds a `gsub` converter replacing a literal string.
+    pub fn gsub_literal(pattern: &str, replacement: &str) -> Result<Self, String> {
+        Self::gsub(&regex::escape(pattern), RegexFlags::default(), replacement)
+    }
+
+    /// Applies the converter, borrowing from `value` whenever the result is a slice of it.
+    #[inline]
+    pub fn apply<'a>(&self, value: Cow<'a, str>) -> Cow<'a, str> {
+        match self {
+            Converter::Strip => match value {
+                Cow::Borrowed(value) => Cow::Borrowed(value.trim()),
+                Cow::Owned(value) if value.trim().len() == value.len() => Cow::Owned(value),
+                Cow::Owned(value) => Cow::Owned(value.trim().to_string()),
+            },
+            Converter::Squish => match Converter::Strip.apply(value) {
+                value if is_squished(&value) => value,
+                value => Cow::Owned(value.split_whitespace().collect::<Vec<_>>().join(" ")),
+            },
+            Converter::Downcase => {
+                if value.chars().any(char::is_uppercase) {
+                    Cow::Owned(value.to_lowercase())
+                } else {
+                    value
+                }
+            }
+            Converter::Gsub { regex, replacement } => {
+                match regex.replace_all(&value, replacement.as_str()) {
+                    Cow::Borrowed(_) => value,
+                    Cow::Owned(replaced) => Cow::Owned(replaced),
+                }
+            }
+        }
+    }
+}
+
+/// Converters declared per column name, as given to `converters:`.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnConverters(pub Vec<(String, Vec<Converter>)>);
+
+impl ColumnConverters {
+    /// Resolves the column names against the headers of the input.
+    pub(crate) fn resolve(&self, headers: &[String]) -> Result<FieldConverters, ReaderError> {
+        let mut columns = vec![Vec::new(); headers.len()];
+        for (name, converters) in &self.0 {
+            let mut found = false;
+            for (i, header) in headers.iter().enumerate() {
+                if header == name {
+                    columns[i].extend(converters.iter().cloned());
+                    found = true;
+                }
+            }
+            if !found {
+                return Err(ReaderError::UnknownColumn(name.clone()));
+            }
+        }
+        Ok(FieldConverters { columns })
+    }
+}
+
+/// The converters to apply to each field of a record, by position.
+pub(crate) struct FieldConverters {
+    columns: Vec<Vec<Converter>>,
+}
+
+impl FieldConverters {
+    /// Runs the converters of column `index` over `value`, in the order they were given.
+    #[inline]
+    pub(crate) fn convert<'a>(&self, index: usize, value: Cow<'a, str>) -> Cow<'a, str> {
+        match self.columns.get(index) {
+            Some(converters) => converters
+                .iter()
+                .fold(value, |value, converter| converter.apply(value)),
+            None => value,
+        }
+    }
+}
+
+/// Returns true if the only whitespace in `value` is single spaces between words.
+fn is_squished(value: &str) -> bool {
+    let mut after_space = false;
+    for c in value.chars() {
+        if c.is_whitespace() {
+            if c != ' ' || after_space {
+                return false;
+            }
+            after_space = true;
+        } else {
+            after_space = false;
+        }
+    }
+    true
+}
+
+/// Translates a Ruby replacement string to the syntax of the regex crate.
+fn ruby_replacement(replacement: &str) -> String {
+    let mut translated = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek().copied()) {
+            ('$', _) => translated.push_str("$$"),
+            ('\\', Some(digit @ '0'..='9')) => {
+                chars.next();
+                translated.push_str(&format!("${{{digit}}}"));
+            }
+            ('\\', Some('&')) => {
+                chars.next();
+                translated.push_str("${0}");
+            }
+            ('\\', Some('\\')) => {
+                chars.next();
+                translated.push('\\');
+            }
+            ('\\', Some('k')) => {
+                chars.next();
+                let rest: String = chars.clone().collect();
+                match rest.strip_prefix('<').and_then(|rest| rest.split_once('>')) {
+                    Some((name, _)) => {
+                        translated.push_str(&format!("${{{name}}}"));
+                        // Skip the `<name>` that was just consumed
+                        for _ in 0..name.chars().count() + 2 {
+                            chars.next();
+                        }
+                    }
+                    None => translated.push_str("\\k"),
+                }
+            }
+            _ => translated.push(c),
+        }
+    }
+    translated
+}
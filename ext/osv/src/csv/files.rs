@@ -0,0 +1,254 @@
+use super::batch::RecordBatch;
+use super::builder::ReaderError;
+use super::parallel::Dialect;
+use super::parser::CsvRecordType;
+use super::quoting::{QuoteTracker, QuotedFields};
+use super::record_reader::RecordReader;
+use super::ruby_reader::RubyReader;
+use magnus::Ruby;
+use std::io::BufReader;
+use std::vec;
+
+/// How the files of a multi-file read are opened and their headers reconciled.
+pub(crate) struct FileOptions {
+    pub dialect: Dialect,
+    pub has_headers: bool,
+    pub lossy: bool,
+    pub ignore_null_bytes: bool,
+    pub buffer_size: usize,
+    /// Merge the headers of all files by name instead of requiring them to match.
+    pub union_headers: bool,
+    /// Name of a column, added in front of the others, holding each row's file path.
+    pub source_column: Option<String>,
+    /// Member to read from files that are zip or tar archives.
+    pub entry: Option<String>,
+}
+
+type FileReader = csv::Reader<QuoteTracker<BufReader<RubyReader>>>;
+
+struct OpenFile {
+    reader: FileReader,
+    batch: RecordBatch,
+    path: String,
+    /// For each output column, the index of the matching field in this file, when
+    /// the file's columns differ from the output columns.
+    columns: Option<Vec<Option<usize>>>,
+}
+
+/// Reads several files one after another as a single stream of records.
+///
+/// Every record comes out laid out according to `headers`: either the header of
+/// the first file, which every other file must match, or the union of all the
+/// files' headers, in the order they first appear.
+pub(crate) struct FileChain {
+    options: FileOptions,
+    paths: vec::IntoIter<String>,
+    current: Option<OpenFile>,
+    headers: Vec<String>,
+    scratch: CsvRecordType,
+}
+
+impl FileChain {
+    pub(crate) fn new(
+        ruby: &Ruby,
+        paths: Vec<String>,
+        options: FileOptions,
+    ) -> Result<Self, ReaderError> {
+        let mut headers: Vec<String> = Vec::new();
+        if options.union_headers {
+            // Each file is only open while its header is read, and is opened again
+            // when its turn comes, so a long list of files never holds them all open
+            for path in &paths {
+                let (_, file_headers) = open_file(ruby, path, &options)?;
+                for header in file_headers {
+                    if !headers.contains(&header) {
+                        headers.push(header);
+                    }
+                }
+            }
+        }
+
+        let scratch = if options.lossy {
+            CsvRecordType::Byte(csv::ByteRecord::new())
+        } else {
+            CsvRecordType::String(csv::StringRecord::new())
+        };
+
+        let mut chain = Self {
+            options,
+            paths: paths.into_iter(),
+            current: None,
+            headers,
+            scratch,
+        };
+        // Opening the first file settles the headers when they must all match
+        chain.open_next(ruby)?;
+        Ok(chain)
+    }
+
+    /// The output headers, including the source column if there is one.
+    pub(crate) fn headers(&self) -> Vec<String> {
+        self.options
+            .source_column
+            .iter()
+            .chain(self.headers.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Opens the next file with a header row, returning false once there are none left.
+    fn open_next(&mut self, ruby: &Ruby) -> Result<bool, ReaderError> {
+        for path in self.paths.by_ref() {
+            let (reader, headers) = open_file(ruby, &path, &self.options)?;
+            // An empty file has no header to check and no records to read
+            if headers.is_empty() {
+                continue;
+            }
+
+            let columns = if self.options.union_headers {
+                (headers != self.headers).then(|| {
+                    self.headers
+                        .iter()
+                        .map(|name| headers.iter().position(|header| header == name))
+                        .collect()
+                })
+            } else if self.headers.is_empty() {
+                self.headers = headers;
+                None
+            } else if headers != self.headers {
+                return Err(ReaderError::HeaderMismatch {
+                    path,
+                    expected: self.headers.join(","),
+                    found: headers.join(","),
+                });
+            } else {
+                None
+            };
+
+            self.current = Some(OpenFile {
+                reader,
+                batch: RecordBatch::new(self.options.lossy),
+                path,
+                columns,
+            });
+            return Ok(true);
+        }
+        self.current = None;
+        Ok(false)
+    }
+
+    /// Reads the next record of the stream into `record`, moving on to the next
+    /// file whenever one runs out.
+    pub(crate) fn read_record(
+        &mut self,
+        ruby: &Ruby,
+        record: &mut CsvRecordType,
+        quoted: &mut QuotedFields,
+    ) -> Result<bool, ReaderError> {
+        loop {
+            let Some(file) = self.current.as_mut() else {
+                return Ok(false);
+            };
+            if file.batch.read_record(&mut file.reader, record, quoted)? {
+                break;
+            }
+            if !self.open_next(ruby)? {
+                return Ok(false);
+            }
+        }
+
+        let Some(file) = self.current.as_ref() else {
+            return Ok(false);
+        };
+        if file.columns.is_some() || self.options.source_column.is_some() {
+            let fields = record.len();
+            relayout(
+                record,
+                &mut self.scratch,
+                file.columns.as_deref(),
+                self.options
+                    .source_column
+                    .as_ref()
+                    .map(|_| file.path.as_str()),
+            );
+            // The source column is never quoted
+            let source = self.options.source_column.as_ref().map(|_| None);
+            *quoted = match file.columns.as_deref() {
+                Some(columns) => {
+                    let mut relaid =
+                        quoted.relayout(source.into_iter().chain(columns.iter().copied()));
+                    // A column missing from the file, or a field missing from a
+                    // short row, reads as nil
+                    let offset = usize::from(self.options.source_column.is_some());
+                    for (index, column) in columns.iter().enumerate() {
+                        if !column.is_some_and(|column| column < fields) {
+                            relaid.mark_absent(offset + index);
+                        }
+                    }
+                    relaid
+                }
+                None => quoted.relayout(source.into_iter().chain((0..fields).map(Some))),
+            };
+        }
+        Ok(true)
+    }
+}
+
+/// Opens `path` and reads its header row.
+fn open_file(
+    ruby: &Ruby,
+    path: &str,
+    options: &FileOptions,
+) -> Result<(FileReader, Vec<String>), ReaderError> {
+    let readable = RubyReader::open_path(path, options.entry.as_deref())?;
+    let reader = BufReader::with_capacity(options.buffer_size, readable);
+    let mut builder = options.dialect.reader_builder(options.has_headers);
+    builder.buffer_capacity(options.buffer_size);
+    let mut reader = builder.from_reader(options.dialect.tracker(reader));
+
+    let mut headers =
+        RecordReader::<()>::get_headers(ruby, &mut reader, options.has_headers, options.lossy)?;
+    if options.ignore_null_bytes {
+        headers = headers.iter().map(|h| h.replace("\0", "")).collect();
+    }
+    Ok((reader, headers))
+}
+
+/// Rewrites `record` in the output layout: the source path first, if any, then
+/// the fields picked out by `columns`. Columns a file doesn't have are left empty,
+/// to be marked absent in the record's [`QuotedFields`].
+fn relayout(
+    record: &mut CsvRecordType,
+    scratch: &mut CsvRecordType,
+    columns: Option<&[Option<usize>]>,
+    source: Option<&str>,
+) {
+    match (&mut *record, &mut *scratch) {
+        (CsvRecordType::String(record), CsvRecordType::String(scratch)) => {
+            scratch.clear();
+            if let Some(source) = source {
+                scratch.push_field(source);
+            }
+            match columns {
+                Some(columns) => columns.iter().for_each(|column| {
+                    scratch.push_field(column.and_then(|i| record.get(i)).unwrap_or(""))
+                }),
+                None => record.iter().for_each(|field| scratch.push_field(field)),
+            }
+        }
+        (CsvRecordType::Byte(record), CsvRecordType::Byte(scratch)) => {
+            scratch.clear();
+            if let Some(source) = source {
+                scratch.push_field(source.as_bytes());
+            }
+            match columns {
+                Some(columns) => columns.iter().for_each(|column| {
+                    scratch.push_field(column.and_then(|i| record.get(i)).unwrap_or(b""))
+                }),
+                None => record.iter().for_each(|field| scratch.push_field(field)),
+            }
+        }
+        _ => unreachable!("the scratch record has the same type as the records read"),
+    }
+    std::mem::swap(record, scratch);
+}
@@ -0,0 +1,81 @@
+use super::record::{CowStr, Field, RowValue};
+use super::types::Cast;
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+impl Serialize for CowStr<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl Serialize for Field<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Field::Str(value) => value.serialize(serializer),
+            Field::Cast(value) => value.serialize(serializer),
+        }
+    }
+}
+
+/// Numbers and booleans are written as JSON numbers and booleans, dates and
+/// times as ISO 8601 strings.
+impl Serialize for Cast {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Cast::Integer(value) => serializer.serialize_i64(value),
+            Cast::Number(value) => serializer.serialize_f64(value),
+            Cast::Boolean(value) => serializer.serialize_bool(value),
+            Cast::Date { .. } | Cast::Datetime { .. } => serializer.collect_str(self),
+        }
+    }
+}
+
+impl Serialize for RowValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            RowValue::Field(field) => field.serialize(serializer),
+            RowValue::Fields(fields) => fields.serialize(serializer),
+        }
+    }
+}
+
+/// A Hash row whose keys keep the order of the header, so objects are written
+/// with their keys in the order of the columns.
+#[derive(Debug)]
+pub struct OrderedRow<'a>(pub Vec<(&'static str, RowValue<'a>)>);
+
+/// A row written as a line of JSON, with the names of the fields it was missing
+/// under the filled column when `filled_column:` is set: as a key of objects, or
+/// as the last element of arrays.
+pub struct JsonLine<'r, R> {
+    pub row: &'r R,
+    pub filled: Option<(&'r str, &'r [String])>,
+}
+
+impl Serialize for JsonLine<'_, OrderedRow<'_>> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields = &self.row.0;
+        let mut map = serializer.serialize_map(Some(fields.len() + self.filled.iter().len()))?;
+        for (key, value) in fields {
+            map.serialize_entry(key, value)?;
+        }
+        if let Some((column, names)) = self.filled {
+            map.serialize_entry(column, names)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for JsonLine<'_, Vec<Option<Field<'_>>>> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields = self.row;
+        let mut seq = serializer.serialize_seq(Some(fields.len() + self.filled.iter().len()))?;
+        for field in fields {
+            seq.serialize_element(field)?;
+        }
+        if let Some((_, names)) = self.filled {
+            seq.serialize_element(names)?;
+        }
+        seq.end()
+    }
+}
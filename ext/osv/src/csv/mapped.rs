@@ -0,0 +1,84 @@
+use super::builder::ReaderError;
+use super::parser::CsvRecordType;
+use super::ruby_reader::RubyReader;
+use magnus::Value;
+use memmap2::Mmap;
+use std::fs::File;
+
+/// Maps `to_read` into memory when it is a plain, uncompressed local file.
+///
+/// Returns `None` for every other kind of input, which is then read as usual.
+pub fn map_input(to_read: Value) -> Result<Option<Mmap>, ReaderError> {
+    let Some(path) = RubyReader::local_file_path(&to_read) else {
+        return Ok(None);
+    };
+    let file = File::open(path)?;
+    // SAFETY: the mapping is only ever read. Truncating the file while it is being
+    // parsed makes reads past the new end fault, which is documented for `mmap: true`.
+    let mapping = unsafe { Mmap::map(&file)? };
+    Ok(Some(mapping))
+}
+
+/// Returns the bytes of `record` in the in-memory input, without its terminator, if
+/// every field appears there verbatim.
+///
+/// The csv reader only ever removes bytes from a record (quotes, escapes, trimmed
+/// whitespace), so a record whose raw length matches the length of its fields plus
+/// delimiters was copied unchanged.
+#[inline]
+pub(crate) fn verbatim_record<'a>(
+    data: &'a [u8],
+    record: &CsvRecordType,
+    end: u64,
+) -> Option<&'a [u8]> {
+    let (position, fields, field_bytes) = match record {
+        CsvRecordType::String(record) => (record.position(), record.len(), record.as_slice().len()),
+        CsvRecordType::Byte(record) => (record.position(), record.len(), record.as_slice().len()),
+    };
+    let start = usize::try_from(position?.byte()).ok()?;
+    let end = usize::try_from(end).ok()?;
+
+    // A record can't start with an unquoted line break, so any found here are
+    // blank lines or the rest of the previous record's `\r\n`.
+    let bytes = data.get(start..end)?;
+    let skipped = bytes
+        .iter()
+        .take_while(|&&b| b == b'\r' || b == b'\n')
+        .count();
+    let bytes = &bytes[skipped..];
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+
+    (bytes.len() == field_bytes + fields.saturating_sub(1)).then_some(bytes)
+}
+
+/// Hands out the fields of a verbatim record as slices of the in-memory input.
+pub(crate) struct VerbatimFields<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> VerbatimFields<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    /// Returns the next field as a string borrowed from the input, if it matches
+    /// `field`, the same field as parsed by the csv reader.
+    #[inline]
+    pub(crate) fn next_str(&mut self, field: &str) -> Option<&'a str> {
+        // SAFETY: the returned bytes are equal to `field`, which is valid UTF-8
+        self.next_bytes(field.as_bytes())
+            .map(|slice| unsafe { std::str::from_utf8_unchecked(slice) })
+    }
+
+    /// Returns the next field's bytes borrowed from the input, if they match `field`.
+    #[inline]
+    pub(crate) fn next_bytes(&mut self, field: &[u8]) -> Option<&'a [u8]> {
+        let start = self.offset;
+        let end = start + field.len();
+        // Skip the delimiter following the field
+        self.offset = end + 1;
+        self.bytes.get(start..end).filter(|slice| *slice == field)
+    }
+}
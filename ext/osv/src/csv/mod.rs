@@ -1,10 +1,54 @@
+mod aggregate;
+mod archive;
+mod arrow_batch;
+mod batch;
 mod builder;
+mod columnar;
+mod converters;
+mod files;
 mod header_cache;
+mod json;
+mod mapped;
+mod parallel;
 mod parser;
+mod pinned;
+mod profile;
+mod quoting;
 mod record;
 mod record_reader;
 mod ruby_reader;
+mod ruby_writer;
+mod schema;
+mod selection;
+mod table_schema;
+mod transform;
+mod types;
+mod unique;
+mod values;
 
+pub use aggregate::{Aggregate, Aggregates, Aggregator, GroupKey, GroupResult};
+pub use arrow_batch::ArrowBatch;
 pub use builder::RecordReaderBuilder;
+pub use columnar::{
+    BatchBuilder, ColumnType, ColumnTypes, ColumnarFormat, ColumnarWriter, ParquetCompression,
+};
+pub use converters::{ColumnConverters, Converter, RegexFlags};
+pub use json::{JsonLine, OrderedRow};
+pub use mapped::map_input;
+pub use parallel::ParallelOptions;
+pub use parser::RecordParser;
+pub use pinned::PinnedString;
+pub use profile::{Bound, ColumnStats, Profiler};
 pub use record::CowStr;
 pub use record::CsvRecord;
+pub use record::Field;
+pub use record::RowValue;
+pub(crate) use record_reader::READ_BUFFER_SIZE;
+pub use ruby_reader::InputKind;
+pub use ruby_writer::RubyWriter;
+pub use schema::{ColumnSchema, Constraints, Schema, ValidationReport};
+pub use selection::Sample;
+pub use table_schema::TableSchema;
+pub use transform::{field_text, ColumnSelection, Condition, RowFilter};
+pub use unique::{Keep, Unique};
+pub use values::{DuplicateHeaders, ExtraFields, MissingValues, NilValues};
@@ -0,0 +1,450 @@
+use super::builder::ReaderError;
+use super::parser::CsvRecordType;
+use super::quoting::{QuoteTracker, QuotedFields};
+use super::record_reader::READ_BUFFER_SIZE;
+use crate::gvl::without_gvl;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{sync_channel, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// Number of records a worker sends to the reading thread at a time.
+const BATCH_SIZE: usize = 1024;
+/// Number of batches a worker may get ahead of the reading thread, per chunk.
+const BATCHES_IN_FLIGHT: usize = 2;
+/// Bounds on the size of the byte range handed to a worker.
+const MIN_CHUNK_SIZE: u64 = 64 * 1024;
+const MAX_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// CSV dialect settings shared by every reader created for one input.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Dialect {
+    pub delimiter: u8,
+    pub quote_char: u8,
+    pub flexible: bool,
+    pub trim: csv::Trim,
+    /// Whether to keep track of which fields are quoted, for nil markers.
+    pub track_quotes: bool,
+}
+
+impl Dialect {
+    pub(crate) fn reader_builder(&self, has_headers: bool) -> csv::ReaderBuilder {
+        let mut builder = csv::ReaderBuilder::new();
+        builder
+            .has_headers(has_headers)
+            .delimiter(self.delimiter)
+            .quote(self.quote_char)
+            .flexible(self.flexible)
+            .trim(self.trim);
+        builder
+    }
+
+    /// Wraps `input` to track quoted fields if the dialect asks for it.
+    pub(crate) fn tracker<R>(&self, input: R) -> QuoteTracker<R> {
+        QuoteTracker::new(
+            input,
+            self.track_quotes
+                .then_some((self.delimiter, self.quote_char)),
+        )
+    }
+}
+
+/// Everything a worker needs to turn a byte range into records.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ChunkFormat {
+    pub dialect: Dialect,
+    pub lossy: bool,
+    /// Number of fields every record must have, unless the dialect is flexible.
+    pub expected_len: Option<usize>,
+}
+
+/// How a parallel read should be carried out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParallelOptions {
+    pub threads: usize,
+    pub ordered: bool,
+}
+
+type Batch = Result<Vec<(CsvRecordType, QuotedFields)>, ReaderError>;
+
+struct Job {
+    start: u64,
+    end: u64,
+    sender: SyncSender<Batch>,
+}
+
+enum Batches {
+    /// One channel per chunk, drained in chunk order.
+    Ordered(VecDeque<Receiver<Batch>>),
+    /// A single channel shared by every chunk, drained as batches arrive.
+    Unordered(Receiver<Batch>),
+}
+
+/// Reads a local file by splitting it into byte ranges that start on record
+/// boundaries and tokenizing each range on a worker thread.
+///
+/// Workers never touch Ruby. They hand back batches of raw records, which the
+/// reading thread turns into Ruby objects exactly as it would for a single reader.
+pub(crate) struct ParallelReader {
+    batches: Batches,
+    current: std::vec::IntoIter<(CsvRecordType, QuotedFields)>,
+    cancelled: Arc<AtomicBool>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ParallelReader {
+    /// Splits `path` from `data_start` to the end of the file and starts the workers.
+    pub(crate) fn spawn(
+        path: &Path,
+        data_start: u64,
+        options: ParallelOptions,
+        format: ChunkFormat,
+    ) -> io::Result<Self> {
+        let threads = options.threads.max(1);
+        let end = File::open(path)?.metadata()?.len();
+        let chunk_size = (end.saturating_sub(data_start) / (threads as u64 * 4))
+            .clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+        let boundaries = find_boundaries(
+            path,
+            data_start,
+            end,
+            chunk_size,
+            threads,
+            format.dialect.quote_char,
+        )?;
+
+        let mut jobs = VecDeque::with_capacity(boundaries.len());
+        let batches = if options.ordered {
+            let mut receivers = VecDeque::with_capacity(boundaries.len());
+            for range in boundaries.windows(2) {
+                let (sender, receiver) = sync_channel(BATCHES_IN_FLIGHT);
+                jobs.push_back(Job {
+                    start: range[0],
+                    end: range[1],
+                    sender,
+                });
+                receivers.push_back(receiver);
+            }
+            Batches::Ordered(receivers)
+        } else {
+            let (sender, receiver) = sync_channel(BATCHES_IN_FLIGHT * threads);
+            for range in boundaries.windows(2) {
+                jobs.push_back(Job {
+                    start: range[0],
+                    end: range[1],
+                    sender: sender.clone(),
+                });
+            }
+            Batches::Unordered(receiver)
+        };
+
+        let worker_count = threads.min(jobs.len());
+        let jobs = Arc::new(Mutex::new(jobs));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let workers = (0..worker_count)
+            .map(|_| {
+                let path = path.to_path_buf();
+                let jobs = Arc::clone(&jobs);
+                let cancelled = Arc::clone(&cancelled);
+                thread::spawn(move || run_worker(&path, &jobs, &cancelled, format))
+            })
+            .collect();
+
+        Ok(Self {
+            batches,
+            current: Vec::new().into_iter(),
+            cancelled,
+            workers,
+        })
+    }
+
+    /// Moves the next record into `record`, returning false once every chunk is exhausted.
+    pub(crate) fn read_record(
+        &mut self,
+        record: &mut CsvRecordType,
+        quoted: &mut QuotedFields,
+    ) -> Result<bool, ReaderError> {
+        loop {
+            if let Some((next, next_quoted)) = self.current.next() {
+                *record = next;
+                *quoted = next_quoted;
+                return Ok(true);
+            }
+            // Waiting on the workers never touches Ruby, so let other threads run meanwhile
+            match without_gvl(|| self.next_batch()) {
+                Some(batch) => self.current = batch?.into_iter(),
+                None => return Ok(false),
+            }
+        }
+    }
+
+    fn next_batch(&mut self) -> Option<Batch> {
+        match &mut self.batches {
+            Batches::Ordered(receivers) => {
+                while let Some(receiver) = receivers.front() {
+                    match receiver.recv() {
+                        Ok(batch) => return Some(batch),
+                        // The worker finished this chunk and dropped its sender
+                        Err(_) => {
+                            receivers.pop_front();
+                        }
+                    }
+                }
+                None
+            }
+            Batches::Unordered(receiver) => receiver.recv().ok(),
+        }
+    }
+}
+
+impl Drop for ParallelReader {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        // Dropping the receivers unblocks any worker waiting to send a batch
+        self.batches = Batches::Ordered(VecDeque::new());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run_worker(
+    path: &Path,
+    jobs: &Mutex<VecDeque<Job>>,
+    cancelled: &AtomicBool,
+    format: ChunkFormat,
+) {
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        let Some(job) = jobs.lock().ok().and_then(|mut jobs| jobs.pop_front()) else {
+            return;
+        };
+
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+            read_chunk(path, &job, cancelled, format)
+        }));
+        let keep_going = match outcome {
+            Ok(Ok(keep_going)) => keep_going,
+            Ok(Err(err)) => {
+                let _ = job.sender.send(Err(err));
+                false
+            }
+            Err(_) => {
+                let _ = job.sender.send(Err(ReaderError::Parallel(
+                    "worker thread panicked".to_string(),
+                )));
+                false
+            }
+        };
+        if !keep_going {
+            return;
+        }
+    }
+}
+
+/// Tokenizes one byte range, sending its records in batches.
+///
+/// Returns false if the reading thread has gone away and the worker should stop.
+fn read_chunk(
+    path: &Path,
+    job: &Job,
+    cancelled: &AtomicBool,
+    format: ChunkFormat,
+) -> Result<bool, ReaderError> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(job.start))?;
+    let input = BufReader::with_capacity(READ_BUFFER_SIZE, file.take(job.end - job.start));
+    let mut reader = format
+        .dialect
+        .reader_builder(false)
+        .from_reader(format.dialect.tracker(input));
+
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+
+        let record = match next_record(&mut reader, format) {
+            Ok(Some(record)) => record,
+            Ok(None) => break,
+            Err(err) => {
+                // Hand over the rows read before the error, as a single reader would
+                if !batch.is_empty() && job.sender.send(Ok(batch)).is_err() {
+                    return Ok(false);
+                }
+                return Err(err);
+            }
+        };
+
+        batch.push(record);
+        if batch.len() == BATCH_SIZE {
+            let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+            if job.sender.send(Ok(full)).is_err() {
+                return Ok(false);
+            }
+        }
+    }
+
+    if !batch.is_empty() && job.sender.send(Ok(batch)).is_err() {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+fn next_record(
+    reader: &mut csv::Reader<QuoteTracker<impl Read>>,
+    format: ChunkFormat,
+) -> Result<Option<(CsvRecordType, QuotedFields)>, ReaderError> {
+    let (has_record, record) = if format.lossy {
+        let mut record = csv::ByteRecord::new();
+        (
+            reader.read_byte_record(&mut record)?,
+            CsvRecordType::Byte(record),
+        )
+    } else {
+        let mut record = csv::StringRecord::new();
+        (
+            reader.read_record(&mut record)?,
+            CsvRecordType::String(record),
+        )
+    };
+    if !has_record {
+        return Ok(None);
+    }
+
+    // Each chunk's reader only sees its own records, so check against the header
+    // to catch a mismatch on the first record of a chunk.
+    if let Some(expected) = format.expected_len {
+        let found = match &record {
+            CsvRecordType::String(record) => record.len(),
+            CsvRecordType::Byte(record) => record.len(),
+        };
+        if found != expected {
+            return Err(ReaderError::UnequalLengths { expected, found });
+        }
+    }
+
+    let mut quoted = QuotedFields::default();
+    reader.get_mut().next_record(&mut quoted);
+    Ok(Some((record, quoted)))
+}
+
+/// Quote and newline positions found while scanning one slice of the file.
+struct SliceScan {
+    quotes: u64,
+    /// First offset following a newline with an even number of quotes before it in the slice.
+    first_even: Option<u64>,
+    /// First offset following a newline with an odd number of quotes before it in the slice.
+    first_odd: Option<u64>,
+}
+
+/// Splits `start..end` into byte ranges that each begin on a record boundary.
+///
+/// Slices of the file are scanned in parallel, counting quote characters and noting
+/// the first newline reached after an even and after an odd number of them. The quote
+/// counts of the preceding slices then tell us which of the two newlines is outside a
+/// quoted field. This is exact as long as quotes only surround fields or are doubled
+/// inside them, as RFC 4180 requires.
+fn find_boundaries(
+    path: &Path,
+    start: u64,
+    end: u64,
+    chunk_size: u64,
+    threads: usize,
+    quote: u8,
+) -> io::Result<Vec<u64>> {
+    let slices: Vec<(u64, u64)> = (start..end)
+        .step_by(chunk_size as usize)
+        .map(|slice_start| (slice_start, (slice_start + chunk_size).min(end)))
+        .collect();
+    let per_thread = slices.len().div_ceil(threads).max(1);
+
+    let scans = thread::scope(|scope| {
+        let handles: Vec<_> = slices
+            .chunks(per_thread)
+            .map(|group| {
+                scope.spawn(move || {
+                    group
+                        .iter()
+                        .map(|&(slice_start, slice_end)| {
+                            scan_slice(path, slice_start, slice_end, quote)
+                        })
+                        .collect::<io::Result<Vec<_>>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(io::Error::other("boundary scan thread panicked")))
+            })
+            .collect::<io::Result<Vec<_>>>()
+    })?;
+
+    let mut boundaries = vec![start];
+    let mut odd = false;
+    for (index, scan) in scans.iter().flatten().enumerate() {
+        if index > 0 {
+            let candidate = if odd { scan.first_odd } else { scan.first_even };
+            if let Some(boundary) = candidate {
+                if boundary > boundaries[boundaries.len() - 1] && boundary < end {
+                    boundaries.push(boundary);
+                }
+            }
+        }
+        odd ^= scan.quotes % 2 == 1;
+    }
+    boundaries.push(end.max(start));
+    Ok(boundaries)
+}
+
+fn scan_slice(path: &Path, start: u64, end: u64, quote: u8) -> io::Result<SliceScan> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut input = file.take(end - start);
+
+    let mut scan = SliceScan {
+        quotes: 0,
+        first_even: None,
+        first_odd: None,
+    };
+    let mut buffer = vec![0; READ_BUFFER_SIZE * 4];
+    let mut position = start;
+    loop {
+        let read = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        for &byte in &buffer[..read] {
+            position += 1;
+            if byte == quote {
+                scan.quotes += 1;
+            } else if byte == b'\n' {
+                let first = if scan.quotes % 2 == 0 {
+                    &mut scan.first_even
+                } else {
+                    &mut scan.first_odd
+                };
+                first.get_or_insert(position);
+            }
+        }
+    }
+    Ok(scan)
+}
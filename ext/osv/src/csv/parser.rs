@@ -1,16 +1,32 @@
 use super::builder::ReaderError;
 use super::header_cache::StringCacheKey;
-use super::CowStr;
+use super::json::OrderedRow;
+use super::mapped::VerbatimFields;
+use super::quoting::QuotedFields;
+use super::values::{FieldDuplicates, FieldExtras, FieldValues};
+use super::{CowStr, Field, RowValue};
 use magnus::Ruby;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::hash::BuildHasher;
 
+#[derive(Clone)]
 pub enum CsvRecordType {
     String(csv::StringRecord),
     Byte(csv::ByteRecord),
 }
 
+impl CsvRecordType {
+    /// The number of fields in the record.
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            CsvRecordType::String(record) => record.len(),
+            CsvRecordType::Byte(record) => record.len(),
+        }
+    }
+}
+
 pub trait RecordParser<'a> {
     type Output;
 
@@ -18,16 +34,15 @@ pub trait RecordParser<'a> {
         handle: &Ruby,
         headers: &[StringCacheKey],
         record: &CsvRecordType,
-        null_string: Option<Cow<'a, str>>,
-        ignore_null_bytes: bool,
+        quoted: &QuotedFields,
+        verbatim: Option<&'a [u8]>,
+        values: &FieldValues<'a>,
     ) -> Result<Self::Output, ReaderError>;
 
     fn uses_headers() -> bool;
 }
 
-impl<'a, S: BuildHasher + Default> RecordParser<'a>
-    for HashMap<&'static str, Option<CowStr<'a>>, S>
-{
+impl<'a, S: BuildHasher + Default> RecordParser<'a> for HashMap<&'static str, RowValue<'a>, S> {
     type Output = Self;
 
     #[inline]
@@ -40,41 +55,71 @@ impl<'a, S: BuildHasher + Default> RecordParser<'a>
         handle: &Ruby,
         headers: &[StringCacheKey],
         record: &CsvRecordType,
-        null_string: Option<Cow<'a, str>>,
-        ignore_null_bytes: bool,
+        quoted: &QuotedFields,
+        verbatim: Option<&'a [u8]>,
+        values: &FieldValues<'a>,
     ) -> Result<Self::Output, ReaderError> {
         let mut map = HashMap::with_capacity_and_hasher(headers.len(), S::default());
         let shared_empty = Cow::Borrowed("");
+        let mut verbatim = verbatim.map(VerbatimFields::new);
 
         for (i, header) in headers.iter().enumerate() {
             let value = match record {
-                CsvRecordType::String(s) => s.get(i).and_then(|field| {
-                    convert_field_to_cow_str(
-                        field,
-                        null_string.as_deref(),
-                        ignore_null_bytes,
-                        &shared_empty,
-                    )
-                }),
-                CsvRecordType::Byte(b) => b.get(i).and_then(|field| {
-                    let field = String::from_utf8_lossy(field);
-                    convert_field_to_cow_str(
-                        &field,
-                        null_string.as_deref(),
-                        ignore_null_bytes,
-                        &shared_empty,
-                    )
-                }),
+                CsvRecordType::String(s) => match s.get(i) {
+                    Some(field) => {
+                        let borrowed = verbatim.as_mut().and_then(|v| v.next_str(field));
+                        convert_field(i, field, borrowed, quoted, values, &shared_empty)
+                    }
+                    None => values.missing(i),
+                },
+                CsvRecordType::Byte(b) => match b.get(i) {
+                    Some(field) => {
+                        convert_byte_field(i, field, &mut verbatim, quoted, values, &shared_empty)
+                    }
+                    None => values.missing(i),
+                },
             };
 
-            map.insert(header.as_str(handle)?, value);
+            insert_field(&mut map, header.as_str(handle)?, i, value, values);
+        }
+
+        if record.len() > headers.len() {
+            match &values.extras {
+                FieldExtras::Drop => {}
+                FieldExtras::Raise => {
+                    return Err(ReaderError::ExtraFields {
+                        expected: headers.len(),
+                        found: record.len(),
+                    })
+                }
+                FieldExtras::Collect(key) => {
+                    let extras = (headers.len()..record.len())
+                        .map(|i| match record {
+                            CsvRecordType::String(s) => {
+                                let field = &s[i];
+                                let borrowed = verbatim.as_mut().and_then(|v| v.next_str(field));
+                                convert_field(i, field, borrowed, quoted, values, &shared_empty)
+                            }
+                            CsvRecordType::Byte(b) => convert_byte_field(
+                                i,
+                                &b[i],
+                                &mut verbatim,
+                                quoted,
+                                values,
+                                &shared_empty,
+                            ),
+                        })
+                        .collect();
+                    map.insert(key.as_str(handle)?, RowValue::Fields(extras));
+                }
+            }
         }
 
         Ok(map)
     }
 }
 
-impl<'a> RecordParser<'a> for Vec<Option<CowStr<'a>>> {
+impl<'a> RecordParser<'a> for Vec<Option<Field<'a>>> {
     type Output = Self;
 
     #[inline]
@@ -85,59 +130,204 @@ impl<'a> RecordParser<'a> for Vec<Option<CowStr<'a>>> {
     #[inline]
     fn parse(
         _handle: &Ruby,
-        headers: &[StringCacheKey],
+        _headers: &[StringCacheKey],
         record: &CsvRecordType,
-        null_string: Option<Cow<'a, str>>,
-        ignore_null_bytes: bool,
+        quoted: &QuotedFields,
+        verbatim: Option<&'a [u8]>,
+        values: &FieldValues<'a>,
     ) -> Result<Self::Output, ReaderError> {
-        let target_len = headers.len();
-        let mut vec = Vec::with_capacity(target_len);
+        if record.len() > values.columns && matches!(values.extras, FieldExtras::Raise) {
+            return Err(ReaderError::ExtraFields {
+                expected: values.columns,
+                found: record.len(),
+            });
+        }
+
+        let mut vec = Vec::with_capacity(values.columns);
         let shared_empty = Cow::Borrowed("");
+        let mut verbatim = verbatim.map(VerbatimFields::new);
 
         match record {
             CsvRecordType::String(record) => {
-                for field in record.iter() {
-                    let value = convert_field_to_cow_str(
-                        field,
-                        null_string.as_deref(),
-                        ignore_null_bytes,
-                        &shared_empty,
-                    );
+                for (i, field) in record.iter().enumerate() {
+                    let borrowed = verbatim.as_mut().and_then(|v| v.next_str(field));
+                    let value = convert_field(i, field, borrowed, quoted, values, &shared_empty);
                     vec.push(value);
                 }
             }
             CsvRecordType::Byte(record) => {
-                for field in record.iter() {
-                    let field = String::from_utf8_lossy(field);
-                    let value = convert_field_to_cow_str(
-                        &field,
-                        null_string.as_deref(),
-                        ignore_null_bytes,
-                        &shared_empty,
-                    );
+                for (i, field) in record.iter().enumerate() {
+                    let value =
+                        convert_byte_field(i, field, &mut verbatim, quoted, values, &shared_empty);
                     vec.push(value);
                 }
             }
         }
 
+        // Short records are padded to the full width when there are defaults
+        if let Some(defaults) = &values.defaults {
+            for i in vec.len()..values.columns {
+                vec.push(defaults.get(i).map(Field::Str));
+            }
+        }
+
         Ok(vec)
     }
 }
 
+impl<'a> RecordParser<'a> for OrderedRow<'a> {
+    type Output = Self;
+
+    #[inline]
+    fn uses_headers() -> bool {
+        true
+    }
+
+    #[inline]
+    fn parse(
+        handle: &Ruby,
+        headers: &[StringCacheKey],
+        record: &CsvRecordType,
+        quoted: &QuotedFields,
+        verbatim: Option<&'a [u8]>,
+        values: &FieldValues<'a>,
+    ) -> Result<Self::Output, ReaderError> {
+        let mut map = <HashMap<&'static str, RowValue<'a>, ahash::RandomState> as RecordParser<
+            'a,
+        >>::parse(handle, headers, record, quoted, verbatim, values)?;
+
+        // A repeated header was inserted once, at its first position
+        let mut fields = Vec::with_capacity(map.len());
+        for header in headers {
+            let key = header.as_str(handle)?;
+            if let Some(value) = map.remove(key) {
+                fields.push((key, value));
+            }
+        }
+        // What is left are the extra fields, collected under a key of their own
+        fields.extend(map);
+        Ok(OrderedRow(fields))
+    }
+}
+
+/// Parser used when only the number of records matters. Fields are never
+/// looked at, so counting creates no strings and no Ruby objects.
+impl<'a> RecordParser<'a> for () {
+    type Output = ();
+
+    #[inline]
+    fn uses_headers() -> bool {
+        false
+    }
+
+    #[inline]
+    fn parse(
+        _handle: &Ruby,
+        _headers: &[StringCacheKey],
+        record: &CsvRecordType,
+        _quoted: &QuotedFields,
+        _verbatim: Option<&'a [u8]>,
+        values: &FieldValues<'a>,
+    ) -> Result<Self::Output, ReaderError> {
+        // Nothing is converted, but long rows still raise when asked to
+        if record.len() > values.columns && matches!(values.extras, FieldExtras::Raise) {
+            return Err(ReaderError::ExtraFields {
+                expected: values.columns,
+                found: record.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Inserts the field `index` of a record under `key`, following how duplicate
+/// headers are handled.
 #[inline]
-fn convert_field_to_cow_str<'a>(
+fn insert_field<'a, S: BuildHasher>(
+    map: &mut HashMap<&'static str, RowValue<'a>, S>,
+    key: &'static str,
+    index: usize,
+    value: Option<Field<'a>>,
+    values: &FieldValues<'a>,
+) {
+    match &values.duplicates {
+        FieldDuplicates::Last => {
+            map.insert(key, RowValue::Field(value));
+        }
+        FieldDuplicates::First => {
+            map.entry(key).or_insert(RowValue::Field(value));
+        }
+        FieldDuplicates::Array(grouped) if grouped.get(index) == Some(&true) => {
+            let entry = map
+                .entry(key)
+                .or_insert_with(|| RowValue::Fields(Vec::new()));
+            if let RowValue::Fields(fields) = entry {
+                fields.push(value);
+            }
+        }
+        FieldDuplicates::Array(_) => {
+            map.insert(key, RowValue::Field(value));
+        }
+    }
+}
+
+/// Converts a field of a lossy record, borrowing it from the in-memory input when the
+/// field is valid UTF-8 and was found there verbatim.
+#[inline]
+fn convert_byte_field<'a>(
+    index: usize,
+    field: &[u8],
+    verbatim: &mut Option<VerbatimFields<'a>>,
+    quoted: &QuotedFields,
+    values: &FieldValues<'a>,
+    shared_empty: &Cow<'a, str>,
+) -> Option<Field<'a>> {
+    let borrowed = verbatim
+        .as_mut()
+        .and_then(|v| v.next_bytes(field))
+        .map(String::from_utf8_lossy);
+    match borrowed {
+        Some(Cow::Borrowed(field)) => {
+            convert_field(index, field, Some(field), quoted, values, shared_empty)
+        }
+        _ => convert_field(
+            index,
+            &String::from_utf8_lossy(field),
+            None,
+            quoted,
+            values,
+            shared_empty,
+        ),
+    }
+}
+
+/// Converts the field `index` of a record, using `borrowed` (the same text in the
+/// in-memory input) instead of copying the field when it is available, then runs
+/// the column's converters over it and casts it to the column's type.
+#[inline]
+fn convert_field<'a>(
+    index: usize,
     field: &str,
-    null_string: Option<&str>,
-    ignore_null_bytes: bool,
+    borrowed: Option<&'a str>,
+    quoted: &QuotedFields,
+    values: &FieldValues<'a>,
     shared_empty: &Cow<'a, str>,
-) -> Option<CowStr<'a>> {
-    if Some(field) == null_string {
-        None
+) -> Option<Field<'a>> {
+    let value = if values.is_nil(index, field, quoted) {
+        return None;
     } else if field.is_empty() {
-        Some(CowStr(shared_empty.clone()))
-    } else if ignore_null_bytes {
-        Some(CowStr(Cow::Owned(field.replace("\0", ""))))
+        shared_empty.clone()
+    } else if values.ignore_null_bytes && field.contains('\0') {
+        Cow::Owned(field.replace("\0", ""))
+    } else if let Some(borrowed) = borrowed {
+        Cow::Borrowed(borrowed)
     } else {
-        Some(CowStr(Cow::Owned(field.to_string())))
-    }
+        Cow::Owned(field.to_string())
+    };
+
+    let value = match &values.converters {
+        Some(converters) => converters.convert(index, value),
+        None => value,
+    };
+    Some(values.cast(index, CowStr(value)))
 }
@@ -0,0 +1,32 @@
+use magnus::{gc, RString};
+
+/// A frozen Ruby string whose bytes can be borrowed for as long as this value lives.
+///
+/// Freezing stops Ruby code from modifying the bytes, and registering the string
+/// with the GC keeps it alive and stops compaction from moving it.
+pub struct PinnedString {
+    string: Box<RString>,
+}
+
+impl PinnedString {
+    /// Pins a frozen version of `string`. Strings that aren't frozen yet share
+    /// their buffer with the frozen version rather than being copied, unless
+    /// they are short enough to be embedded in the object itself.
+    pub fn new(string: RString) -> Self {
+        let string = Box::new(RString::new_frozen(string));
+        gc::register_address(&*string);
+        Self { string }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: the string is frozen, so it can't be modified, and registered
+        // with the GC, so it can be neither collected nor moved while borrowed
+        unsafe { self.string.as_slice() }
+    }
+}
+
+impl Drop for PinnedString {
+    fn drop(&mut self) {
+        gc::unregister_address(&*self.string);
+    }
+}
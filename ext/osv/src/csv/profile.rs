@@ -0,0 +1,267 @@
+use super::columnar::{is_empty, ColumnType, INFERRED};
+use super::record::Field;
+use super::transform::field_text;
+use super::types::Cast;
+use ahash::RandomState;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+/// The statistics of a column, as returned by `OSV.profile`.
+#[derive(Debug, Clone)]
+pub struct ColumnStats {
+    pub name: String,
+    /// The type all of its values are, inferred like `OSV.to_parquet` infers it.
+    pub column_type: ColumnType,
+    /// Nil, missing and empty values.
+    pub nulls: usize,
+    /// The number of distinct values, estimated once there are more than the
+    /// distinct limit.
+    pub distinct: usize,
+    pub distinct_estimated: bool,
+    /// The fewest and most characters in a value.
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// The least and greatest values, compared as values of the column's type.
+    pub min: Option<Bound>,
+    pub max: Option<Bound>,
+    /// The most frequent values and how often they occur, most frequent first.
+    pub top: Vec<(String, usize)>,
+}
+
+/// The least or greatest value of a column: cast to the column's type, or its
+/// text for string columns.
+#[derive(Debug, Clone)]
+pub enum Bound {
+    Text(String),
+    Cast(Cast),
+}
+
+/// Gathers the statistics of every column of the rows pushed to it.
+pub struct Profiler {
+    columns: Vec<ColumnProfile>,
+    rows: usize,
+    top: usize,
+    distinct_limit: usize,
+}
+
+impl Profiler {
+    /// Creates a profiler keeping the `top` most frequent values of each column,
+    /// and counting distinct values exactly up to `distinct_limit` of them.
+    pub fn new(top: usize, distinct_limit: usize) -> Self {
+        Self {
+            columns: Vec::new(),
+            rows: 0,
+            top,
+            distinct_limit,
+        }
+    }
+
+    pub fn push(&mut self, row: &[Option<Field<'_>>]) {
+        if self.columns.len() < row.len() {
+            self.columns.resize_with(row.len(), ColumnProfile::default);
+        }
+        for (profile, field) in self.columns.iter_mut().zip(row) {
+            if let Some(field) = field.as_ref().filter(|field| !is_empty(field)) {
+                profile.observe(field, self.distinct_limit);
+            }
+        }
+        self.rows += 1;
+    }
+
+    /// Returns the number of rows pushed and the statistics of the columns named
+    /// `columns`. Columns no row had a value for are all nulls.
+    pub fn finish(mut self, columns: &[String]) -> (usize, Vec<ColumnStats>) {
+        self.columns
+            .resize_with(columns.len(), ColumnProfile::default);
+        let stats = self
+            .columns
+            .into_iter()
+            .zip(columns)
+            .map(|(profile, name)| profile.stats(name, self.rows, self.top))
+            .collect();
+        (self.rows, stats)
+    }
+}
+
+/// What is gathered of a column's values as they are seen.
+struct ColumnProfile {
+    values: usize,
+    /// How often each value occurs, for up to the distinct limit of them. Values
+    /// seen once the limit is reached aren't counted.
+    counts: HashMap<String, usize, RandomState>,
+    /// Every distinct value seen, once there are more than the limit.
+    estimate: Option<HyperLogLog>,
+    min_length: usize,
+    max_length: usize,
+    /// The types all values could still be, with the least and greatest value as
+    /// each of them.
+    candidates: Vec<(ColumnType, Option<(Cast, Cast)>)>,
+    text_bounds: Option<(String, String)>,
+}
+
+impl Default for ColumnProfile {
+    fn default() -> Self {
+        Self {
+            values: 0,
+            counts: HashMap::default(),
+            estimate: None,
+            min_length: usize::MAX,
+            max_length: 0,
+            candidates: INFERRED
+                .iter()
+                .map(|&column_type| (column_type, None))
+                .collect(),
+            text_bounds: None,
+        }
+    }
+}
+
+impl ColumnProfile {
+    fn observe(&mut self, field: &Field<'_>, distinct_limit: usize) {
+        self.values += 1;
+        let text = field_text(Some(field));
+
+        let length = text.chars().count();
+        self.min_length = self.min_length.min(length);
+        self.max_length = self.max_length.max(length);
+
+        if let Some(count) = self.counts.get_mut(text.as_ref()) {
+            *count += 1;
+        } else {
+            if self.counts.len() < distinct_limit {
+                self.counts.insert(text.to_string(), 1);
+            } else if self.estimate.is_none() {
+                let mut estimate = HyperLogLog::default();
+                self.counts.keys().for_each(|value| estimate.insert(value));
+                self.estimate = Some(estimate);
+            }
+            if let Some(estimate) = &mut self.estimate {
+                estimate.insert(&text);
+            }
+        }
+
+        self.candidates.retain_mut(|(column_type, bounds)| {
+            let Some(value) = column_type.cast(field) else {
+                return false;
+            };
+            match bounds {
+                Some((min, max)) => {
+                    if value.partial_cmp(min) == Some(Ordering::Less) {
+                        *min = value;
+                    } else if value.partial_cmp(max) == Some(Ordering::Greater) {
+                        *max = value;
+                    }
+                }
+                None => *bounds = Some((value, value)),
+            }
+            true
+        });
+
+        // Kept for every column, in case it turns out to be text
+        match &mut self.text_bounds {
+            Some((min, max)) => {
+                if text.as_ref() < min.as_str() {
+                    *min = text.into_owned();
+                } else if text.as_ref() > max.as_str() {
+                    *max = text.into_owned();
+                }
+            }
+            None => self.text_bounds = Some((text.to_string(), text.into_owned())),
+        }
+    }
+
+    fn stats(self, name: &str, rows: usize, top: usize) -> ColumnStats {
+        let (column_type, bounds) = match self.candidates.into_iter().next() {
+            Some((column_type, Some((min, max)))) => {
+                (column_type, Some((Bound::Cast(min), Bound::Cast(max))))
+            }
+            _ => (
+                ColumnType::String,
+                self.text_bounds
+                    .map(|(min, max)| (Bound::Text(min), Bound::Text(max))),
+            ),
+        };
+
+        let (distinct, distinct_estimated) = match &self.estimate {
+            Some(estimate) => (estimate.count(), true),
+            None => (self.counts.len(), false),
+        };
+
+        let mut counts: Vec<(String, usize)> = self.counts.into_iter().collect();
+        counts.sort_unstable_by(|(a, a_count), (b, b_count)| {
+            b_count.cmp(a_count).then_with(|| a.cmp(b))
+        });
+        counts.truncate(top);
+
+        let (min, max) = bounds.unzip();
+        ColumnStats {
+            name: name.to_string(),
+            column_type,
+            nulls: rows - self.values,
+            distinct,
+            distinct_estimated,
+            min_length: (self.values > 0).then_some(self.min_length),
+            max_length: (self.values > 0).then_some(self.max_length),
+            min,
+            max,
+            top: counts,
+        }
+    }
+}
+
+/// The number of bits of a value's hash picking its register.
+const PRECISION: u32 = 14;
+const REGISTERS: usize = 1 << PRECISION;
+
+/// Estimates the number of distinct values of a column with HyperLogLog, to
+/// within about 1% with its 16384 registers.
+struct HyperLogLog {
+    registers: Box<[u8]>,
+    hasher: RandomState,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; REGISTERS].into_boxed_slice(),
+            // Fixed seeds, so profiling the same input always gives the same estimate
+            hasher: RandomState::with_seeds(
+                0x243f_6a88_85a3_08d3,
+                0x1319_8a2e_0370_7344,
+                0xa409_3822_299f_31d0,
+                0x082e_fa98_ec4e_6c89,
+            ),
+        }
+    }
+}
+
+impl HyperLogLog {
+    fn insert(&mut self, value: &str) {
+        let hash = self.hasher.hash_one(value);
+        let register = (hash >> (64 - PRECISION)) as usize;
+        // The guard bit caps the rank when the remaining bits are all zero
+        let rest = (hash << PRECISION) | (1 << (PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[register] = self.registers[register].max(rank);
+    }
+
+    fn count(&self) -> usize {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-i32::from(rank)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        // Small counts are better estimated by the registers left empty
+        let empty = self.registers.iter().filter(|&&rank| rank == 0).count();
+        if estimate <= 2.5 * m && empty > 0 {
+            (m * (m / empty as f64).ln()).round() as usize
+        } else {
+            estimate.round() as usize
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+/// Which fields of a record were quoted in the input, and which weren't in the
+/// input at all.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct QuotedFields {
+    words: Vec<u64>,
+    /// Fields standing in for a column the input doesn't have, like a column a
+    /// file of a multi-file read lacks. They read as nil.
+    absent: Vec<u64>,
+}
+
+impl QuotedFields {
+    #[inline]
+    pub(crate) fn contains(&self, index: usize) -> bool {
+        has_bit(&self.words, index)
+    }
+
+    /// Whether the field `index` stands in for a column the input doesn't have.
+    #[inline]
+    pub(crate) fn is_absent(&self, index: usize) -> bool {
+        has_bit(&self.absent, index)
+    }
+
+    fn insert(&mut self, index: usize) {
+        set_bit(&mut self.words, index);
+    }
+
+    /// Marks the field `index` as standing in for a column the input doesn't have.
+    pub(crate) fn mark_absent(&mut self, index: usize) {
+        set_bit(&mut self.absent, index);
+    }
+
+    fn clear(&mut self) {
+        self.words.clear();
+        self.absent.clear();
+    }
+
+    /// Returns the flags of a record rearranged so that its field `i` is the field
+    /// `fields[i]` of this one, or an unquoted field for `None`.
+    pub(crate) fn relayout(&self, fields: impl Iterator<Item = Option<usize>>) -> Self {
+        let mut quoted = QuotedFields::default();
+        if self.words.is_empty() {
+            return quoted;
+        }
+        for (index, field) in fields.enumerate() {
+            if field.is_some_and(|field| self.contains(field)) {
+                quoted.insert(index);
+            }
+        }
+        quoted
+    }
+}
+
+#[inline]
+fn has_bit(words: &[u64], index: usize) -> bool {
+    words
+        .get(index / 64)
+        .is_some_and(|word| word & (1 << (index % 64)) != 0)
+}
+
+fn set_bit(words: &mut Vec<u64>, index: usize) {
+    let word = index / 64;
+    if words.len() <= word {
+        words.resize(word + 1, 0);
+    }
+    words[word] |= 1 << (index % 64);
+}
+
+/// States of the csv parser that matter for knowing where fields start and
+/// whether they are quoted, following the csv-core state machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    StartRecord,
+    StartField,
+    InField,
+    InQuotedField,
+    /// After a quote inside a quoted field, which either escapes a quote or ends the quoting.
+    QuoteInQuotedField,
+    /// After the `\r` ending a record, which may be followed by a `\n`.
+    Cr,
+}
+
+struct Tracking {
+    delimiter: u8,
+    quote: u8,
+    state: State,
+    field: usize,
+    current: QuotedFields,
+    records: VecDeque<QuotedFields>,
+    spare: Vec<QuotedFields>,
+}
+
+impl Tracking {
+    fn scan(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.step(byte);
+        }
+    }
+
+    #[inline]
+    fn step(&mut self, byte: u8) {
+        let is_term = byte == b'\r' || byte == b'\n';
+        self.state = match self.state {
+            State::StartRecord if is_term => State::StartRecord,
+            State::StartRecord => {
+                self.field = 0;
+                return self.start_field(byte);
+            }
+            State::StartField => return self.start_field(byte),
+            State::InField | State::QuoteInQuotedField if byte == self.delimiter => {
+                self.field += 1;
+                State::StartField
+            }
+            State::InField | State::QuoteInQuotedField if is_term => self.end_record(byte),
+            State::QuoteInQuotedField if byte == self.quote => State::InQuotedField,
+            State::InField | State::QuoteInQuotedField => State::InField,
+            State::InQuotedField if byte == self.quote => State::QuoteInQuotedField,
+            State::InQuotedField => State::InQuotedField,
+            State::Cr if byte == b'\n' => State::StartRecord,
+            State::Cr => {
+                self.state = State::StartRecord;
+                return self.step(byte);
+            }
+        };
+    }
+
+    #[inline]
+    fn start_field(&mut self, byte: u8) {
+        self.state = if byte == self.quote {
+            self.current.insert(self.field);
+            State::InQuotedField
+        } else if byte == self.delimiter {
+            self.field += 1;
+            State::StartField
+        } else if byte == b'\r' || byte == b'\n' {
+            self.end_record(byte)
+        } else {
+            State::InField
+        };
+    }
+
+    fn end_record(&mut self, byte: u8) -> State {
+        let next = self.spare.pop().unwrap_or_default();
+        self.records
+            .push_back(std::mem::replace(&mut self.current, next));
+        if byte == b'\r' {
+            State::Cr
+        } else {
+            State::StartRecord
+        }
+    }
+
+    /// Ends the last record when the input doesn't end with a line break.
+    fn finish(&mut self) {
+        if !matches!(self.state, State::StartRecord | State::Cr) {
+            self.end_record(b'\n');
+        }
+        self.state = State::StartRecord;
+    }
+}
+
+/// Wraps the input of a csv reader and follows its bytes through the same state
+/// machine as the csv parser, to record which fields of each record were quoted.
+///
+/// The csv reader drops the quotes around fields, so this is the only way to tell
+/// a quoted value, such as `""`, from the same value unquoted. Tracking is off
+/// unless enabled, in which case reads are passed straight through.
+pub(crate) struct QuoteTracker<R> {
+    inner: R,
+    tracking: Option<Box<Tracking>>,
+}
+
+impl<R> QuoteTracker<R> {
+    pub(crate) fn new(inner: R, quotes: Option<(u8, u8)>) -> Self {
+        Self {
+            inner,
+            tracking: quotes.map(|(delimiter, quote)| {
+                Box::new(Tracking {
+                    delimiter,
+                    quote,
+                    state: State::StartRecord,
+                    field: 0,
+                    current: QuotedFields::default(),
+                    records: VecDeque::new(),
+                    spare: Vec::new(),
+                })
+            }),
+        }
+    }
+
+    /// Moves the quoted fields of the next record read by the csv reader into `quoted`.
+    ///
+    /// The csv reader reads ahead, so records are handed out in the order they were
+    /// scanned, one for every record the csv reader returns.
+    #[inline]
+    pub(crate) fn next_record(&mut self, quoted: &mut QuotedFields) {
+        let Some(tracking) = self.tracking.as_mut() else {
+            return;
+        };
+        if let Some(next) = tracking.records.pop_front() {
+            let mut previous = std::mem::replace(quoted, next);
+            previous.clear();
+            tracking.spare.push(previous);
+        }
+    }
+}
+
+impl<R: Read> Read for QuoteTracker<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if let Some(tracking) = self.tracking.as_mut() {
+            if read == 0 {
+                tracking.finish();
+            } else {
+                tracking.scan(&buf[..read]);
+            }
+        }
+        Ok(read)
+    }
+}
@@ -1,11 +1,12 @@
+use super::types::Cast;
 use itertools::Itertools;
 use magnus::{value::ReprValue, IntoValue, Ruby, Value};
 use std::{borrow::Cow, collections::HashMap, hash::BuildHasher};
 
 #[derive(Debug)]
 pub enum CsvRecord<'a, S: BuildHasher + Default> {
-    Vec(Vec<Option<CowStr<'a>>>),
-    Map(HashMap<&'static str, Option<CowStr<'a>>, S>),
+    Vec(Vec<Option<Field<'a>>>),
+    Map(HashMap<&'static str, RowValue<'a>, S>),
 }
 
 impl<S: BuildHasher + Default> IntoValue for CsvRecord<'_, S> {
@@ -43,6 +44,42 @@ impl<S: BuildHasher + Default> IntoValue for CsvRecord<'_, S> {
     }
 }
 
+/// The value under a key of a Hash row: a single field, or several fields
+/// gathered under the same key.
+#[derive(Debug, Clone)]
+pub enum RowValue<'a> {
+    Field(Option<Field<'a>>),
+    Fields(Vec<Option<Field<'a>>>),
+}
+
+impl IntoValue for RowValue<'_> {
+    #[inline]
+    fn into_value_with(self, handle: &Ruby) -> Value {
+        match self {
+            RowValue::Field(field) => field.into_value_with(handle),
+            RowValue::Fields(fields) => handle.ary_from_iter(fields).into_value_with(handle),
+        }
+    }
+}
+
+/// A field handed to Ruby: its text, or the value the type of its column cast
+/// it to.
+#[derive(Debug, Clone)]
+pub enum Field<'a> {
+    Str(CowStr<'a>),
+    Cast(Cast),
+}
+
+impl IntoValue for Field<'_> {
+    #[inline]
+    fn into_value_with(self, handle: &Ruby) -> Value {
+        match self {
+            Field::Str(value) => value.into_value_with(handle),
+            Field::Cast(value) => value.into_value_with(handle),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CowStr<'a>(pub Cow<'a, str>);
 
@@ -1,25 +1,72 @@
+use super::batch::RecordBatch;
 use super::builder::ReaderError;
+use super::files::FileChain;
 use super::header_cache::StringCacheKey;
+use super::mapped::verbatim_record;
+use super::parallel::ParallelReader;
 use super::parser::{CsvRecordType, RecordParser};
+use super::quoting::{QuoteTracker, QuotedFields};
 use super::ruby_reader::RubyReader;
+use super::schema::{Validation, ValidationReport};
+use super::selection::{Admission, RowSelection};
+use super::transform::FieldFilter;
+use super::unique::Dedup;
+use super::values::FieldValues;
 use magnus::{Error, Ruby};
-use std::borrow::Cow;
 use std::io::{BufReader, Read};
 
-/// Size of the internal buffer used for reading CSV records
+/// Default size of the buffers used for reading CSV records
 pub(crate) const READ_BUFFER_SIZE: usize = 16384;
 
+/// Where raw records are read from.
+pub(crate) enum RecordSource<'a> {
+    /// A csv reader over a Ruby object, which can only be read while holding the GVL.
+    Stream(csv::Reader<QuoteTracker<BufReader<RubyReader>>>),
+    /// A csv reader over a native file, read in batches with the GVL released.
+    Native {
+        reader: csv::Reader<QuoteTracker<BufReader<RubyReader>>>,
+        batch: RecordBatch,
+    },
+    /// Byte ranges of a local file tokenized on worker threads.
+    Parallel(ParallelReader),
+    /// Several files read one after another, each in batches like a native input.
+    Files(FileChain),
+    /// A csv reader over input already in memory, such as a memory-mapped file.
+    /// Fields are borrowed from `data` whenever they appear in it verbatim.
+    Memory {
+        reader: csv::Reader<QuoteTracker<&'a [u8]>>,
+        batch: RecordBatch,
+        data: &'a [u8],
+    },
+}
+
 /// A reader that processes CSV records using a specified parser.
 ///
 /// This struct implements Iterator to provide a streaming interface for CSV records.
 pub struct RecordReader<'a, 'r, T: RecordParser<'a>> {
     handle: &'r Ruby,
-    reader: csv::Reader<BufReader<RubyReader>>,
+    source: RecordSource<'a>,
     headers: Vec<StringCacheKey>,
-    null_string: Option<Cow<'a, str>>,
+    values: FieldValues<'a>,
     string_record: CsvRecordType,
+    /// Which fields of `string_record` were quoted, when nil markers need to know.
+    quoted: QuotedFields,
     parser: std::marker::PhantomData<T>,
-    ignore_null_bytes: bool,
+    selection: RowSelection,
+    /// The names of the columns, once duplicate headers are resolved.
+    columns: Vec<String>,
+    filled: Option<FilledFields>,
+    validation: Option<Validation>,
+    filter: Option<FieldFilter>,
+    unique: Option<Dedup>,
+}
+
+/// Keeps track of the fields missing from the last record, to report them under
+/// a column of their own.
+struct FilledFields {
+    column: String,
+    /// Index of the first field the last record was missing.
+    from: usize,
 }
 
 impl<'a, 'r, T: RecordParser<'a>> RecordReader<'a, 'r, T> {
@@ -35,7 +82,7 @@ impl<'a, 'r, T: RecordParser<'a>> RecordReader<'a, 'r, T> {
     #[inline]
     pub(crate) fn get_headers(
         ruby: &Ruby,
-        reader: &mut csv::Reader<impl Read>,
+        reader: &mut csv::Reader<QuoteTracker<impl Read>>,
         has_headers: bool,
         lossy: bool,
     ) -> Result<Vec<String>, Error> {
@@ -69,58 +116,241 @@ impl<'a, 'r, T: RecordParser<'a>> RecordReader<'a, 'r, T> {
             }
         };
 
+        // The header row's quoted fields are of no use, unlike a first row that is
+        // kept as a record, which the csv reader will hand out again
+        if has_headers {
+            reader.get_mut().next_record(&mut QuotedFields::default());
+        }
         Ok(headers)
     }
 
     /// Creates a new RecordReader instance.
     pub(crate) fn new(
         handle: &'r Ruby,
-        reader: csv::Reader<BufReader<RubyReader>>,
+        source: RecordSource<'a>,
         headers: Vec<StringCacheKey>,
-        null_string: Option<Cow<'a, str>>,
-        ignore_null_bytes: bool,
+        values: FieldValues<'a>,
         lossy: bool,
+        record_capacity: usize,
+        selection: RowSelection,
     ) -> Self {
         let headers_len = headers.len();
         Self {
             handle,
-            reader,
+            source,
             headers,
-            null_string,
+            values,
             string_record: if lossy {
-                CsvRecordType::Byte(csv::ByteRecord::with_capacity(
-                    READ_BUFFER_SIZE,
-                    headers_len,
-                ))
+                CsvRecordType::Byte(csv::ByteRecord::with_capacity(record_capacity, headers_len))
             } else {
                 CsvRecordType::String(csv::StringRecord::with_capacity(
-                    READ_BUFFER_SIZE,
+                    record_capacity,
                     headers_len,
                 ))
             },
+            quoted: QuotedFields::default(),
             parser: std::marker::PhantomData,
-            ignore_null_bytes,
+            selection,
+            columns: Vec::new(),
+            filled: None,
+            validation: None,
+            filter: None,
+            unique: None,
         }
     }
 
+    /// Checks rows against a schema, skipping the ones that break it.
+    pub(crate) fn validate(mut self, validation: Option<Validation>) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Returns what checking against the schema found so far, if there is one.
+    pub fn validation_report(&self) -> Option<&ValidationReport> {
+        self.validation.as_ref().map(Validation::report)
+    }
+
+    /// Skips the rows that don't meet the conditions of `where:`.
+    pub(crate) fn filter_rows(mut self, filter: Option<FieldFilter>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Skips the rows repeating the key of another, as `unique_by:` asks.
+    pub(crate) fn deduplicate(mut self, unique: Option<Dedup>) -> Self {
+        self.unique = unique;
+        self
+    }
+
+    /// Returns the number of repeated rows skipped so far, when deduplicating.
+    pub fn duplicates(&self) -> Option<usize> {
+        self.unique.as_ref().map(Dedup::duplicates)
+    }
+
+    /// Sets the names of the columns, once duplicate headers are resolved.
+    pub(crate) fn name_columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Returns the names of the columns, after duplicate headers were resolved.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Reports the fields each record is missing under `column`.
+    pub(crate) fn report_filled(mut self, column: Option<String>) -> Self {
+        self.filled = column.map(|column| FilledFields { column, from: 0 });
+        self
+    }
+
+    /// Returns the name of the column reporting missing fields and the names of the
+    /// fields the last record returned was missing, if that column was requested.
+    pub fn filled_fields(&self) -> Option<(&str, &[String])> {
+        self.filled.as_ref().map(|filled| {
+            let from = filled.from.min(self.columns.len());
+            (filled.column.as_str(), &self.columns[from..])
+        })
+    }
+
     /// Attempts to read the next record, returning any errors encountered.
+    ///
+    /// Rows excluded by `offset`, `limit` or sampling are tokenized but never
+    /// handed to the parser, and reading stops as soon as `limit` is reached.
     fn try_next(&mut self) -> Result<Option<T::Output>, ReaderError> {
-        let record = match self.string_record {
-            CsvRecordType::String(ref mut record) => self.reader.read_record(record),
-            CsvRecordType::Byte(ref mut record) => self.reader.read_byte_record(record),
+        loop {
+            if self.selection.is_exhausted() {
+                return Ok(None);
+            }
+
+            if let Some((record, quoted)) = self.selection.next_reserved() {
+                self.note_width(record.len());
+                return self.parse_record(&record, &quoted, None).map(Some);
+            }
+
+            // Rows held back to keep the last of each key come back once the input is read
+            let replaying = self.unique.as_ref().is_some_and(Dedup::is_replaying);
+            let read = if replaying {
+                self.unique
+                    .as_mut()
+                    .is_some_and(|unique| unique.replay(&mut self.string_record, &mut self.quoted))
+            } else {
+                self.read_record()?
+            };
+
+            if !read {
+                if self.unique.as_mut().is_some_and(Dedup::release) {
+                    continue;
+                }
+                if self.selection.finish() {
+                    continue;
+                }
+                return Ok(None);
+            }
+
+            if !replaying {
+                // Rows are checked before `where`, deduplication or selection
+                // leave any out, and rows breaking the schema are still returned
+                if let Some(validation) = self.validation.as_mut() {
+                    validation.check(&self.string_record, &self.quoted, &self.values);
+                }
+
+                if let Some(filter) = &self.filter {
+                    if !filter.admits(&self.string_record, &self.quoted, &self.values) {
+                        continue;
+                    }
+                }
+
+                if let Some(unique) = self.unique.as_mut() {
+                    if unique.holds_rows() {
+                        unique.hold(&self.string_record, &self.quoted, &self.values);
+                        continue;
+                    }
+                    if !unique.admits(&self.string_record, &self.quoted, &self.values) {
+                        continue;
+                    }
+                }
+            }
+
+            if self.selection.admit(&self.string_record, &self.quoted) == Admission::Yield {
+                self.note_width(self.string_record.len());
+                // Rows held back were read long ago, so they are no longer borrowed
+                let verbatim = if replaying { None } else { self.verbatim() };
+                return self
+                    .parse_record(&self.string_record, &self.quoted, verbatim)
+                    .map(Some);
+            }
+        }
+    }
+
+    /// Reads the next raw record, and which of its fields were quoted, into the
+    /// reusable record buffers.
+    #[inline]
+    fn read_record(&mut self) -> Result<bool, ReaderError> {
+        let record = &mut self.string_record;
+        let quoted = &mut self.quoted;
+        let reader = match self.source {
+            RecordSource::Stream(ref mut reader) => reader,
+            RecordSource::Native {
+                ref mut reader,
+                ref mut batch,
+            } => return batch.read_record(reader, record, quoted),
+            RecordSource::Parallel(ref mut reader) => return reader.read_record(record, quoted),
+            RecordSource::Files(ref mut files) => {
+                return files.read_record(self.handle, record, quoted)
+            }
+            RecordSource::Memory {
+                ref mut reader,
+                ref mut batch,
+                ..
+            } => return batch.read_record(reader, record, quoted),
+        };
+        let has_record = match record {
+            CsvRecordType::String(record) => reader.read_record(record),
+            CsvRecordType::Byte(record) => reader.read_byte_record(record),
         }?;
-        if record {
-            Ok(Some(T::parse(
-                self.handle,
-                &self.headers,
-                &self.string_record,
-                self.null_string.clone(),
-                self.ignore_null_bytes,
-            )?))
-        } else {
-            Ok(None)
+        if has_record {
+            reader.get_mut().next_record(quoted);
+        }
+        Ok(has_record)
+    }
+
+    /// Remembers the number of fields of the record about to be returned.
+    #[inline]
+    fn note_width(&mut self, width: usize) {
+        if let Some(filled) = self.filled.as_mut() {
+            filled.from = width;
         }
     }
+
+    /// Returns the raw bytes of the record just read, if it came from input held
+    /// in memory and can be borrowed from it.
+    #[inline]
+    fn verbatim(&self) -> Option<&'a [u8]> {
+        match self.source {
+            RecordSource::Memory {
+                ref batch, data, ..
+            } => verbatim_record(data, &self.string_record, batch.last_end()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn parse_record(
+        &self,
+        record: &CsvRecordType,
+        quoted: &QuotedFields,
+        verbatim: Option<&'a [u8]>,
+    ) -> Result<T::Output, ReaderError> {
+        T::parse(
+            self.handle,
+            &self.headers,
+            record,
+            quoted,
+            verbatim,
+            &self.values,
+        )
+    }
 }
 
 impl<'a, T: RecordParser<'a>> Iterator for RecordReader<'a, '_, T> {
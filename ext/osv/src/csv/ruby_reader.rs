@@ -1,24 +1,157 @@
 use flate2::bufread::GzDecoder;
 use magnus::{
+    gc,
     value::{Opaque, ReprValue},
     RString, Ruby, Value,
 };
 use std::{
     fs::File,
     io::{self, BufReader, Read, Write},
+    path::PathBuf,
 };
 
-use super::{builder::ReaderError, record_reader::READ_BUFFER_SIZE};
+use super::{
+    archive::{open_member, ArchiveKind},
+    builder::ReaderError,
+    record_reader::READ_BUFFER_SIZE,
+};
+
+/// How an input passed to one of the reading methods is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    /// A file to open, given as a String or an object with `to_path` (such as `Pathname`).
+    Path,
+    /// An IO, or any other object responding to `read`, to read from.
+    Io,
+    /// An Array of file paths, read one after another as a single input.
+    Files,
+}
+
+impl InputKind {
+    /// Works out whether `value` names a file or is something to read from.
+    ///
+    /// `File` and `Pathname` both respond to `to_path` and `read`, so IOs are told
+    /// apart by class. Anything that is neither a path nor readable is rejected
+    /// rather than guessed at.
+    pub fn of(value: Value) -> Result<Self, ReaderError> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        if value.is_kind_of(ruby.class_string()) {
+            Ok(InputKind::Path)
+        } else if value.is_kind_of(ruby.class_io()) {
+            Ok(InputKind::Io)
+        } else if value.is_kind_of(ruby.class_array()) {
+            Ok(InputKind::Files)
+        } else if value.respond_to("to_path", false).unwrap_or(false) {
+            Ok(InputKind::Path)
+        } else if value.respond_to("read", false).unwrap_or(false) {
+            Ok(InputKind::Io)
+        } else {
+            Err(ReaderError::InvalidInput(format!(
+                "expected a file path (String or Pathname), an Array of file paths or an IO \
+                 responding to `read`, got {}. \
+                 Use OSV.parse to parse CSV content held in a String",
+                unsafe { value.classname() }
+            )))
+        }
+    }
+}
+
+/// A Ruby String handed to every read from an IO as its output buffer, so reads
+/// don't allocate a new String each time.
+pub struct ReadBuffer {
+    string: Box<RString>,
+}
+
+impl ReadBuffer {
+    fn new(capacity: usize) -> Self {
+        let string = Box::new(RString::buf_new(capacity));
+        // The reader lives on the heap, out of sight of the GC
+        gc::register_address(&*string);
+        Self { string }
+    }
+
+    fn get(&self) -> RString {
+        *self.string
+    }
+}
+
+impl Drop for ReadBuffer {
+    fn drop(&mut self) {
+        gc::unregister_address(&*self.string);
+    }
+}
+
+/// How bytes are requested from an IO-like object.
+pub enum IoRead {
+    /// `readpartial(len, buffer)`, for IOs whose reading methods are Ruby's own
+    /// and which don't convert encodings. Returns whatever is available instead
+    /// of waiting for `len` bytes.
+    Partial(ReadBuffer),
+    /// `read(len, buffer)`, for objects whose `read` takes an output buffer.
+    Buffered(ReadBuffer),
+    /// `read(len)`, for `read` methods that take a length only.
+    Plain,
+}
+
+impl IoRead {
+    /// Picks how to read from `io`, with reads of `buffer_size` bytes.
+    fn for_io(io: Value, buffer_size: usize) -> Self {
+        if IoRead::uses_builtin_reads(io) && !IoRead::converts_encoding(io) {
+            return IoRead::Partial(ReadBuffer::new(buffer_size));
+        }
+        let arity = io
+            .funcall::<_, _, Value>("method", ("read",))
+            .and_then(|method| method.funcall::<_, _, i64>("arity", ()));
+        match arity {
+            Ok(arity) if arity < 0 || arity >= 2 => IoRead::Buffered(ReadBuffer::new(buffer_size)),
+            _ => IoRead::Plain,
+        }
+    }
+
+    /// Whether `io` is an IO whose `read` and `readpartial` are the ones defined
+    /// by `IO`, so neither is a custom method that must be called as written.
+    fn uses_builtin_reads(io: Value) -> bool {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        io.is_kind_of(ruby.class_io())
+            && ["read", "readpartial"].iter().all(|name| {
+                io.funcall::<_, _, Value>("method", (*name,))
+                    .and_then(|method| method.funcall::<_, _, Value>("owner", ()))
+                    .and_then(|owner| owner.equal(ruby.class_io()))
+                    .unwrap_or(false)
+            })
+    }
+
+    /// Whether `io` transcodes what it reads to an internal encoding. Such an IO
+    /// buffers characters rather than bytes, and `readpartial` raises on it once
+    /// it holds any, while `read` with a length still returns the raw bytes.
+    fn converts_encoding(io: Value) -> bool {
+        !matches!(
+            io.funcall::<_, _, Option<Value>>("internal_encoding", ()),
+            Ok(None)
+        )
+    }
+
+    fn read(&self, io: Value, len: usize) -> Result<Option<RString>, magnus::Error> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        match self {
+            IoRead::Partial(buffer) => {
+                match io.funcall::<_, _, Option<RString>>("readpartial", (len, buffer.get())) {
+                    Err(e) if e.is_kind_of(ruby.exception_eof_error()) => Ok(None),
+                    result => result,
+                }
+            }
+            IoRead::Buffered(buffer) => io.funcall("read", (len, buffer.get())),
+            IoRead::Plain => io.funcall("read", (len,)),
+        }
+    }
+}
 
-/// A reader that can handle various Ruby input types (String, StringIO, IO-like objects)
+/// A reader that can handle the Ruby input types (file paths and IO-like objects)
 /// and provide a standard Read implementation for them.
 pub enum RubyReader {
-    String {
-        inner: Opaque<RString>,
-        offset: usize,
-    },
     RubyIoLike {
         inner: Opaque<Value>,
+        method: IoRead,
     },
     NativeProxyIoLike {
         proxy_file: Box<dyn Read>,
@@ -26,43 +159,123 @@ pub enum RubyReader {
 }
 
 impl RubyReader {
-    fn is_io_like(value: &Value) -> bool {
-        value.respond_to("read", false).unwrap_or(false)
+    /// Whether reading never calls back into Ruby, so it can happen without the GVL.
+    pub(crate) fn is_native(&self) -> bool {
+        matches!(self, RubyReader::NativeProxyIoLike { .. })
     }
-}
 
-impl TryFrom<Value> for RubyReader {
-    type Error = ReaderError;
+    /// Reads a `File` straight from a duplicate of its file descriptor, without
+    /// going through Ruby at all.
+    ///
+    /// Only regular files whose reading methods are Ruby's own qualify, so a
+    /// custom `read` is never bypassed, and a read without the GVL never blocks on
+    /// a FIFO or device that Ruby couldn't interrupt. Reading starts at the file's
+    /// current position. Returns `None` for anything else, which is then read
+    /// through Ruby.
+    #[cfg(unix)]
+    pub(crate) fn from_file_descriptor(value: Value) -> Option<Self> {
+        use magnus::RFile;
+        use std::io::{Seek, SeekFrom};
+        use std::os::fd::{AsRawFd, BorrowedFd};
 
-    fn try_from(value: Value) -> Result<Self, Self::Error> {
         let ruby = unsafe { Ruby::get_unchecked() };
-        if RubyReader::is_io_like(&value) {
-            Ok(RubyReader::RubyIoLike {
+        if !value.is_kind_of(ruby.class_file()) || !IoRead::uses_builtin_reads(value) {
+            return None;
+        }
+        let fd = RFile::from_value(value)?.as_raw_fd();
+        if fd < 0 {
+            return None;
+        }
+        // Ruby may have buffered data past its position, so start from `pos`
+        // rather than from wherever the descriptor's offset happens to be
+        let position = value.funcall::<_, _, u64>("pos", ()).ok()?;
+        let fd = unsafe { BorrowedFd::borrow_raw(fd) }
+            .try_clone_to_owned()
+            .ok()?;
+        let mut file = File::from(fd);
+        if !file.metadata().ok()?.is_file() {
+            return None;
+        }
+        file.seek(SeekFrom::Start(position)).ok()?;
+        Some(RubyReader::NativeProxyIoLike {
+            proxy_file: Box::new(file),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn from_file_descriptor(_value: Value) -> Option<Self> {
+        None
+    }
+
+    /// Returns the path named by a path input.
+    pub(crate) fn path(value: Value) -> Result<String, ReaderError> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let path = if value.is_kind_of(ruby.class_string()) {
+            value.to_r_string()?
+        } else {
+            value.funcall::<_, _, RString>("to_path", ())?
+        };
+        Ok(path.to_string()?)
+    }
+
+    /// Opens `value`, which must be a path or an IO-like object. `entry` picks the
+    /// member to read when the path is a zip or tar archive, and an IO is read
+    /// `buffer_size` bytes at a time.
+    pub(crate) fn new(
+        value: Value,
+        entry: Option<&str>,
+        buffer_size: usize,
+    ) -> Result<Self, ReaderError> {
+        match InputKind::of(value)? {
+            InputKind::Io => Ok(RubyReader::RubyIoLike {
                 inner: Opaque::from(value),
-            })
-        } else if value.is_kind_of(ruby.class_string()) {
-            let ruby_string = value.to_r_string()?;
-            let file_path = ruby_string.to_string()?;
-            let file = File::open(&file_path)?;
-
-            let x: Box<dyn Read> = if file_path.ends_with(".gz") {
-                let decoder = GzDecoder::new(BufReader::with_capacity(READ_BUFFER_SIZE, file));
-                Box::new(decoder)
-            } else {
-                Box::new(file)
-            };
-
-            Ok(RubyReader::NativeProxyIoLike { proxy_file: x })
+                method: IoRead::for_io(value, buffer_size),
+            }),
+            InputKind::Path => RubyReader::open_path(&RubyReader::path(value)?, entry),
+            InputKind::Files => Err(ReaderError::InvalidInput(
+                "a list of files can't be read as a single input".to_string(),
+            )),
+        }
+    }
+
+    /// Opens the file at `file_path`, decompressing it if it is gzipped, or
+    /// streaming the member selected by `entry` if it is an archive.
+    pub(crate) fn open_path(file_path: &str, entry: Option<&str>) -> Result<Self, ReaderError> {
+        if let Some(kind) = ArchiveKind::of(file_path) {
+            return Ok(RubyReader::NativeProxyIoLike {
+                proxy_file: open_member(file_path, kind, entry)?,
+            });
+        }
+
+        let file = File::open(file_path)?;
+
+        let x: Box<dyn Read> = if file_path.ends_with(".gz") {
+            let decoder = GzDecoder::new(BufReader::with_capacity(READ_BUFFER_SIZE, file));
+            Box::new(decoder)
         } else {
-            // Try calling `to_str`, and if that fails, try `to_s`
-            let string_content = value
-                .funcall::<_, _, RString>("to_str", ())
-                .or_else(|_| value.funcall::<_, _, RString>("to_s", ()))?;
-            Ok(RubyReader::String {
-                inner: Opaque::from(string_content),
-                offset: 0,
-            })
+            Box::new(file)
+        };
+
+        Ok(RubyReader::NativeProxyIoLike { proxy_file: x })
+    }
+
+    /// Returns the path of a plain, uncompressed local file, the only kind of
+    /// input that can be split into byte ranges or memory-mapped.
+    pub(crate) fn local_file_path(value: &Value) -> Option<PathBuf> {
+        if InputKind::of(*value).ok()? != InputKind::Path {
+            return None;
         }
+        let file_path = RubyReader::path(*value).ok()?;
+        (!file_path.ends_with(".gz") && ArchiveKind::of(&file_path).is_none())
+            .then(|| PathBuf::from(file_path))
+    }
+}
+
+impl TryFrom<Value> for RubyReader {
+    type Error = ReaderError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        RubyReader::new(value, None, READ_BUFFER_SIZE)
     }
 }
 
@@ -71,27 +284,11 @@ impl Read for RubyReader {
         let ruby = unsafe { Ruby::get_unchecked() };
         match self {
             RubyReader::NativeProxyIoLike { proxy_file } => proxy_file.read(buf),
-            RubyReader::String { inner, offset } => {
-                let unwrapped_inner = ruby.get_inner(*inner);
-
-                let string_buffer = unsafe { unwrapped_inner.as_slice() };
-                if *offset >= string_buffer.len() {
-                    return Ok(0); // EOF
-                }
-
-                let remaining = string_buffer.len() - *offset;
-                let copy_size = remaining.min(buf.len());
-                buf[..copy_size].copy_from_slice(&string_buffer[*offset..*offset + copy_size]);
-
-                *offset += copy_size;
-
-                Ok(copy_size)
-            }
-            RubyReader::RubyIoLike { inner } => {
+            RubyReader::RubyIoLike { inner, method } => {
                 let unwrapped_inner = ruby.get_inner(*inner);
 
-                let bytes = unwrapped_inner
-                    .funcall::<_, _, Option<RString>>("read", (buf.len(),))
+                let bytes = method
+                    .read(unwrapped_inner, buf.len())
                     .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
 
                 match bytes {
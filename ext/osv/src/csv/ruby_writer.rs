@@ -0,0 +1,86 @@
+use flate2::{write::GzEncoder, Compression};
+use magnus::{
+    value::{Opaque, ReprValue},
+    Ruby, Value,
+};
+use std::{
+    fs::File,
+    io::{self, Write},
+};
+
+use super::{builder::ReaderError, ruby_reader::RubyReader};
+
+/// A writer for the Ruby outputs converted records are written to: a file path,
+/// or an IO-like object responding to `write`.
+pub enum RubyWriter {
+    RubyIoLike {
+        inner: Opaque<Value>,
+    },
+    File(File),
+    /// A file whose path ends in `.gz`, compressed as it is written.
+    GzipFile(GzEncoder<File>),
+}
+
+impl RubyWriter {
+    /// Creates (or truncates) the file named by `value`, or writes to `value`
+    /// itself when it is an IO.
+    pub fn new(value: Value) -> Result<Self, ReaderError> {
+        let ruby = unsafe { Ruby::get_unchecked() };
+        let is_path = value.is_kind_of(ruby.class_string())
+            || (!value.is_kind_of(ruby.class_io())
+                && value.respond_to("to_path", false).unwrap_or(false));
+
+        if is_path {
+            let path = RubyReader::path(value)?;
+            let file = File::create(&path)?;
+            Ok(if path.ends_with(".gz") {
+                RubyWriter::GzipFile(GzEncoder::new(file, Compression::default()))
+            } else {
+                RubyWriter::File(file)
+            })
+        } else if value.respond_to("write", false).unwrap_or(false) {
+            Ok(RubyWriter::RubyIoLike {
+                inner: Opaque::from(value),
+            })
+        } else {
+            Err(ReaderError::InvalidInput(format!(
+                "expected a file path (String or Pathname) or an IO responding to `write`, got {}",
+                unsafe { value.classname() }
+            )))
+        }
+    }
+
+    /// Writes whatever is left, like the end of a gzip stream. IOs are flushed
+    /// but left open for the caller to close.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            RubyWriter::RubyIoLike { .. } => Ok(()),
+            RubyWriter::File(mut file) => file.flush(),
+            RubyWriter::GzipFile(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for RubyWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            RubyWriter::File(file) => file.write(buf),
+            RubyWriter::GzipFile(encoder) => encoder.write(buf),
+            RubyWriter::RubyIoLike { inner } => {
+                let ruby = unsafe { Ruby::get_unchecked() };
+                ruby.get_inner(*inner)
+                    .funcall::<_, _, Value>("write", (ruby.str_from_slice(buf),))
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            RubyWriter::File(file) => file.flush(),
+            RubyWriter::GzipFile(encoder) => encoder.flush(),
+            RubyWriter::RubyIoLike { .. } => Ok(()),
+        }
+    }
+}
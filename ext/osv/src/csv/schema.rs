@@ -0,0 +1,326 @@
+use super::parser::CsvRecordType;
+use super::quoting::QuotedFields;
+use super::types::{Cast, FieldType, FieldTypes};
+use super::values::FieldValues;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+/// The columns a CSV input is expected to have, and the constraints on their values.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    pub columns: Vec<ColumnSchema>,
+    /// Whether the columns of the schema must appear in the header in the same order.
+    pub ordered: bool,
+    /// Whether the header may only have the columns of the schema.
+    pub strict: bool,
+    /// Whether the header must have at least one of the columns of the schema,
+    /// even when every one of them is optional.
+    pub any_column: bool,
+}
+
+/// A column of a schema.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnSchema {
+    pub name: String,
+    /// Whether the column may be missing from the header.
+    pub optional: bool,
+    /// The type values must be of, and are cast to.
+    pub field_type: Option<FieldType>,
+    pub constraints: Constraints,
+}
+
+/// Constraints on the values of a column.
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    /// The value can be neither nil nor empty.
+    pub required: bool,
+    /// The value must be one of these.
+    pub enumeration: Option<Vec<String>>,
+    /// The least number of characters in the value.
+    pub min_length: Option<usize>,
+    /// The most characters in the value.
+    pub max_length: Option<usize>,
+    /// The value must match this regular expression.
+    pub format: Option<Regex>,
+    /// The least value of the column's type.
+    pub minimum: Option<Cast>,
+    /// The greatest value of the column's type.
+    pub maximum: Option<Cast>,
+    /// No two values may be the same.
+    pub unique: bool,
+}
+
+/// The rule a header or a value breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    MissingColumn,
+    UnexpectedColumn,
+    ColumnOrder,
+    Required,
+    Type,
+    Enum,
+    MinLength,
+    MaxLength,
+    Minimum,
+    Maximum,
+    Format,
+    Unique,
+}
+
+impl Rule {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::MissingColumn => "missing_column",
+            Rule::UnexpectedColumn => "unexpected_column",
+            Rule::ColumnOrder => "column_order",
+            Rule::Required => "required",
+            Rule::Type => "type",
+            Rule::Enum => "enum",
+            Rule::MinLength => "min_length",
+            Rule::MaxLength => "max_length",
+            Rule::Minimum => "minimum",
+            Rule::Maximum => "maximum",
+            Rule::Format => "format",
+            Rule::Unique => "unique",
+        }
+    }
+}
+
+/// A problem found in the header or in a row.
+#[derive(Debug, Clone)]
+pub struct Violation {
+    /// The number of the row, counting from 1 after the header, or `None` for the header.
+    pub row: Option<usize>,
+    pub column: String,
+    pub rule: Rule,
+    /// The offending value, if the problem is with a value.
+    pub value: Option<String>,
+}
+
+/// The outcome of checking an input against a schema.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    /// The number of rows checked.
+    pub rows: usize,
+    /// The number of rows breaking at least one constraint.
+    pub invalid_rows: usize,
+    /// The number of problems found, including those left out of `violations`.
+    pub error_count: usize,
+    /// The problems found, up to `max_errors` of them.
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    /// Returns true if neither the header nor any row broke the schema.
+    pub fn is_valid(&self) -> bool {
+        self.error_count == 0
+    }
+}
+
+impl Schema {
+    /// Returns the type of each field of a record with these headers, or `None`
+    /// if no column has a type.
+    pub(crate) fn types(&self, headers: &[String]) -> Option<FieldTypes> {
+        if self
+            .columns
+            .iter()
+            .all(|column| column.field_type.is_none())
+        {
+            return None;
+        }
+        let columns = headers
+            .iter()
+            .map(|header| {
+                self.columns
+                    .iter()
+                    .find(|column| column.name == *header)
+                    .and_then(|column| column.field_type.clone())
+            })
+            .collect();
+        Some(FieldTypes { columns })
+    }
+}
+
+/// Checks the header once, then every record read, against a schema.
+pub(crate) struct Validation {
+    columns: Vec<ColumnSchema>,
+    /// The position in the header of each column of the schema, if it has it.
+    positions: Vec<Option<usize>>,
+    /// The values seen so far in each column that must be unique.
+    seen: Vec<HashSet<String>>,
+    max_errors: Option<usize>,
+    report: ValidationReport,
+}
+
+impl Validation {
+    /// Checks `headers` against `schema`, reporting missing, unexpected and
+    /// misplaced columns.
+    pub(crate) fn new(schema: Schema, headers: &[String], max_errors: Option<usize>) -> Self {
+        let positions: Vec<Option<usize>> = schema
+            .columns
+            .iter()
+            .map(|column| headers.iter().position(|header| *header == column.name))
+            .collect();
+
+        let mut problems = Vec::new();
+        for (column, position) in schema.columns.iter().zip(&positions) {
+            if position.is_none() && !column.optional {
+                problems.push((column.name.clone(), Rule::MissingColumn));
+            }
+        }
+        if schema.any_column && positions.iter().all(Option::is_none) {
+            for column in &schema.columns {
+                problems.push((column.name.clone(), Rule::MissingColumn));
+            }
+        }
+        if schema.strict {
+            for header in headers {
+                if !schema.columns.iter().any(|column| column.name == *header) {
+                    problems.push((header.clone(), Rule::UnexpectedColumn));
+                }
+            }
+        }
+        if schema.ordered {
+            let mut last = None;
+            for (column, position) in schema.columns.iter().zip(&positions) {
+                let Some(position) = *position else { continue };
+                if last.is_some_and(|last| position < last) {
+                    problems.push((column.name.clone(), Rule::ColumnOrder));
+                    break;
+                }
+                last = Some(position);
+            }
+        }
+
+        let mut validation = Self {
+            seen: vec![HashSet::new(); schema.columns.len()],
+            columns: schema.columns,
+            positions,
+            max_errors,
+            report: ValidationReport::default(),
+        };
+        for (column, rule) in problems {
+            validation.add(Violation {
+                row: None,
+                column,
+                rule,
+                value: None,
+            });
+        }
+        validation
+    }
+
+    pub(crate) fn report(&self) -> &ValidationReport {
+        &self.report
+    }
+
+    /// Checks the next row, adding the constraints it breaks to the report.
+    pub(crate) fn check(
+        &mut self,
+        record: &CsvRecordType,
+        quoted: &QuotedFields,
+        values: &FieldValues<'_>,
+    ) {
+        self.report.rows += 1;
+        let row = self.report.rows;
+        let errors = self.report.error_count;
+
+        for i in 0..self.columns.len() {
+            let Some(position) = self.positions[i] else {
+                continue;
+            };
+            let field = match record {
+                CsvRecordType::String(record) => record.get(position).map(Cow::Borrowed),
+                CsvRecordType::Byte(record) => record.get(position).map(String::from_utf8_lossy),
+            };
+            let value = field.filter(|field| !values.is_nil(position, field, quoted));
+            let column = &self.columns[i];
+            let broken = column
+                .constraints
+                .broken_by(value.as_deref(), column.field_type.as_ref())
+                .or_else(|| match value.as_deref() {
+                    Some(value) if column.constraints.unique && !value.is_empty() => {
+                        (!self.seen[i].insert(value.to_string())).then_some(Rule::Unique)
+                    }
+                    _ => None,
+                });
+            if let Some(rule) = broken {
+                let column = self.columns[i].name.clone();
+                self.add(Violation {
+                    row: Some(row),
+                    column,
+                    rule,
+                    value: value.map(Cow::into_owned),
+                });
+            }
+        }
+
+        if self.report.error_count > errors {
+            self.report.invalid_rows += 1;
+        }
+    }
+
+    fn add(&mut self, violation: Violation) {
+        self.report.error_count += 1;
+        let keep = match self.max_errors {
+            Some(max) => self.report.violations.len() < max,
+            None => true,
+        };
+        if keep {
+            self.report.violations.push(violation);
+        }
+    }
+}
+
+impl Constraints {
+    /// Returns the first constraint `value`, of a column of type `field_type`,
+    /// breaks, leaving out `unique`. Nil and empty values only break `required`.
+    fn broken_by(&self, value: Option<&str>, field_type: Option<&FieldType>) -> Option<Rule> {
+        let value = match value {
+            Some(value) if !value.is_empty() => value,
+            _ if self.required => return Some(Rule::Required),
+            _ => return None,
+        };
+        let cast = match field_type.map(|field_type| field_type.cast(value)) {
+            Some(None) => return Some(Rule::Type),
+            Some(cast) => cast,
+            None => None,
+        };
+        if let Some(allowed) = &self.enumeration {
+            // Values of a type are compared once cast, so `1.0` is one of `["1"]`
+            let allowed = match (field_type, cast) {
+                (Some(field_type), Some(cast)) => allowed
+                    .iter()
+                    .any(|allowed| field_type.cast(allowed) == Some(cast)),
+                _ => allowed.iter().any(|allowed| allowed == value),
+            };
+            if !allowed {
+                return Some(Rule::Enum);
+            }
+        }
+        if self.min_length.is_some() || self.max_length.is_some() {
+            let length = value.chars().count();
+            if self.min_length.is_some_and(|min| length < min) {
+                return Some(Rule::MinLength);
+            }
+            if self.max_length.is_some_and(|max| length > max) {
+                return Some(Rule::MaxLength);
+            }
+        }
+        if let Some(cast) = cast {
+            if self.minimum.is_some_and(|minimum| cast < minimum) {
+                return Some(Rule::Minimum);
+            }
+            if self.maximum.is_some_and(|maximum| cast > maximum) {
+                return Some(Rule::Maximum);
+            }
+        }
+        if let Some(format) = &self.format {
+            if !format.is_match(value) {
+                return Some(Rule::Format);
+            }
+        }
+        None
+    }
+}
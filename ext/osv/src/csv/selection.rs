@@ -0,0 +1,179 @@
+use super::parser::CsvRecordType;
+use super::quoting::QuotedFields;
+use ahash::RandomState;
+
+/// How rows are sampled once `offset` rows have been skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sample {
+    /// Keep every `n`th row, starting with the first one.
+    Every(usize),
+    /// Keep a uniform random sample of `size` rows, yielded in file order.
+    Reservoir { size: usize, seed: Option<u64> },
+}
+
+/// What the reader should do with the row it just read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Admission {
+    Yield,
+    Skip,
+}
+
+/// Applies `offset`, `limit` and sampling to the stream of raw CSV records,
+/// so rows that are never returned are never converted to Ruby objects.
+pub(crate) struct RowSelection {
+    offset: usize,
+    limit: Option<usize>,
+    sampler: Option<Sampler>,
+    /// Rows read so far, including the ones skipped by `offset`.
+    seen: usize,
+    /// Rows handed back to the caller so far.
+    yielded: usize,
+}
+
+enum Sampler {
+    Every(usize),
+    Reservoir(Reservoir),
+}
+
+impl RowSelection {
+    pub(crate) fn new(offset: usize, limit: Option<usize>, sample: Option<Sample>) -> Self {
+        Self {
+            offset,
+            limit,
+            sampler: sample.map(|sample| match sample {
+                Sample::Every(n) => Sampler::Every(n.max(1)),
+                Sample::Reservoir { size, seed } => Sampler::Reservoir(Reservoir::new(size, seed)),
+            }),
+            seen: 0,
+            yielded: 0,
+        }
+    }
+
+    /// Returns true once `limit` rows have been yielded, so reading can stop early.
+    #[inline]
+    pub(crate) fn is_exhausted(&self) -> bool {
+        self.limit.is_some_and(|limit| self.yielded >= limit)
+    }
+
+    /// Decides whether the record that was just read should be yielded.
+    ///
+    /// Records offered to a reservoir are retained internally and only come back
+    /// out through `next_reserved` once the input is exhausted.
+    #[inline]
+    pub(crate) fn admit(&mut self, record: &CsvRecordType, quoted: &QuotedFields) -> Admission {
+        let index = self.seen;
+        self.seen += 1;
+
+        if index < self.offset {
+            return Admission::Skip;
+        }
+        let position = index - self.offset;
+
+        let admission = match &mut self.sampler {
+            None => Admission::Yield,
+            Some(Sampler::Every(n)) if position % *n == 0 => Admission::Yield,
+            Some(Sampler::Every(_)) => Admission::Skip,
+            Some(Sampler::Reservoir(reservoir)) => {
+                reservoir.offer(position, record, quoted);
+                Admission::Skip
+            }
+        };
+
+        if admission == Admission::Yield {
+            self.yielded += 1;
+        }
+        admission
+    }
+
+    /// Called when the underlying reader hits EOF. Returns true if sampled rows
+    /// are now waiting to be drained through `next_reserved`.
+    pub(crate) fn finish(&mut self) -> bool {
+        match &mut self.sampler {
+            Some(Sampler::Reservoir(reservoir)) => reservoir.start_draining(),
+            _ => false,
+        }
+    }
+
+    /// Returns the next sampled record once the reservoir is being drained.
+    #[inline]
+    pub(crate) fn next_reserved(&mut self) -> Option<(CsvRecordType, QuotedFields)> {
+        let record = match &mut self.sampler {
+            Some(Sampler::Reservoir(reservoir)) => reservoir.next_drained(),
+            _ => None,
+        }?;
+        self.yielded += 1;
+        Some(record)
+    }
+}
+
+/// A row kept by the reservoir: its position, the record and its quoted fields.
+type ReservedRow = (usize, CsvRecordType, QuotedFields);
+
+/// Algorithm R reservoir. Rows are kept alongside their position so the
+/// sample can be returned in the order the rows appeared in the input.
+struct Reservoir {
+    size: usize,
+    rng: SplitMix64,
+    rows: Vec<ReservedRow>,
+    drained: Option<std::vec::IntoIter<ReservedRow>>,
+}
+
+impl Reservoir {
+    fn new(size: usize, seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| RandomState::new().hash_one(size));
+        Self {
+            size,
+            rng: SplitMix64(seed),
+            rows: Vec::with_capacity(size.min(1024)),
+            drained: None,
+        }
+    }
+
+    fn offer(&mut self, position: usize, record: &CsvRecordType, quoted: &QuotedFields) {
+        if self.rows.len() < self.size {
+            self.rows.push((position, record.clone(), quoted.clone()));
+        } else {
+            let slot = self.rng.below(position as u64 + 1) as usize;
+            if slot < self.size {
+                self.rows[slot] = (position, record.clone(), quoted.clone());
+            }
+        }
+    }
+
+    fn start_draining(&mut self) -> bool {
+        if self.drained.is_some() {
+            return false;
+        }
+        let mut rows = std::mem::take(&mut self.rows);
+        rows.sort_unstable_by_key(|(position, ..)| *position);
+        let has_rows = !rows.is_empty();
+        self.drained = Some(rows.into_iter());
+        has_rows
+    }
+
+    fn next_drained(&mut self) -> Option<(CsvRecordType, QuotedFields)> {
+        self.drained
+            .as_mut()
+            .and_then(Iterator::next)
+            .map(|(_, record, quoted)| (record, quoted))
+    }
+}
+
+/// Small splitmix64 generator. Sampling only needs cheap, reproducible
+/// randomness, which isn't worth pulling in another dependency for.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly distributed value in `0..bound`.
+    fn below(&mut self, bound: u64) -> u64 {
+        ((u128::from(self.next_u64()) * u128::from(bound)) >> 64) as u64
+    }
+}
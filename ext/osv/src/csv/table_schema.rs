@@ -0,0 +1,253 @@
+use super::schema::{ColumnSchema, Constraints, Schema};
+use super::types::{Cast, FieldType, NumberFormat};
+use super::values::NilValues;
+use regex::Regex;
+use serde::Deserialize;
+
+/// A Frictionless Table Schema, as found in a `tableschema.json` descriptor.
+///
+/// Only the parts that bear on reading a CSV input are kept: the fields, their
+/// types and constraints, the values standing for a missing value, and how the
+/// fields must match the header.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableSchema {
+    pub fields: Vec<TableField>,
+    #[serde(default = "default_missing_values")]
+    pub missing_values: Vec<String>,
+    /// `exact`, `equal`, `subset`, `superset` or `partial`, as in version 2 of
+    /// the spec. Version 1 descriptors don't have it and are `exact`.
+    pub fields_match: Option<String>,
+}
+
+/// A field of a Table Schema.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: Option<String>,
+    pub format: Option<String>,
+    #[serde(default)]
+    pub constraints: TableConstraints,
+    pub true_values: Option<Vec<String>>,
+    pub false_values: Option<Vec<String>>,
+    pub bare_number: Option<bool>,
+    pub decimal_char: Option<String>,
+    pub group_char: Option<String>,
+    /// Replaces the schema's `missingValues` for this field.
+    pub missing_values: Option<Vec<String>>,
+}
+
+/// The constraints of a Table Schema field.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableConstraints {
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub unique: bool,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub minimum: Option<Scalar>,
+    pub maximum: Option<Scalar>,
+    pub pattern: Option<String>,
+    #[serde(rename = "enum")]
+    pub enumeration: Option<Vec<Scalar>>,
+}
+
+/// A value given in a descriptor, which JSON may write as a number or a boolean
+/// as much as a string.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Scalar {
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    String(String),
+}
+
+impl Scalar {
+    /// The value as the text of a CSV field.
+    fn to_text(&self) -> String {
+        match self {
+            Scalar::Boolean(value) => value.to_string(),
+            Scalar::Integer(value) => value.to_string(),
+            Scalar::Number(value) => value.to_string(),
+            Scalar::String(value) => value.clone(),
+        }
+    }
+}
+
+fn default_missing_values() -> Vec<String> {
+    vec![String::new()]
+}
+
+impl TableSchema {
+    /// The schema the header and rows are checked against, with the types values
+    /// are cast to.
+    pub fn schema(&self) -> Result<Schema, String> {
+        // `subset`: every field is in the header, which may have other columns.
+        // `superset`: the header only has fields, but may lack some of them.
+        let (ordered, strict, optional) = match self.fields_match.as_deref() {
+            None | Some("exact") => (true, true, false),
+            Some("equal") => (false, true, false),
+            Some("subset") => (false, false, false),
+            Some("superset") => (false, true, true),
+            Some("partial") => (false, false, true),
+            Some(other) => {
+                return Err(format!(
+                "unknown fieldsMatch {other}, expected exact, equal, subset, superset or partial"
+            ))
+            }
+        };
+
+        let columns = self
+            .fields
+            .iter()
+            .map(|field| -> Result<ColumnSchema, String> {
+                let field_type = field.field_type()?;
+                Ok(ColumnSchema {
+                    name: field.name.clone(),
+                    optional,
+                    constraints: field.constraints(field_type.as_ref())?,
+                    field_type,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Schema {
+            columns,
+            ordered,
+            strict,
+            // A partial match still needs one of the fields in the header
+            any_column: self.fields_match.as_deref() == Some("partial"),
+        })
+    }
+
+    /// The values read as nil, for every field and for the fields that have
+    /// their own.
+    pub fn nil_values(&self) -> NilValues {
+        NilValues {
+            all: self.missing_values.clone(),
+            columns: self
+                .fields
+                .iter()
+                .filter_map(|field| Some((field.name.clone(), field.missing_values.clone()?)))
+                .collect(),
+        }
+    }
+}
+
+impl TableField {
+    /// The type values of the field are cast to. Fields of the other types,
+    /// like `string` or `time`, are read as Strings.
+    fn field_type(&self) -> Result<Option<FieldType>, String> {
+        let number = || -> Result<NumberFormat, String> {
+            Ok(NumberFormat {
+                bare: self.bare_number.unwrap_or(true),
+                decimal_char: self
+                    .single_char("decimalChar", &self.decimal_char)?
+                    .unwrap_or('.'),
+                group_char: self.single_char("groupChar", &self.group_char)?,
+            })
+        };
+
+        Ok(match self.field_type.as_deref() {
+            Some("integer") => Some(FieldType::Integer(number()?)),
+            Some("number") => Some(FieldType::Number(number()?)),
+            Some("boolean") => {
+                Some(FieldType::Boolean {
+                    true_values: self.true_values.clone().unwrap_or_else(|| {
+                        ["true", "True", "TRUE", "1"].map(String::from).to_vec()
+                    }),
+                    false_values: self.false_values.clone().unwrap_or_else(|| {
+                        ["false", "False", "FALSE", "0"].map(String::from).to_vec()
+                    }),
+                })
+            }
+            Some(kind @ ("date" | "datetime")) => {
+                // Other formats are strptime patterns, which aren't supported
+                if let Some(format) = self
+                    .format
+                    .as_deref()
+                    .filter(|format| !matches!(*format, "default" | "any"))
+                {
+                    return Err(format!(
+                        "unsupported format {format} for {kind} field {}, only ISO 8601 is read",
+                        self.name
+                    ));
+                }
+                Some(if kind == "date" {
+                    FieldType::Date
+                } else {
+                    FieldType::Datetime
+                })
+            }
+            Some("year") => Some(FieldType::Year),
+            _ => None,
+        })
+    }
+
+    /// The constraints of the field, with `minimum` and `maximum` cast to its type.
+    fn constraints(&self, field_type: Option<&FieldType>) -> Result<Constraints, String> {
+        let constraints = &self.constraints;
+        let bound = |name: &str, value: &Option<Scalar>| {
+            value
+                .as_ref()
+                .map(|value| -> Result<Cast, String> {
+                    let field_type = field_type.ok_or_else(|| {
+                        format!(
+                            "{name} of field {} needs an integer, number, date, datetime or year type",
+                            self.name
+                        )
+                    })?;
+                    let text = value.to_text();
+                    field_type.cast(&text).ok_or_else(|| {
+                        format!("{name} {text} of field {} isn't a value of its type", self.name)
+                    })
+                })
+                .transpose()
+        };
+
+        // Patterns match whole values
+        let format = constraints
+            .pattern
+            .as_deref()
+            .map(|pattern| {
+                Regex::new(&format!(r"\A(?:{pattern})\z")).map_err(|e| {
+                    format!("unsupported pattern {pattern} for field {}: {e}", self.name)
+                })
+            })
+            .transpose()?;
+
+        Ok(Constraints {
+            required: constraints.required,
+            enumeration: constraints
+                .enumeration
+                .as_ref()
+                .map(|values| values.iter().map(Scalar::to_text).collect()),
+            min_length: constraints.min_length,
+            max_length: constraints.max_length,
+            format,
+            minimum: bound("minimum", &constraints.minimum)?,
+            maximum: bound("maximum", &constraints.maximum)?,
+            unique: constraints.unique,
+        })
+    }
+
+    /// Reads a property that must be a single character.
+    fn single_char(&self, property: &str, value: &Option<String>) -> Result<Option<char>, String> {
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(Some(c)),
+            _ => Err(format!(
+                "{property} of field {} must be a single character",
+                self.name
+            )),
+        }
+    }
+}
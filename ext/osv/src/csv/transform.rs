@@ -0,0 +1,125 @@
+use super::builder::ReaderError;
+use super::parser::CsvRecordType;
+use super::quoting::QuotedFields;
+use super::values::FieldValues;
+use super::Field;
+use regex::Regex;
+use std::borrow::Cow;
+
+/// A condition on the value of a column, as given to `where:`.
+#[derive(Debug, Clone)]
+pub enum Condition {
+    /// The value is one of these, where `None` stands for nil.
+    OneOf(Vec<Option<String>>),
+    /// The value matches the regex somewhere. Nil never does.
+    Matches(Regex),
+}
+
+impl Condition {
+    #[inline]
+    fn holds(&self, value: Option<&str>) -> bool {
+        match self {
+            Condition::OneOf(values) => values.iter().any(|allowed| allowed.as_deref() == value),
+            Condition::Matches(regex) => value.is_some_and(|value| regex.is_match(value)),
+        }
+    }
+}
+
+/// Conditions rows must meet to be read, by column name, as given to `where:`.
+#[derive(Debug, Clone, Default)]
+pub struct RowFilter(pub Vec<(String, Condition)>);
+
+impl RowFilter {
+    /// Resolves the column names against the headers of the input. A repeated
+    /// header stands for its first column.
+    pub(crate) fn resolve(&self, headers: &[String]) -> Result<FieldFilter, ReaderError> {
+        let conditions = self
+            .0
+            .iter()
+            .map(|(name, condition)| {
+                let position = headers
+                    .iter()
+                    .position(|header| header == name)
+                    .ok_or_else(|| ReaderError::UnknownColumn(name.clone()))?;
+                Ok((position, condition.clone()))
+            })
+            .collect::<Result<_, ReaderError>>()?;
+        Ok(FieldFilter { conditions })
+    }
+}
+
+/// The conditions rows must meet, by field position.
+pub(crate) struct FieldFilter {
+    conditions: Vec<(usize, Condition)>,
+}
+
+impl FieldFilter {
+    /// Whether `record` meets every condition. Values are compared as read, once
+    /// nil markers apply and before converters run. Fields a short row lacks are nil.
+    #[inline]
+    pub(crate) fn admits(
+        &self,
+        record: &CsvRecordType,
+        quoted: &QuotedFields,
+        values: &FieldValues<'_>,
+    ) -> bool {
+        self.conditions.iter().all(|(position, condition)| {
+            let field = match record {
+                CsvRecordType::String(record) => record.get(*position).map(Cow::Borrowed),
+                CsvRecordType::Byte(record) => record.get(*position).map(String::from_utf8_lossy),
+            };
+            let value = field.filter(|field| !values.is_nil(*position, field, quoted));
+            condition.holds(value.as_deref())
+        })
+    }
+}
+
+/// The columns written by `OSV.transform`, as given to `select:` and `rename:`.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnSelection {
+    /// The columns to keep, in the order they are written. Every column when `None`.
+    pub select: Option<Vec<String>>,
+    /// New names for columns, by their name in the input.
+    pub rename: Vec<(String, String)>,
+}
+
+impl ColumnSelection {
+    /// Resolves the selection against the headers of the input, returning the
+    /// position of each column written and the header it is written under.
+    pub fn resolve(&self, headers: &[String]) -> Result<(Vec<usize>, Vec<String>), ReaderError> {
+        let position = |name: &String| {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| ReaderError::UnknownColumn(name.clone()))
+        };
+
+        let positions = match &self.select {
+            Some(select) => select.iter().map(position).collect::<Result<Vec<_>, _>>()?,
+            None => (0..headers.len()).collect(),
+        };
+
+        let mut names: Vec<String> = positions.iter().map(|&i| headers[i].clone()).collect();
+        for (from, to) in &self.rename {
+            let from = position(from)?;
+            for (name, &i) in names.iter_mut().zip(&positions) {
+                if i == from {
+                    name.clone_from(to);
+                }
+            }
+        }
+
+        Ok((positions, names))
+    }
+}
+
+/// The text a field is written as: nil as an empty field, and values cast by a
+/// table schema as they are displayed.
+#[inline]
+pub fn field_text<'f>(field: Option<&'f Field<'_>>) -> Cow<'f, str> {
+    match field {
+        None => Cow::Borrowed(""),
+        Some(Field::Str(value)) => Cow::Borrowed(&value.0),
+        Some(Field::Cast(value)) => Cow::Owned(value.to_string()),
+    }
+}
@@ -0,0 +1,380 @@
+use magnus::{
+    value::{Lazy, ReprValue},
+    IntoValue, Module, RClass, Ruby, Symbol, Value,
+};
+use std::{cmp::Ordering, fmt};
+
+/// The type of a column, which its values are cast to before they reach Ruby.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    /// A whole number, read as an `Integer`.
+    Integer(NumberFormat),
+    /// A decimal number, read as a `Float`.
+    Number(NumberFormat),
+    /// One of the true or false values, read as `true` or `false`.
+    Boolean {
+        true_values: Vec<String>,
+        false_values: Vec<String>,
+    },
+    /// An ISO 8601 date like `2024-01-31`, read as a `Date`.
+    Date,
+    /// An ISO 8601 date and time like `2024-01-31T10:00:00Z`, read as a `Time`.
+    /// Times without an offset are in UTC.
+    Datetime,
+    /// A year like `2024`, read as an `Integer`.
+    Year,
+}
+
+/// How numbers are written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberFormat {
+    /// Whether the number may have no other characters around it, like `$` or `%`.
+    pub bare: bool,
+    /// The character separating the integer part from the fraction.
+    pub decimal_char: char,
+    /// A character grouping digits, like the `,` in `1,000`, which is ignored.
+    pub group_char: Option<char>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            bare: true,
+            decimal_char: '.',
+            group_char: None,
+        }
+    }
+}
+
+/// A value cast to the type of its column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cast {
+    Integer(i64),
+    Number(f64),
+    Boolean(bool),
+    Date {
+        year: i32,
+        month: u32,
+        day: u32,
+    },
+    Datetime {
+        /// Seconds since the Unix epoch.
+        seconds: i64,
+        nanos: u32,
+        /// The offset from UTC in seconds, or `None` for UTC.
+        offset: Option<i32>,
+    },
+}
+
+impl FieldType {
+    /// Casts `value` to this type, returning `None` if it isn't a value of it.
+    pub fn cast(&self, value: &str) -> Option<Cast> {
+        match self {
+            FieldType::Integer(format) => {
+                parse_integer(&format.normalize(value)?).map(Cast::Integer)
+            }
+            FieldType::Number(format) => parse_number(&format.normalize(value)?).map(Cast::Number),
+            FieldType::Boolean {
+                true_values,
+                false_values,
+            } => {
+                if true_values.iter().any(|true_value| true_value == value) {
+                    Some(Cast::Boolean(true))
+                } else if false_values.iter().any(|false_value| false_value == value) {
+                    Some(Cast::Boolean(false))
+                } else {
+                    None
+                }
+            }
+            FieldType::Date => {
+                parse_date(value).map(|(year, month, day)| Cast::Date { year, month, day })
+            }
+            FieldType::Datetime => parse_datetime(value),
+            FieldType::Year => parse_digits(value)
+                .filter(|_| value.len() == 4)
+                .map(|year| Cast::Integer(year.into())),
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Strips the characters around the number when it needn't be bare, and the
+    /// group characters, and makes `.` the decimal separator.
+    fn normalize(&self, value: &str) -> Option<String> {
+        let value = if self.bare {
+            value
+        } else {
+            let start = value.find(|c: char| c.is_ascii_digit() || "+-.".contains(c))?;
+            let end = value.rfind(|c: char| c.is_ascii_digit())? + 1;
+            value.get(start..end)?
+        };
+        Some(
+            value
+                .chars()
+                .filter(|&c| Some(c) != self.group_char)
+                .map(|c| if c == self.decimal_char { '.' } else { c })
+                .collect(),
+        )
+    }
+}
+
+impl PartialOrd for Cast {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Cast::Integer(a), Cast::Integer(b)) => a.partial_cmp(b),
+            (Cast::Number(a), Cast::Number(b)) => a.partial_cmp(b),
+            (Cast::Integer(a), Cast::Number(b)) => (*a as f64).partial_cmp(b),
+            (Cast::Number(a), Cast::Integer(b)) => a.partial_cmp(&(*b as f64)),
+            (Cast::Boolean(a), Cast::Boolean(b)) => a.partial_cmp(b),
+            (
+                Cast::Date { year, month, day },
+                Cast::Date {
+                    year: other_year,
+                    month: other_month,
+                    day: other_day,
+                },
+            ) => (year, month, day).partial_cmp(&(other_year, other_month, other_day)),
+            (
+                Cast::Datetime { seconds, nanos, .. },
+                Cast::Datetime {
+                    seconds: other_seconds,
+                    nanos: other_nanos,
+                    ..
+                },
+            ) => (seconds, nanos).partial_cmp(&(other_seconds, other_nanos)),
+            _ => None,
+        }
+    }
+}
+
+/// Writes the value as text: dates and times in ISO 8601, like `2024-01-31` and
+/// `2024-01-31T10:00:00.5+02:00`, in the offset they were read with.
+impl fmt::Display for Cast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Cast::Integer(value) => write!(f, "{value}"),
+            Cast::Number(value) => write!(f, "{value}"),
+            Cast::Boolean(value) => write!(f, "{value}"),
+            Cast::Date { year, month, day } => write!(f, "{year:04}-{month:02}-{day:02}"),
+            Cast::Datetime {
+                seconds,
+                nanos,
+                offset,
+            } => {
+                let local = seconds + i64::from(offset.unwrap_or(0));
+                let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+                let time = local.rem_euclid(86_400);
+                write!(
+                    f,
+                    "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+                    time / 3600,
+                    time / 60 % 60,
+                    time % 60
+                )?;
+                if nanos > 0 {
+                    let fraction = format!("{nanos:09}");
+                    write!(f, ".{}", fraction.trim_end_matches('0'))?;
+                }
+                match offset {
+                    None => write!(f, "Z"),
+                    Some(offset) => {
+                        let sign = if offset < 0 { '-' } else { '+' };
+                        let offset = offset.unsigned_abs();
+                        write!(f, "{sign}{:02}:{:02}", offset / 3600, offset / 60 % 60)
+                    }
+                }
+            }
+        }
+    }
+}
+
+static DATE: Lazy<RClass> = Lazy::new(|ruby| {
+    ruby.require("date").unwrap();
+    ruby.class_object().const_get("Date").unwrap()
+});
+
+impl IntoValue for Cast {
+    fn into_value_with(self, handle: &Ruby) -> Value {
+        match self {
+            Cast::Integer(value) => value.into_value_with(handle),
+            Cast::Number(value) => value.into_value_with(handle),
+            Cast::Boolean(value) => value.into_value_with(handle),
+            // The date was checked when it was cast, so Ruby accepts it
+            Cast::Date { year, month, day } => handle
+                .get_inner(&DATE)
+                .funcall("new", (year, month, day))
+                .unwrap(),
+            Cast::Datetime {
+                seconds,
+                nanos,
+                offset,
+            } => {
+                let time: Value = handle
+                    .class_time()
+                    .funcall("at", (seconds, nanos, Symbol::new("nsec")))
+                    .unwrap();
+                match offset {
+                    Some(offset) => time.funcall("getlocal", (offset,)).unwrap(),
+                    None => time.funcall("utc", ()).unwrap(),
+                }
+            }
+        }
+    }
+}
+
+/// Parses an optionally signed run of digits.
+pub(crate) fn parse_integer(value: &str) -> Option<i64> {
+    let digits = value.strip_prefix(['+', '-']).unwrap_or(value);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// Parses a decimal number, with an optional exponent, or `NaN`, `INF` and `-INF`.
+pub(crate) fn parse_number(value: &str) -> Option<f64> {
+    match value {
+        "NaN" => return Some(f64::NAN),
+        "INF" => return Some(f64::INFINITY),
+        "-INF" => return Some(f64::NEG_INFINITY),
+        _ => {}
+    }
+    // Rust also reads `inf`, `infinity` and `nan`, which aren't numbers here
+    let mantissa = value.split(['e', 'E']).next()?;
+    if !mantissa.bytes().any(|b| b.is_ascii_digit())
+        || value
+            .bytes()
+            .any(|b| b.is_ascii_alphabetic() && b != b'e' && b != b'E')
+    {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// Parses a `YYYY-MM-DD` date, checking the day exists.
+fn parse_date(value: &str) -> Option<(i32, u32, u32)> {
+    let bytes = value.as_bytes();
+    if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return None;
+    }
+    let year = parse_digits(&value[0..4])? as i32;
+    let month = parse_digits(&value[5..7])?;
+    let day = parse_digits(&value[8..10])?;
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    Some((year, month, day))
+}
+
+/// Parses a `YYYY-MM-DDThh:mm:ss` date and time, with an optional fraction of a
+/// second and an optional `Z` or `±hh:mm` offset.
+fn parse_datetime(value: &str) -> Option<Cast> {
+    let (date, time) = value.split_once('T')?;
+    let (year, month, day) = parse_date(date)?;
+
+    let (time, offset) = if let Some(time) = time.strip_suffix('Z') {
+        (time, None)
+    } else if let Some(position) = time.rfind(['+', '-']) {
+        let (time, offset) = time.split_at(position);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let (hours, minutes) = offset[1..].split_once(':')?;
+        if hours.len() != 2 || minutes.len() != 2 {
+            return None;
+        }
+        let (hours, minutes) = (parse_digits(hours)?, parse_digits(minutes)?);
+        if hours > 23 || minutes > 59 {
+            return None;
+        }
+        (time, Some(sign * (hours * 3600 + minutes * 60) as i32))
+    } else {
+        (time, None)
+    };
+
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (time, None),
+    };
+    let bytes = time.as_bytes();
+    if bytes.len() != 8 || bytes[2] != b':' || bytes[5] != b':' {
+        return None;
+    }
+    let hour = parse_digits(&time[0..2])?;
+    let minute = parse_digits(&time[3..5])?;
+    let second = parse_digits(&time[6..8])?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let nanos = match fraction {
+        Some(fraction) if fraction.len() <= 9 => {
+            parse_digits(fraction)? * 10u32.pow(9 - fraction.len() as u32)
+        }
+        Some(_) => return None,
+        None => 0,
+    };
+
+    let seconds = days_from_civil(year, month, day) * 86_400
+        + i64::from(hour * 3600 + minute * 60 + second)
+        - i64::from(offset.unwrap_or(0));
+    Some(Cast::Datetime {
+        seconds,
+        nanos,
+        offset,
+    })
+}
+
+/// Parses a non-empty run of ASCII digits.
+fn parse_digits(value: &str) -> Option<u32> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The number of days between the Unix epoch and a date of the proleptic
+/// Gregorian calendar.
+pub(crate) fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The date of the proleptic Gregorian calendar a number of days after the Unix
+/// epoch, the inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// The type of each field of a record, by position.
+pub(crate) struct FieldTypes {
+    pub(crate) columns: Vec<Option<FieldType>>,
+}
+
+impl FieldTypes {
+    /// Casts the value of field `index` to the type of its column, if it has one.
+    #[inline]
+    pub(crate) fn cast(&self, index: usize, value: &str) -> Option<Cast> {
+        self.columns.get(index)?.as_ref()?.cast(value)
+    }
+}
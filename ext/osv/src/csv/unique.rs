@@ -0,0 +1,216 @@
+use super::builder::ReaderError;
+use super::parser::CsvRecordType;
+use super::quoting::QuotedFields;
+use super::values::FieldValues;
+use ahash::RandomState;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+/// Which of the rows sharing a key is read, as given to `keep:`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Keep {
+    #[default]
+    First,
+    /// The last row of each key, read in the order of those last rows. The last
+    /// row read of each key is held in memory until the input is read, so memory
+    /// grows with the number of distinct keys.
+    Last,
+}
+
+/// The key rows are deduplicated by, as given to `unique_by:` or `unique: true`.
+#[derive(Debug, Clone, Default)]
+pub struct Unique {
+    /// The columns making up the key, or the whole row when `None`.
+    pub columns: Option<Vec<String>>,
+    pub keep: Keep,
+}
+
+impl Unique {
+    /// Resolves the column names against the headers of the input. A repeated
+    /// header stands for its first column.
+    pub(crate) fn resolve(&self, headers: &[String]) -> Result<Dedup, ReaderError> {
+        let columns = self
+            .columns
+            .as_ref()
+            .map(|columns| {
+                columns
+                    .iter()
+                    .map(|name| {
+                        headers
+                            .iter()
+                            .position(|header| header == name)
+                            .ok_or_else(|| ReaderError::UnknownColumn(name.clone()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+
+        Ok(Dedup {
+            columns,
+            width: headers.len(),
+            keep: self.keep,
+            seen: HashSet::default(),
+            duplicates: 0,
+            held: Vec::new(),
+            last: HashMap::default(),
+            rows: 0,
+            replay: None,
+            key: Vec::new(),
+        })
+    }
+}
+
+/// Leaves out the rows whose key was already read, or, keeping the last ones,
+/// that is read again later.
+///
+/// Keys are remembered by their values, so rows are only ever left out for
+/// repeating another exactly, byte for byte.
+pub(crate) struct Dedup {
+    columns: Option<Vec<usize>>,
+    /// The number of columns, which whole rows are compared over at least.
+    width: usize,
+    keep: Keep,
+    /// The keys read so far, when keeping the first rows.
+    seen: HashSet<Box<[u8]>, RandomState>,
+    duplicates: usize,
+    /// The last row read of each key, when keeping the last ones, with its
+    /// number among the rows held, which gives the order they are handed back in.
+    held: Vec<(CsvRecordType, QuotedFields, usize)>,
+    /// The position in `held` of the row of each key.
+    last: HashMap<Box<[u8]>, usize, RandomState>,
+    /// The number of rows held so far, duplicates included.
+    rows: usize,
+    /// The last rows of each key, once the input is read.
+    replay: Option<std::vec::IntoIter<(CsvRecordType, QuotedFields)>>,
+    /// The key of the last row, reused to spare an allocation per row.
+    key: Vec<u8>,
+}
+
+impl Dedup {
+    /// Whether rows are held until the input is read, rather than read as they come.
+    #[inline]
+    pub(crate) fn holds_rows(&self) -> bool {
+        self.keep == Keep::Last
+    }
+
+    /// Whether the rows held are being handed back.
+    #[inline]
+    pub(crate) fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// The number of rows left out so far.
+    pub(crate) fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    /// Whether `record` is the first of its key, remembering the key if it is.
+    #[inline]
+    pub(crate) fn admits(
+        &mut self,
+        record: &CsvRecordType,
+        quoted: &QuotedFields,
+        values: &FieldValues<'_>,
+    ) -> bool {
+        self.encode_key(record, quoted, values);
+        if self.seen.contains(self.key.as_slice()) {
+            self.duplicates += 1;
+            return false;
+        }
+        self.seen.insert(self.key.as_slice().into());
+        true
+    }
+
+    /// Holds `record` until the input is read, in place of any earlier row of its key.
+    pub(crate) fn hold(
+        &mut self,
+        record: &CsvRecordType,
+        quoted: &QuotedFields,
+        values: &FieldValues<'_>,
+    ) {
+        self.encode_key(record, quoted, values);
+        let row = self.rows;
+        self.rows += 1;
+        match self.last.get(self.key.as_slice()) {
+            Some(&position) => {
+                self.duplicates += 1;
+                let (held, held_quoted, held_row) = &mut self.held[position];
+                held.clone_from(record);
+                held_quoted.clone_from(quoted);
+                *held_row = row;
+            }
+            None => {
+                self.last
+                    .insert(self.key.as_slice().into(), self.held.len());
+                self.held.push((record.clone(), quoted.clone(), row));
+            }
+        }
+    }
+
+    /// Starts handing back the rows held once the input is read, returning false
+    /// if none are held or they already were.
+    pub(crate) fn release(&mut self) -> bool {
+        if !self.holds_rows() || self.replay.is_some() {
+            return false;
+        }
+        self.last = HashMap::default();
+        let mut held = std::mem::take(&mut self.held);
+        held.sort_unstable_by_key(|(_, _, row)| *row);
+        let rows: Vec<_> = held
+            .into_iter()
+            .map(|(record, quoted, _)| (record, quoted))
+            .collect();
+        self.replay = Some(rows.into_iter());
+        true
+    }
+
+    /// Moves the next row held into `record` and `quoted`, returning false once
+    /// every one was.
+    #[inline]
+    pub(crate) fn replay(&mut self, record: &mut CsvRecordType, quoted: &mut QuotedFields) -> bool {
+        match self.replay.as_mut().and_then(Iterator::next) {
+            Some((next, next_quoted)) => {
+                *record = next;
+                *quoted = next_quoted;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Writes the key of `record` to `self.key`: the raw bytes of the key columns,
+    /// or nothing for the ones that are nil once nil markers apply, where fields a
+    /// short row lacks are nil. Each value is prefixed by its length so no two
+    /// keys can be written the same.
+    fn encode_key(
+        &mut self,
+        record: &CsvRecordType,
+        quoted: &QuotedFields,
+        values: &FieldValues<'_>,
+    ) {
+        let key = &mut self.key;
+        key.clear();
+        let mut add = |position: usize| {
+            let field = match record {
+                CsvRecordType::String(record) => record
+                    .get(position)
+                    .map(|field| (field.as_bytes(), Cow::Borrowed(field))),
+                CsvRecordType::Byte(record) => record
+                    .get(position)
+                    .map(|field| (field, String::from_utf8_lossy(field))),
+            };
+            match field.filter(|(_, text)| !values.is_nil(position, text, quoted)) {
+                Some((bytes, _)) => {
+                    key.push(1);
+                    key.extend_from_slice(&bytes.len().to_le_bytes());
+                    key.extend_from_slice(bytes);
+                }
+                None => key.push(0),
+            }
+        };
+        match &self.columns {
+            Some(columns) => columns.iter().for_each(|&position| add(position)),
+            None => (0..record.len().max(self.width)).for_each(add),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use super::builder::ReaderError;
+use super::converters::FieldConverters;
+use super::header_cache::{StringCache, StringCacheKey};
+use super::quoting::QuotedFields;
+use super::types::FieldTypes;
+use super::{CowStr, Field};
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+/// Values read as nil, for every column and for some columns in particular.
+#[derive(Debug, Clone, Default)]
+pub struct NilValues {
+    /// Markers for the columns not listed in `columns`.
+    pub all: Vec<String>,
+    /// Markers replacing `all` for the named columns.
+    pub columns: Vec<(String, Vec<String>)>,
+}
+
+impl NilValues {
+    /// Resolves the column names against the headers of the input.
+    pub(crate) fn resolve(&self, headers: &[String]) -> Result<FieldNils, ReaderError> {
+        let mut sets = vec![self.all.clone()];
+        let mut columns = vec![0; headers.len()];
+        for (name, markers) in &self.columns {
+            let positions: Vec<usize> = headers
+                .iter()
+                .enumerate()
+                .filter(|(_, header)| *header == name)
+                .map(|(i, _)| i)
+                .collect();
+            if positions.is_empty() {
+                return Err(ReaderError::UnknownColumn(name.clone()));
+            }
+            sets.push(markers.clone());
+            for i in positions {
+                columns[i] = sets.len() - 1;
+            }
+        }
+        Ok(FieldNils { sets, columns })
+    }
+}
+
+/// Values given to the fields missing from short rows, in flexible mode.
+#[derive(Debug, Clone, Default)]
+pub struct MissingValues {
+    /// The value of missing fields in the columns not listed in `columns`.
+    pub all: Option<String>,
+    /// Values replacing `all` for the named columns.
+    pub columns: Vec<(String, String)>,
+}
+
+impl MissingValues {
+    /// Resolves the column names against the headers of the input, turning each
+    /// value into a string that can be shared by every record with `share`.
+    pub(crate) fn resolve<'a>(
+        &self,
+        headers: &[String],
+        share: impl Fn(&str) -> Result<Cow<'a, str>, ReaderError>,
+    ) -> Result<FieldDefaults<'a>, ReaderError> {
+        let all = self.all.as_deref().map(&share).transpose()?;
+        let mut columns = vec![all; headers.len()];
+        for (name, value) in &self.columns {
+            let value = share(value)?;
+            let mut found = false;
+            for (i, header) in headers.iter().enumerate() {
+                if header == name {
+                    columns[i] = Some(value.clone());
+                    found = true;
+                }
+            }
+            if !found {
+                return Err(ReaderError::UnknownColumn(name.clone()));
+            }
+        }
+        Ok(FieldDefaults { columns })
+    }
+}
+
+/// The value of each field missing from a short record, by position.
+pub(crate) struct FieldDefaults<'a> {
+    columns: Vec<Option<Cow<'a, str>>>,
+}
+
+impl<'a> FieldDefaults<'a> {
+    /// The value of field `index` when the record doesn't have it.
+    #[inline]
+    pub(crate) fn get(&self, index: usize) -> Option<CowStr<'a>> {
+        self.columns.get(index).cloned().flatten().map(CowStr)
+    }
+}
+
+/// What to do with the fields of a record past the last header, in flexible mode.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ExtraFields {
+    /// Leaves them out of Hash rows.
+    #[default]
+    Drop,
+    /// Fails on the first record that has any.
+    Raise,
+    /// Gathers them in an Array under the given key of Hash rows.
+    Collect(String),
+}
+
+impl ExtraFields {
+    /// Interns the key extra fields are collected under, checking it doesn't
+    /// clash with a header.
+    pub(crate) fn resolve(&self, headers: &[String]) -> Result<FieldExtras, ReaderError> {
+        Ok(match self {
+            ExtraFields::Drop => FieldExtras::Drop,
+            ExtraFields::Raise => FieldExtras::Raise,
+            ExtraFields::Collect(key) if headers.contains(key) => {
+                return Err(ReaderError::ColumnExists(key.clone()))
+            }
+            ExtraFields::Collect(key) => FieldExtras::Collect(StringCache::intern_many(&[key])?[0]),
+        })
+    }
+}
+
+/// What to do with the fields of a record past the last header, with the key
+/// they are collected under interned.
+pub(crate) enum FieldExtras {
+    Drop,
+    Raise,
+    Collect(StringCacheKey),
+}
+
+/// How Hash rows deal with several columns having the same header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateHeaders {
+    /// Keeps the value of the last of the columns.
+    #[default]
+    Last,
+    /// Keeps the value of the first of the columns.
+    First,
+    /// Fails as soon as the headers are read.
+    Raise,
+    /// Renames the columns after the first to `name_2`, `name_3` and so on.
+    Suffix,
+    /// Gathers the values of all the columns in an Array under the shared key.
+    Array,
+}
+
+impl DuplicateHeaders {
+    /// Checks `headers` for duplicates, renaming them when suffixing, and returns
+    /// how the parser must insert fields under them.
+    pub(crate) fn resolve(self, headers: &mut [String]) -> Result<FieldDuplicates, ReaderError> {
+        let mut seen = HashSet::with_capacity(headers.len());
+        let duplicated: Vec<&String> = headers
+            .iter()
+            .filter(|header| !seen.insert(header.as_str()))
+            .collect();
+        let Some(first) = duplicated.first() else {
+            return Ok(FieldDuplicates::Last);
+        };
+
+        Ok(match self {
+            DuplicateHeaders::Last => FieldDuplicates::Last,
+            DuplicateHeaders::First => FieldDuplicates::First,
+            DuplicateHeaders::Raise => return Err(ReaderError::DuplicateHeader(first.to_string())),
+            DuplicateHeaders::Array => {
+                let grouped = headers
+                    .iter()
+                    .map(|header| duplicated.contains(&header))
+                    .collect();
+                FieldDuplicates::Array(grouped)
+            }
+            DuplicateHeaders::Suffix => {
+                let mut taken: HashSet<String> = headers.iter().cloned().collect();
+                let mut seen = HashSet::with_capacity(headers.len());
+                for header in headers.iter_mut() {
+                    if seen.insert(header.clone()) {
+                        continue;
+                    }
+                    let renamed = (2..)
+                        .map(|n| format!("{header}_{n}"))
+                        .find(|candidate| !taken.contains(candidate))
+                        .expect("an unused suffix exists");
+                    taken.insert(renamed.clone());
+                    seen.insert(renamed.clone());
+                    *header = renamed;
+                }
+                FieldDuplicates::Last
+            }
+        })
+    }
+}
+
+/// How fields are inserted into Hash rows once duplicate headers are resolved.
+pub(crate) enum FieldDuplicates {
+    Last,
+    First,
+    /// For each column, whether its header is shared with another column, and its
+    /// value goes in an Array.
+    Array(Vec<bool>),
+}
+
+/// The nil markers of each field of a record, by position.
+pub(crate) struct FieldNils {
+    /// The distinct lists of markers, the first being the one for every column.
+    sets: Vec<Vec<String>>,
+    /// For each column, the index of its list in `sets`.
+    columns: Vec<usize>,
+}
+
+impl FieldNils {
+    #[inline]
+    fn contains(&self, index: usize, field: &str) -> bool {
+        let set = self.columns.get(index).copied().unwrap_or(0);
+        self.sets[set].iter().any(|marker| marker == field)
+    }
+}
+
+/// How the fields of a record are turned into values.
+pub(crate) struct FieldValues<'a> {
+    /// A value read as nil in every column, whether it is quoted or not.
+    pub null_string: Option<Cow<'a, str>>,
+    /// Values read as nil when they appear unquoted.
+    pub nils: Option<FieldNils>,
+    pub converters: Option<FieldConverters>,
+    /// The types values are cast to.
+    pub types: Option<FieldTypes>,
+    /// Values given to the fields missing from short records.
+    pub defaults: Option<FieldDefaults<'a>>,
+    /// What to do with the fields past the last header.
+    pub extras: FieldExtras,
+    /// How fields are inserted under duplicate headers.
+    pub duplicates: FieldDuplicates,
+    /// The number of fields in the header.
+    pub columns: usize,
+    pub ignore_null_bytes: bool,
+}
+
+impl<'a> FieldValues<'a> {
+    /// The value of field `index` when the record doesn't have it: its default,
+    /// or nil.
+    #[inline]
+    pub(crate) fn missing(&self, index: usize) -> Option<Field<'a>> {
+        self.defaults
+            .as_ref()
+            .and_then(|defaults| defaults.get(index))
+            .map(Field::Str)
+    }
+
+    /// Casts the value of field `index` to the type of its column, keeping it as
+    /// text when the column has none or the value isn't of the type.
+    #[inline]
+    pub(crate) fn cast(&self, index: usize, value: CowStr<'a>) -> Field<'a> {
+        match self
+            .types
+            .as_ref()
+            .and_then(|types| types.cast(index, &value.0))
+        {
+            Some(cast) => Field::Cast(cast),
+            None => Field::Str(value),
+        }
+    }
+
+    /// Returns true if `field`, the field `index` of a record, stands for nil.
+    #[inline]
+    pub(crate) fn is_nil(&self, index: usize, field: &str, quoted: &QuotedFields) -> bool {
+        if quoted.is_absent(index) || Some(field) == self.null_string.as_deref() {
+            return true;
+        }
+        match &self.nils {
+            Some(nils) => !quoted.contains(index) && nils.contains(index, field),
+            None => false,
+        }
+    }
+}
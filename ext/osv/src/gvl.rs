@@ -0,0 +1,59 @@
+use std::{
+    ffi::{c_int, c_void},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+/// Mirrors `RB_NOGVL_INTR_FAIL` from `ruby/thread.h`: skip the call instead of
+/// raising when an interrupt is pending, so Ruby never unwinds through Rust frames.
+const RB_NOGVL_INTR_FAIL: c_int = 0x1;
+
+struct Call<F, R> {
+    func: Option<F>,
+    result: Option<std::thread::Result<R>>,
+}
+
+unsafe extern "C" fn call_without_gvl<F, R>(data: *mut c_void) -> *mut c_void
+where
+    F: FnOnce() -> R,
+{
+    let call = &mut *(data as *mut Call<F, R>);
+    if let Some(func) = call.func.take() {
+        call.result = Some(panic::catch_unwind(AssertUnwindSafe(func)));
+    }
+    ptr::null_mut()
+}
+
+/// Runs `func` with the GVL released, so other Ruby threads can run while it works.
+///
+/// `func` must not touch Ruby objects or call into the Ruby API. If an interrupt
+/// is already pending, `func` runs with the GVL held instead and Ruby handles the
+/// interrupt the next time it checks for one.
+pub(crate) fn without_gvl<F, R>(func: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let mut call = Call {
+        func: Some(func),
+        result: None,
+    };
+
+    unsafe {
+        rb_sys::rb_nogvl(
+            Some(call_without_gvl::<F, R>),
+            &mut call as *mut Call<F, R> as *mut c_void,
+            None,
+            ptr::null_mut(),
+            RB_NOGVL_INTR_FAIL,
+        );
+    }
+
+    match call.result {
+        Some(Ok(result)) => result,
+        Some(Err(payload)) => panic::resume_unwind(payload),
+        None => (call
+            .func
+            .take()
+            .expect("function is only taken when it is called"))(),
+    }
+}
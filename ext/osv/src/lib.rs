@@ -1,16 +1,38 @@
 mod allocator;
 mod csv;
+mod gvl;
 mod reader;
 mod utils;
 
 use crate::reader::*;
 
-use magnus::{Error, Ruby};
+use crate::csv::ArrowBatch;
+use magnus::{Class, Error, Module, Ruby};
 
 /// Initializes the Ruby extension and defines methods.
 #[magnus::init]
 fn init(ruby: &Ruby) -> Result<(), Error> {
     let module = ruby.define_module("OSV")?;
     module.define_module_function("for_each", magnus::method!(parse_csv, -1))?;
+    module.define_module_function("open", magnus::method!(open_csv, -1))?;
+    module.define_module_function("read", magnus::method!(read_csv, -1))?;
+    module.define_module_function("parse", magnus::method!(parse_csv_content, -1))?;
+    module.define_module_function("count", magnus::function!(count_csv, -1))?;
+    module.define_module_function("validate", magnus::function!(validate_csv, -1))?;
+    module.define_module_function("to_jsonl", magnus::function!(to_jsonl, -1))?;
+    module.define_module_function("transform", magnus::function!(transform_csv, -1))?;
+    module.define_module_function("to_parquet", magnus::function!(to_parquet, -1))?;
+    module.define_module_function("to_arrow", magnus::function!(to_arrow, -1))?;
+    module.define_module_function("read_arrow", magnus::method!(read_arrow, -1))?;
+    module.define_module_function("profile", magnus::function!(profile_csv, -1))?;
+    module.define_module_function("aggregate", magnus::function!(aggregate_csv, -1))?;
+
+    let batch = module.define_class("ArrowBatch", ruby.class_object())?;
+    batch.undef_default_alloc_func();
+    batch.define_method("num_rows", magnus::method!(ArrowBatch::num_rows, 0))?;
+    batch.define_method("num_columns", magnus::method!(ArrowBatch::num_columns, 0))?;
+    batch.define_method("column_names", magnus::method!(ArrowBatch::column_names, 0))?;
+    // Writing to raw addresses is only done by `to_arrow`, which allocates the structs
+    batch.define_private_method("export_to_c", magnus::method!(ArrowBatch::export_to_c, 2))?;
     Ok(())
 }
@@ -1,10 +1,19 @@
-use crate::csv::{CowStr, CsvRecord, RecordReaderBuilder};
+use crate::csv::{
+    field_text, map_input, Aggregate, ArrowBatch, BatchBuilder, Bound, ColumnStats, ColumnarFormat,
+    ColumnarWriter, CsvRecord, Field, GroupKey, GroupResult, InputKind, JsonLine, OrderedRow,
+    ParallelOptions, PinnedString, Profiler, RecordParser, RecordReaderBuilder, RowValue,
+    RubyWriter, ValidationReport,
+};
 use crate::utils::*;
 use ahash::RandomState;
-use csv::Trim;
 use magnus::value::ReprValue;
-use magnus::{Error, IntoValue, KwArgs, Ruby, Symbol, Value};
+use magnus::{
+    Error, IntoValue, KwArgs, Module, RArray, RClass, RHash, RString, Ruby, TryConvert, Value,
+};
+use memmap2::Mmap;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
 
 /// Valid result types for CSV parsing
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -23,140 +32,813 @@ impl ResultType {
     }
 }
 
-/// Arguments for creating an enumerator
-#[derive(Debug)]
-struct EnumeratorArgs {
-    rb_self: Value,
-    to_read: Value,
-    has_headers: bool,
-    delimiter: u8,
-    quote_char: u8,
-    null_string: Option<String>,
-    result_type: String,
-    flexible: bool,
-    trim: Option<String>,
-    ignore_null_bytes: bool,
-    lossy: bool,
-}
-
 /// Parses a CSV file with the given configuration.
 ///
 /// # Safety
 /// This function uses unsafe code to get the Ruby runtime and leak memory for static references.
 /// This is necessary for Ruby integration but should be used with caution.
 pub fn parse_csv(rb_self: Value, args: &[Value]) -> Result<Value, Error> {
+    read_input(rb_self, args, "for_each", None)
+}
+
+/// Parses the CSV file at a path, given as a String or `Pathname`.
+pub fn open_csv(rb_self: Value, args: &[Value]) -> Result<Value, Error> {
+    read_input(rb_self, args, "open", Some(InputKind::Path))
+}
+
+/// Parses CSV read from an IO, or any other object responding to `read`.
+pub fn read_csv(rb_self: Value, args: &[Value]) -> Result<Value, Error> {
+    read_input(rb_self, args, "read", Some(InputKind::Io))
+}
+
+/// Parses CSV content held in a Ruby String, reading straight from the string's
+/// bytes instead of copying them into a buffer first. The counterpart of
+/// `OSV.open` and `OSV.read` for content.
+///
+/// The string is frozen (sharing its buffer, not copying it) and pinned for the
+/// duration of the parse, so neither Ruby code nor the GC can change it underneath
+/// the reader.
+pub fn parse_csv_content(rb_self: Value, args: &[Value]) -> Result<Value, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+
+    let args = parse_read_csv_args(&ruby, args)?;
+    let string = RString::try_convert(args.to_read)?;
+
+    if !ruby.block_given() {
+        return Ok(
+            create_enumerator(rb_self, "parse", string.as_value(), args.kwargs)
+                .into_value_with(&ruby),
+        );
+    }
+
+    let pinned = PinnedString::new(string);
+    // Content that is already in memory is never mapped or split across threads
+    let args = ReadCsvArgs {
+        parallel: None,
+        mmap: false,
+        ..args
+    };
+    yield_records(&ruby, args, Some(pinned.as_bytes()))
+}
+
+/// Reads a path or IO input, checking it is of the `expected` kind when the
+/// method only accepts one.
+fn read_input(
+    rb_self: Value,
+    args: &[Value],
+    method: &'static str,
+    expected: Option<InputKind>,
+) -> Result<Value, Error> {
     //  SAFETY: We're in a Ruby callback, so Ruby runtime is guaranteed to be initialized
     let ruby = unsafe { Ruby::get_unchecked() };
 
-    let ReadCsvArgs {
-        to_read,
-        has_headers,
-        delimiter,
-        quote_char,
-        null_string,
-        result_type,
-        flexible,
-        trim,
-        ignore_null_bytes,
-        lossy,
-    } = parse_read_csv_args(&ruby, args)?;
+    let args = parse_read_csv_args(&ruby, args)?;
+
+    let kind = match InputKind::of(args.to_read)? {
+        // `open` reads a list of files as readily as a single one
+        InputKind::Files if expected == Some(InputKind::Path) => InputKind::Path,
+        kind => kind,
+    };
+    if let Some(expected) = expected.filter(|&expected| expected != kind) {
+        let wanted = match expected {
+            InputKind::Path => "a file path (String or Pathname)",
+            InputKind::Io => "an IO responding to `read`",
+            InputKind::Files => "an Array of file paths",
+        };
+        return Err(Error::new(
+            ruby.exception_type_error(),
+            format!("OSV.{method} expects {wanted}, got {}", unsafe {
+                args.to_read.classname()
+            }),
+        ));
+    }
 
     if !ruby.block_given() {
-        return create_enumerator(
-            &ruby,
-            EnumeratorArgs {
-                rb_self,
-                to_read,
-                has_headers,
-                delimiter,
-                quote_char,
-                null_string,
-                result_type,
-                flexible,
-                trim: match trim {
-                    Trim::All => Some("all".to_string()),
-                    Trim::Headers => Some("headers".to_string()),
-                    Trim::Fields => Some("fields".to_string()),
-                    _ => None,
-                },
-                ignore_null_bytes,
-                lossy,
-            },
-        )
-        .map(|yield_enum| yield_enum.into_value_with(&ruby));
+        return Ok(
+            create_enumerator(rb_self, method, args.to_read, args.kwargs).into_value_with(&ruby),
+        );
     }
 
-    let result_type = ResultType::from_str(&result_type).ok_or_else(|| {
+    let mapping = map_requested_input(args.to_read, args.mmap && !args.glob, args.parallel)?;
+    yield_records(&ruby, args, mapping.as_deref())
+}
+
+/// Configures a reader of the input with every reading option in `args`, so that
+/// each method reads the same rows `for_each` yields for the same arguments.
+///
+/// When `in_memory` is set, records are read from it rather than from `to_read`.
+fn builder_from_args<'a, 'r, T: RecordParser<'a>>(
+    ruby: &'r Ruby,
+    args: &ReadCsvArgs,
+    in_memory: Option<&'a [u8]>,
+) -> RecordReaderBuilder<'a, 'r, T> {
+    RecordReaderBuilder::new(ruby, args.to_read)
+        .has_headers(args.has_headers)
+        .flexible(args.flexible)
+        .trim(args.trim)
+        .delimiter(args.delimiter)
+        .quote_char(args.quote_char)
+        .null_string(args.null_string.clone())
+        .ignore_null_bytes(args.ignore_null_bytes)
+        .lossy(args.lossy)
+        .offset(args.offset)
+        .limit(args.limit)
+        .sample(args.sample)
+        .unique(args.unique.clone())
+        .parallel(args.parallel)
+        .in_memory(in_memory)
+        .buffer_size(args.buffer_size)
+        .fileno(args.fileno)
+        .glob(args.glob)
+        .union_headers(args.union_headers)
+        .source_column(args.source_column.clone())
+        .entry(args.entry.clone())
+        .converters(args.converters.clone())
+        .nil_values(args.nil_values.clone())
+        .missing_values(args.missing_values.clone())
+        .filled_column(args.filled_column.clone())
+        .extra_fields(args.extra_fields.clone())
+        .duplicate_headers(args.duplicate_headers)
+        .schema(args.schema.clone())
+        .max_errors(args.max_errors)
+}
+
+/// Reads every record of the input and yields it to the block given by the caller.
+///
+/// When `in_memory` is set, records are read from it rather than from `to_read`.
+fn yield_records(ruby: &Ruby, args: ReadCsvArgs, in_memory: Option<&[u8]>) -> Result<Value, Error> {
+    // Batches are typed and sized the way `OSV.read_arrow` does by default
+    if args.result_type == "arrow" {
+        return yield_batches(ruby, args, in_memory, ColumnarArgs::default());
+    }
+
+    let result_type = ResultType::from_str(&args.result_type).ok_or_else(|| {
         Error::new(
             ruby.exception_runtime_error(),
             "Invalid result type, expected 'hash' or 'array'",
         )
     })?;
 
-    match result_type {
+    // With a schema or deduplication, the report of what was skipped is returned
+    // once every row is yielded
+    let report = match result_type {
         ResultType::Hash => {
-            let builder = RecordReaderBuilder::<
-                HashMap<&'static str, Option<CowStr<'_>>, RandomState>,
-            >::new(&ruby, to_read)
-            .has_headers(has_headers)
-            .flexible(flexible)
-            .trim(trim)
-            .delimiter(delimiter)
-            .quote_char(quote_char)
-            .null_string(null_string)
-            .ignore_null_bytes(ignore_null_bytes)
-            .lossy(lossy)
-            .build()?;
+            let mut records =
+                builder_from_args::<HashMap<&'static str, RowValue<'_>, RandomState>>(
+                    ruby, &args, in_memory,
+                )
+                .build()?;
 
             let ruby = unsafe { Ruby::get_unchecked() };
-            for result in builder {
-                let record = result?;
-                let _: Value = ruby.yield_value(CsvRecord::<ahash::RandomState>::Map(record))?;
+            while let Some(result) = records.next() {
+                let row = CsvRecord::<ahash::RandomState>::Map(result?).into_value_with(&ruby);
+                add_filled_fields(&ruby, row, records.filled_fields())?;
+                let _: Value = ruby.yield_value(row)?;
             }
+            read_report(&ruby, records.validation_report(), records.duplicates())?
         }
         ResultType::Array => {
-            let builder = RecordReaderBuilder::<Vec<Option<CowStr<'_>>>>::new(&ruby, to_read)
-                .has_headers(has_headers)
-                .flexible(flexible)
-                .trim(trim)
-                .delimiter(delimiter)
-                .quote_char(quote_char)
-                .null_string(null_string)
-                .ignore_null_bytes(ignore_null_bytes)
-                .lossy(lossy)
-                .build()?;
+            let mut records =
+                builder_from_args::<Vec<Option<Field<'_>>>>(ruby, &args, in_memory).build()?;
 
             let ruby = unsafe { Ruby::get_unchecked() };
-            for result in builder {
-                let record = result?;
-                let _: Value = ruby.yield_value(CsvRecord::<ahash::RandomState>::Vec(record))?;
+            while let Some(result) = records.next() {
+                let row = CsvRecord::<ahash::RandomState>::Vec(result?).into_value_with(&ruby);
+                add_filled_fields(&ruby, row, records.filled_fields())?;
+                let _: Value = ruby.yield_value(row)?;
             }
+            read_report(&ruby, records.validation_report(), records.duplicates())?
         }
+    };
+
+    Ok(report)
+}
+
+/// Adds the names of the fields the row was missing to `row`, under the filled
+/// column: as a key of Hash rows, or as the last element of Array rows.
+fn add_filled_fields(
+    ruby: &Ruby,
+    row: Value,
+    filled: Option<(&str, &[String])>,
+) -> Result<(), Error> {
+    let Some((column, fields)) = filled else {
+        return Ok(());
+    };
+    let names = ruby.ary_from_iter(fields.iter().map(String::as_str));
+    if let Some(hash) = RHash::from_value(row) {
+        hash.aset(column, names)
+    } else if let Some(array) = RArray::from_value(row) {
+        array.push(names)
+    } else {
+        Ok(())
     }
+}
 
+/// Counts the records in the input without converting any of them to Ruby objects.
+///
+/// Accepts the same options as `for_each`, so the result is the number of rows
+/// `for_each` would yield for the same arguments.
+pub fn count_csv(args: &[Value]) -> Result<usize, Error> {
     let ruby = unsafe { Ruby::get_unchecked() };
-    Ok(ruby.qnil().into_value_with(&ruby))
+
+    let args = parse_read_csv_args(&ruby, args)?;
+
+    let mapping = map_requested_input(args.to_read, args.mmap && !args.glob, args.parallel)?;
+
+    let reader = builder_from_args::<()>(&ruby, &args, mapping.as_deref()).build()?;
+
+    let mut count = 0;
+    for result in reader {
+        result?;
+        count += 1;
+    }
+    Ok(count)
 }
 
-/// Creates an enumerator for lazy CSV parsing
-fn create_enumerator(ruby: &Ruby, args: EnumeratorArgs) -> Result<magnus::Enumerator, Error> {
-    let kwargs = ruby.hash_new();
-    kwargs.aset(Symbol::new("has_headers"), args.has_headers)?;
-    kwargs.aset(
-        Symbol::new("col_sep"),
-        String::from_utf8(vec![args.delimiter]).unwrap(),
-    )?;
-    kwargs.aset(
-        Symbol::new("quote_char"),
-        String::from_utf8(vec![args.quote_char]).unwrap(),
+/// Checks the header and every row of the input against `schema:`, returning a
+/// report of the problems found instead of raising at the first one.
+///
+/// Rows are never converted to Ruby objects. Accepts the same options as `for_each`,
+/// and reads the input the same way, so the report is the one `for_each` returns.
+pub fn validate_csv(args: &[Value]) -> Result<Value, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+
+    let args = parse_read_csv_args(&ruby, args)?;
+
+    if args.schema.is_none() {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            "OSV.validate requires a schema: or table_schema:",
+        ));
+    }
+
+    let mapping = map_requested_input(args.to_read, args.mmap && !args.glob, args.parallel)?;
+
+    let mut reader = builder_from_args::<()>(&ruby, &args, mapping.as_deref()).build()?;
+
+    for result in reader.by_ref() {
+        result?;
+    }
+    read_report(&ruby, reader.validation_report(), reader.duplicates())
+}
+
+/// Converts the input to JSON Lines, writing one JSON object per row to `output`,
+/// a file path or an IO responding to `write`. With `result_type: "array"`, rows
+/// are written as JSON arrays instead.
+///
+/// Rows are serialized straight from the parsed fields, so no Ruby objects are
+/// created for them. Accepts the same options as `for_each`, and returns the
+/// number of rows written.
+pub fn to_jsonl(args: &[Value]) -> Result<usize, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+
+    let (args, output) = parse_write_csv_args(&ruby, args)?;
+
+    let result_type = ResultType::from_str(&args.result_type).ok_or_else(|| {
+        Error::new(
+            ruby.exception_runtime_error(),
+            "Invalid result type, expected 'hash' or 'array'",
+        )
+    })?;
+
+    let mapping = map_requested_input(args.to_read, args.mmap && !args.glob, args.parallel)?;
+    let mut writer = BufWriter::with_capacity(args.buffer_size, RubyWriter::new(output)?);
+
+    let mut written = 0;
+    match result_type {
+        ResultType::Hash => {
+            let mut records =
+                builder_from_args::<OrderedRow<'_>>(&ruby, &args, mapping.as_deref()).build()?;
+
+            while let Some(result) = records.next() {
+                let row = result?;
+                let line = JsonLine {
+                    row: &row,
+                    filled: records.filled_fields(),
+                };
+                write_json_line(&ruby, &mut writer, &line)?;
+                written += 1;
+            }
+        }
+        ResultType::Array => {
+            let mut records =
+                builder_from_args::<Vec<Option<Field<'_>>>>(&ruby, &args, mapping.as_deref())
+                    .build()?;
+
+            while let Some(result) = records.next() {
+                let row = result?;
+                let line = JsonLine {
+                    row: &row,
+                    filled: records.filled_fields(),
+                };
+                write_json_line(&ruby, &mut writer, &line)?;
+                written += 1;
+            }
+        }
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(RubyWriter::finish)
+        .map_err(|e| write_error(&ruby, e))?;
+    Ok(written)
+}
+
+/// Reshapes the input into CSV written to `output`, a file path or an IO responding
+/// to `write`: keeping the columns of `select:` in that order, renaming the ones in
+/// `rename:`, keeping the rows meeting `where:`, and writing with `col_sep_out:`
+/// and `quote_char_out:`.
+///
+/// Rows go from reader to writer without becoming Ruby objects. Fields are quoted
+/// only when they need to be. Accepts the same options as `for_each`, and returns
+/// the number of rows written.
+pub fn transform_csv(args: &[Value]) -> Result<usize, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+
+    let (
+        args,
+        output,
+        TransformArgs {
+            selection,
+            row_filter,
+            delimiter: delimiter_out,
+            quote_char: quote_char_out,
+        },
+    ) = parse_transform_args(&ruby, args)?;
+
+    check_output_is_not_input(&ruby, &args, output)?;
+
+    let mapping = map_requested_input(args.to_read, args.mmap && !args.glob, args.parallel)?;
+
+    let records = builder_from_args::<Vec<Option<Field<'_>>>>(&ruby, &args, mapping.as_deref())
+        .row_filter(row_filter)
+        .build()?;
+
+    let (positions, headers) = selection.resolve(records.columns())?;
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter_out.unwrap_or(args.delimiter))
+        .quote(quote_char_out.unwrap_or(args.quote_char))
+        .buffer_capacity(args.buffer_size)
+        .from_writer(RubyWriter::new(output)?);
+
+    let csv_error = |e: csv::Error| write_error(&ruby, io::Error::from(e));
+
+    // Inputs without headers are named c0, c1… for `select:`, but no header is written
+    if args.has_headers {
+        writer.write_record(&headers).map_err(csv_error)?;
+    }
+
+    let mut written = 0;
+    for result in records {
+        let row = result?;
+        let fields = positions
+            .iter()
+            .map(|&i| field_text(row.get(i).and_then(Option::as_ref)));
+        writer.write_record(fields).map_err(csv_error)?;
+        written += 1;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(RubyWriter::finish)
+        .map_err(|e| write_error(&ruby, e))?;
+    Ok(written)
+}
+
+/// Raises when `output` is one of the files given as input, which creating the
+/// output would truncate before its rows are read. Paths are compared once
+/// resolved, so relative paths and symlinks to the input are caught too.
+fn check_output_is_not_input(ruby: &Ruby, args: &ReadCsvArgs, output: Value) -> Result<(), Error> {
+    let path_of = |value: Value| -> Result<String, Error> {
+        if value.is_kind_of(ruby.class_string()) {
+            String::try_convert(value)
+        } else {
+            value.funcall("to_path", ())
+        }
+    };
+
+    if InputKind::of(output).ok() != Some(InputKind::Path) {
+        return Ok(());
+    }
+    // An output that doesn't exist yet can't be an input
+    let Ok(output_path) = std::fs::canonicalize(path_of(output)?) else {
+        return Ok(());
+    };
+    let inputs: Vec<String> = if args.glob {
+        let dir: RClass = ruby.class_object().const_get("Dir")?;
+        dir.funcall("glob", (path_of(args.to_read)?,))?
+    } else {
+        match InputKind::of(args.to_read).ok() {
+            Some(InputKind::Path) => vec![path_of(args.to_read)?],
+            Some(InputKind::Files) => RArray::from_value(args.to_read)
+                .map(|files| files.to_vec::<Value>())
+                .transpose()?
+                .unwrap_or_default()
+                .into_iter()
+                .map(path_of)
+                .collect::<Result<_, _>>()?,
+            _ => return Ok(()),
+        }
+    };
+    if inputs
+        .iter()
+        .any(|input| std::fs::canonicalize(input).is_ok_and(|input| input == output_path))
+    {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!(
+                "output {} is also read as input, and would be truncated before being read",
+                output_path.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Converts the input to a Parquet file written to `output`, a file path or an IO
+/// responding to `write`.
+///
+/// Each column is written with the type given to it in `types:`, or else the type
+/// inferred from its values in the first `batch_size` rows. Returns the number of
+/// rows written.
+///
+/// A later value that isn't of its column's type raises, naming its row and
+/// column, and leaves the output without its footer, so it can't be read.
+pub fn to_parquet(args: &[Value]) -> Result<usize, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let (args, output, columnar) = parse_columnar_args(&ruby, args)?;
+    let format = ColumnarFormat::Parquet {
+        compression: columnar.compression.unwrap_or_default(),
+    };
+    write_columnar(&ruby, args, output, columnar, format)
+}
+
+/// Converts the input to an Arrow IPC file (Feather version 2) written to
+/// `output`, typing its columns like `to_parquet`.
+pub fn to_arrow(args: &[Value]) -> Result<usize, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let (args, output, columnar) = parse_columnar_args(&ruby, args)?;
+    if columnar.compression.is_some() {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            "compression is only supported by OSV.to_parquet",
+        ));
+    }
+    write_columnar(&ruby, args, output, columnar, ColumnarFormat::Arrow)
+}
+
+/// Streams the records of the input into the record batches of a columnar file.
+///
+/// The first `batch_size` rows are held back until the type of every column is
+/// known; the rest are written as they are read.
+fn write_columnar(
+    ruby: &Ruby,
+    args: ReadCsvArgs,
+    output: Value,
+    columnar: ColumnarArgs,
+    format: ColumnarFormat,
+) -> Result<usize, Error> {
+    let mapping = map_requested_input(args.to_read, args.mmap && !args.glob, args.parallel)?;
+
+    let mut records =
+        builder_from_args::<Vec<Option<Field<'_>>>>(ruby, &args, mapping.as_deref()).build()?;
+
+    let first = records
+        .by_ref()
+        .take(columnar.batch_size)
+        .collect::<Result<Vec<_>, _>>()?;
+    let columns = records.columns().to_vec();
+    let types = columnar.types.resolve(&columns, &first)?;
+
+    let mut writer = ColumnarWriter::new(
+        RubyWriter::new(output)?,
+        format,
+        &columns,
+        &types,
+        columnar.batch_size,
     )?;
-    kwargs.aset(Symbol::new("nil_string"), args.null_string)?;
-    kwargs.aset(Symbol::new("result_type"), Symbol::new(args.result_type))?;
-    kwargs.aset(Symbol::new("flexible"), args.flexible)?;
-    kwargs.aset(Symbol::new("trim"), args.trim.map(Symbol::new))?;
-    kwargs.aset(Symbol::new("ignore_null_bytes"), args.ignore_null_bytes)?;
-    kwargs.aset(Symbol::new("lossy"), args.lossy)?;
-    Ok(args
-        .rb_self
-        .enumeratorize("for_each", (args.to_read, KwArgs(kwargs))))
+
+    let mut written = 0;
+    for row in &first {
+        writer.push(row)?;
+        written += 1;
+    }
+    drop(first);
+    for result in records {
+        writer.push(&result?)?;
+        written += 1;
+    }
+
+    writer.finish()?;
+    Ok(written)
+}
+
+/// Reads the input as Arrow record batches of `batch_size` rows, yielding each to
+/// the block as an `OSV::ArrowBatch` that Arrow libraries import through the C
+/// data interface.
+///
+/// Columns are typed like `to_parquet` types them, from `types:` or the first
+/// batch. Returns an enumerator when no block is given.
+pub fn read_arrow(rb_self: Value, args: &[Value]) -> Result<Value, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+    let (args, columnar) = parse_read_arrow_args(&ruby, args)?;
+    if columnar.compression.is_some() {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            "compression is only supported by OSV.to_parquet",
+        ));
+    }
+
+    if !ruby.block_given() {
+        return Ok(
+            create_enumerator(rb_self, "read_arrow", args.to_read, args.kwargs)
+                .into_value_with(&ruby),
+        );
+    }
+
+    let mapping = map_requested_input(args.to_read, args.mmap && !args.glob, args.parallel)?;
+    yield_batches(&ruby, args, mapping.as_deref(), columnar)
+}
+
+/// Reads every record of the input into record batches, yielding each to the
+/// block given by the caller once it is full.
+///
+/// The first batch is only yielded once the type of every column is known, so
+/// it is held as rows until then.
+fn yield_batches(
+    ruby: &Ruby,
+    args: ReadCsvArgs,
+    in_memory: Option<&[u8]>,
+    columnar: ColumnarArgs,
+) -> Result<Value, Error> {
+    let mut records =
+        builder_from_args::<Vec<Option<Field<'_>>>>(ruby, &args, in_memory).build()?;
+
+    let first = records
+        .by_ref()
+        .take(columnar.batch_size)
+        .collect::<Result<Vec<_>, _>>()?;
+    let columns = records.columns().to_vec();
+    let types = columnar.types.resolve(&columns, &first)?;
+    let mut batches = BatchBuilder::new(&columns, &types, columnar.batch_size);
+
+    for row in &first {
+        if let Some(batch) = batches.push(row)? {
+            let _: Value = ruby.yield_value(ArrowBatch::new(batch))?;
+        }
+    }
+    drop(first);
+    for result in records.by_ref() {
+        if let Some(batch) = batches.push(&result?)? {
+            let _: Value = ruby.yield_value(ArrowBatch::new(batch))?;
+        }
+    }
+    if let Some(batch) = batches.finish()? {
+        let _: Value = ruby.yield_value(ArrowBatch::new(batch))?;
+    }
+
+    read_report(ruby, records.validation_report(), records.duplicates())
+}
+
+/// Computes statistics of every column of the input without converting its rows
+/// to Ruby objects: the number of nulls and distinct values, the shortest and
+/// longest value, the least and greatest value of the inferred type, and the
+/// most frequent values.
+///
+/// Accepts the same options as `for_each`, and returns a Hash of the number of
+/// rows and of the statistics by column.
+pub fn profile_csv(args: &[Value]) -> Result<Value, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+
+    let (args, profile) = parse_profile_args(&ruby, args)?;
+
+    let mapping = map_requested_input(args.to_read, args.mmap && !args.glob, args.parallel)?;
+
+    let mut records =
+        builder_from_args::<Vec<Option<Field<'_>>>>(&ruby, &args, mapping.as_deref()).build()?;
+
+    let mut profiler = Profiler::new(profile.top, profile.distinct_limit);
+    for result in records.by_ref() {
+        profiler.push(&result?);
+    }
+    let (rows, stats) = profiler.finish(records.columns());
+    Ok(profile_to_hash(&ruby, rows, &stats)?.into_value_with(&ruby))
+}
+
+fn profile_to_hash(ruby: &Ruby, rows: usize, stats: &[ColumnStats]) -> Result<RHash, Error> {
+    let bound = |bound: &Option<Bound>| match bound {
+        Some(Bound::Text(text)) => text.as_str().into_value_with(ruby),
+        Some(Bound::Cast(cast)) => (*cast).into_value_with(ruby),
+        None => ruby.qnil().as_value(),
+    };
+
+    let columns = ruby.hash_new_capa(stats.len());
+    for column in stats {
+        let top = ruby.ary_new_capa(column.top.len());
+        for (value, count) in &column.top {
+            top.push((value.as_str(), *count))?;
+        }
+
+        let hash = ruby.hash_new();
+        hash.aset(
+            ruby.to_symbol("type"),
+            ruby.to_symbol(column.column_type.name()),
+        )?;
+        hash.aset(ruby.to_symbol("nulls"), column.nulls)?;
+        hash.aset(ruby.to_symbol("distinct"), column.distinct)?;
+        hash.aset(
+            ruby.to_symbol("distinct_estimated"),
+            column.distinct_estimated,
+        )?;
+        hash.aset(ruby.to_symbol("min_length"), column.min_length)?;
+        hash.aset(ruby.to_symbol("max_length"), column.max_length)?;
+        hash.aset(ruby.to_symbol("min"), bound(&column.min))?;
+        hash.aset(ruby.to_symbol("max"), bound(&column.max))?;
+        hash.aset(ruby.to_symbol("top"), top)?;
+        columns.aset(column.name.as_str(), hash)?;
+    }
+
+    let hash = ruby.hash_new();
+    hash.aset(ruby.to_symbol("rows"), rows)?;
+    hash.aset(ruby.to_symbol("columns"), columns)?;
+    Ok(hash)
+}
+
+/// Groups the rows of the input by the columns of `group_by:` and summarizes each
+/// group: counting its rows with `count: true`, and computing the `sum:`, `min:`,
+/// `max:` and `mean:` of numeric columns. Rows are read and summarized without
+/// becoming Ruby objects, and only the groups are held in memory.
+///
+/// Returns a Hash of each group's key to its results, in the order groups are
+/// first seen, or the results alone when no column is grouped by. Accepts the
+/// same options as `for_each`, and `where:` like `OSV.transform`.
+pub fn aggregate_csv(args: &[Value]) -> Result<Value, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+
+    let (
+        args,
+        AggregateArgs {
+            aggregates,
+            row_filter,
+        },
+    ) = parse_aggregate_args(&ruby, args)?;
+
+    let mapping = map_requested_input(args.to_read, args.mmap && !args.glob, args.parallel)?;
+
+    let mut records = builder_from_args::<Vec<Option<Field<'_>>>>(&ruby, &args, mapping.as_deref())
+        .row_filter(row_filter)
+        .build()?;
+
+    let mut aggregator = aggregates.resolve(records.columns())?;
+    for result in records.by_ref() {
+        aggregator.push(&result?)?;
+    }
+    let groups = aggregator.finish();
+
+    if aggregates.group_by.is_empty() {
+        if let Some((_, result)) = groups.first() {
+            return Ok(
+                group_result_to_hash(&ruby, result, &aggregates.columns)?.into_value_with(&ruby)
+            );
+        }
+    }
+
+    let hash = ruby.hash_new_capa(groups.len());
+    for (key, result) in &groups {
+        hash.aset(
+            group_key_to_value(&ruby, key),
+            group_result_to_hash(&ruby, result, &aggregates.columns)?,
+        )?;
+    }
+    Ok(hash.into_value_with(&ruby))
+}
+
+/// The key of a group in Ruby: the value of its group column, or an Array of the
+/// values of its group columns when there are several.
+fn group_key_to_value(ruby: &Ruby, key: &GroupKey) -> Value {
+    match key.as_slice() {
+        [value] => value.as_deref().into_value_with(ruby),
+        values => ruby
+            .ary_from_iter(values.iter().map(Option::as_deref))
+            .into_value_with(ruby),
+    }
+}
+
+/// The results of a group in Ruby: its count, and a Hash of column names to their
+/// value under the name of each function. `names` are the columns each function
+/// was asked for, in the order of the results.
+fn group_result_to_hash(
+    ruby: &Ruby,
+    result: &GroupResult,
+    names: &[(Aggregate, Vec<String>)],
+) -> Result<RHash, Error> {
+    let hash = ruby.hash_new();
+    if let Some(count) = result.count {
+        hash.aset(ruby.to_symbol("count"), count)?;
+    }
+    for ((aggregate, values), (_, names)) in result.values.iter().zip(names) {
+        let columns = ruby.hash_new_capa(values.len());
+        for (name, value) in names.iter().zip(values) {
+            columns.aset(name.as_str(), *value)?;
+        }
+        hash.aset(ruby.to_symbol(aggregate.name()), columns)?;
+    }
+    Ok(hash)
+}
+
+/// Writes `line` as JSON, followed by a newline.
+fn write_json_line(
+    ruby: &Ruby,
+    writer: &mut impl Write,
+    line: &impl Serialize,
+) -> Result<(), Error> {
+    serde_json::to_writer(&mut *writer, line)
+        .map_err(io::Error::from)
+        .and_then(|()| writer.write_all(b"\n"))
+        .map_err(|e| write_error(ruby, e))
+}
+
+fn write_error(ruby: &Ruby, err: io::Error) -> Error {
+    Error::new(
+        ruby.exception_io_error(),
+        format!("Failed to write output: {err}"),
+    )
+}
+
+/// Returns what reading left out: the validation report when there is a schema,
+/// with the number of repeated rows skipped under `duplicates:` when
+/// deduplicating, or nil when neither applies.
+fn read_report(
+    ruby: &Ruby,
+    validation: Option<&ValidationReport>,
+    duplicates: Option<usize>,
+) -> Result<Value, Error> {
+    let report = match validation {
+        Some(report) => validation_report_to_hash(report)?,
+        None if duplicates.is_some() => ruby.hash_new(),
+        None => return Ok(ruby.qnil().into_value_with(ruby)),
+    };
+    if let Some(duplicates) = duplicates {
+        report.aset(ruby.to_symbol("duplicates"), duplicates)?;
+    }
+    Ok(report.into_value_with(ruby))
+}
+
+/// Turns a validation report into a Hash like
+/// `{ valid: false, rows: 10, invalid_rows: 1, error_count: 1, errors: [{ row: 3, column: "id", rule: :required, value: "" }] }`.
+fn validation_report_to_hash(report: &ValidationReport) -> Result<RHash, Error> {
+    let ruby = unsafe { Ruby::get_unchecked() };
+
+    let errors = ruby.ary_new_capa(report.violations.len());
+    for violation in &report.violations {
+        let error = ruby.hash_new();
+        error.aset(ruby.to_symbol("row"), violation.row)?;
+        error.aset(ruby.to_symbol("column"), violation.column.as_str())?;
+        error.aset(
+            ruby.to_symbol("rule"),
+            ruby.to_symbol(violation.rule.name()),
+        )?;
+        error.aset(ruby.to_symbol("value"), violation.value.as_deref())?;
+        errors.push(error)?;
+    }
+
+    let hash = ruby.hash_new();
+    hash.aset(ruby.to_symbol("valid"), report.is_valid())?;
+    hash.aset(ruby.to_symbol("rows"), report.rows)?;
+    hash.aset(ruby.to_symbol("invalid_rows"), report.invalid_rows)?;
+    hash.aset(ruby.to_symbol("error_count"), report.error_count)?;
+    hash.aset(ruby.to_symbol("errors"), errors)?;
+    Ok(hash)
+}
+
+/// Maps the input into memory when `mmap: true` was passed and the input is a
+/// local, uncompressed file. Parallel reads split the file themselves, so the
+/// mapping is skipped for them.
+///
+/// The mapping has to outlive the reader, whose records borrow from it.
+fn map_requested_input(
+    to_read: Value,
+    mmap: bool,
+    parallel: Option<ParallelOptions>,
+) -> Result<Option<Mmap>, Error> {
+    if !mmap || parallel.is_some() {
+        return Ok(None);
+    }
+    Ok(map_input(to_read)?)
+}
+
+/// Creates an enumerator for lazy CSV parsing.
+///
+/// The keyword arguments are replayed exactly as the caller passed them, so every
+/// option is honoured by the enumerator without having to be rebuilt here.
+fn create_enumerator(
+    rb_self: Value,
+    method: &'static str,
+    to_read: Value,
+    kwargs: RHash,
+) -> magnus::Enumerator {
+    rb_self.enumeratorize(method, (to_read, KwArgs(kwargs)))
 }
@@ -1,7 +1,14 @@
+use crate::csv::{
+    Aggregate, Aggregates, ColumnConverters, ColumnSchema, ColumnSelection, ColumnType,
+    ColumnTypes, Condition, Constraints, Converter, DuplicateHeaders, ExtraFields, Keep,
+    MissingValues, NilValues, ParallelOptions, ParquetCompression, RegexFlags, RowFilter, Sample,
+    Schema, TableSchema, Unique, READ_BUFFER_SIZE,
+};
 use magnus::{
+    r_hash::ForEach,
     scan_args::{get_kwargs, scan_args},
     value::ReprValue,
-    Error, RString, Ruby, Symbol, Value,
+    Error, Module, RArray, RHash, RModule, RString, Ruby, Symbol, TryConvert, Value,
 };
 
 fn parse_string_or_symbol(ruby: &Ruby, value: Value) -> Result<Option<String>, Error> {
@@ -25,9 +32,368 @@ fn parse_string_or_symbol(ruby: &Ruby, value: Value) -> Result<Option<String>, E
     }
 }
 
+fn parse_sample(ruby: &Ruby, value: Value) -> Result<Sample, Error> {
+    let options = RHash::try_convert(value).map_err(|_| {
+        Error::new(
+            ruby.exception_type_error(),
+            "sample must be a Hash like { every: n } or { reservoir: n }",
+        )
+    })?;
+
+    let every: Option<usize> = options.lookup(Symbol::new("every"))?;
+    let reservoir: Option<usize> = options.lookup(Symbol::new("reservoir"))?;
+    let seed: Option<u64> = options.lookup(Symbol::new("seed"))?;
+
+    match (every, reservoir) {
+        (Some(0), None) => Err(Error::new(
+            ruby.exception_arg_error(),
+            "sample every: must be greater than 0",
+        )),
+        (Some(n), None) => Ok(Sample::Every(n)),
+        (None, Some(size)) => Ok(Sample::Reservoir { size, seed }),
+        _ => Err(Error::new(
+            ruby.exception_arg_error(),
+            "sample must specify exactly one of every: or reservoir:",
+        )),
+    }
+}
+
+/// Returns the number of worker threads requested by `parallel:`, if any.
+fn parse_parallel(ruby: &Ruby, value: Value) -> Result<Option<usize>, Error> {
+    if value.is_kind_of(ruby.class_true_class()) {
+        Ok(Some(
+            std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        ))
+    } else if value.is_kind_of(ruby.class_false_class()) {
+        Ok(None)
+    } else {
+        match usize::try_convert(value)? {
+            0 => Err(Error::new(
+                ruby.exception_arg_error(),
+                "parallel must be true, false or a positive number of threads",
+            )),
+            threads => Ok(Some(threads)),
+        }
+    }
+}
+
+/// Returns the read buffer size requested by `buffer_size:`.
+fn parse_buffer_size(ruby: &Ruby, value: Value) -> Result<usize, Error> {
+    match usize::try_convert(value)? {
+        0 => Err(Error::new(
+            ruby.exception_arg_error(),
+            "buffer_size must be a positive number of bytes",
+        )),
+        buffer_size => Ok(buffer_size),
+    }
+}
+
+/// Parses `converters:`, a Hash of column names to a converter or an Array of them.
+fn parse_converters(ruby: &Ruby, value: Value) -> Result<ColumnConverters, Error> {
+    let columns = RHash::try_convert(value).map_err(|_| {
+        Error::new(
+            ruby.exception_type_error(),
+            "converters must be a Hash of column names to converters",
+        )
+    })?;
+
+    let mut converters = Vec::new();
+    columns.foreach(|column: Value, list: Value| {
+        let name = parse_string_or_symbol(ruby, column)?.ok_or_else(|| {
+            Error::new(
+                ruby.exception_type_error(),
+                "converters must be keyed by column name",
+            )
+        })?;
+        let list = match RArray::from_value(list) {
+            Some(list) => list.to_vec::<Value>()?,
+            None => vec![list],
+        };
+        let list = list
+            .into_iter()
+            .map(|converter| parse_converter(ruby, converter))
+            .collect::<Result<Vec<_>, _>>()?;
+        converters.push((name, list));
+        Ok(ForEach::Continue)
+    })?;
+
+    Ok(ColumnConverters(converters))
+}
+
+/// Parses a list of nil markers, as given to `nil_values:` or for a column of
+/// `column_nil_values:`.
+fn parse_nil_markers(ruby: &Ruby, value: Value, option: &str) -> Result<Vec<String>, Error> {
+    RArray::from_value(value)
+        .and_then(|markers| markers.to_vec::<String>().ok())
+        .ok_or_else(|| {
+            Error::new(
+                ruby.exception_type_error(),
+                format!("{option} must be an Array of Strings"),
+            )
+        })
+}
+
+/// Parses `nil_values:` and `column_nil_values:`, a Hash of column names to the
+/// markers replacing `nil_values:` for that column.
+fn parse_nil_values(
+    ruby: &Ruby,
+    all: Option<Value>,
+    columns: Option<Value>,
+) -> Result<Option<NilValues>, Error> {
+    if all.is_none() && columns.is_none() {
+        return Ok(None);
+    }
+    let all = all
+        .map(|value| parse_nil_markers(ruby, value, "nil_values"))
+        .transpose()?
+        .unwrap_or_default();
+
+    let mut by_column = Vec::new();
+    if let Some(columns) = columns {
+        let columns = RHash::try_convert(columns).map_err(|_| {
+            Error::new(
+                ruby.exception_type_error(),
+                "column_nil_values must be a Hash of column names to Arrays of Strings",
+            )
+        })?;
+        columns.foreach(|column: Value, markers: Value| {
+            let name = parse_string_or_symbol(ruby, column)?.ok_or_else(|| {
+                Error::new(
+                    ruby.exception_type_error(),
+                    "column_nil_values must be keyed by column name",
+                )
+            })?;
+            by_column.push((name, parse_nil_markers(ruby, markers, "column_nil_values")?));
+            Ok(ForEach::Continue)
+        })?;
+    }
+
+    Ok(Some(NilValues {
+        all,
+        columns: by_column,
+    }))
+}
+
+/// Parses `missing_value:` and `defaults:`, a Hash of column names to the value
+/// of their missing fields.
+fn parse_missing_values(
+    ruby: &Ruby,
+    all: Option<String>,
+    columns: Option<Value>,
+) -> Result<Option<MissingValues>, Error> {
+    if all.is_none() && columns.is_none() {
+        return Ok(None);
+    }
+
+    let mut by_column = Vec::new();
+    if let Some(columns) = columns {
+        let columns = RHash::try_convert(columns).map_err(|_| {
+            Error::new(
+                ruby.exception_type_error(),
+                "defaults must be a Hash of column names to Strings",
+            )
+        })?;
+        columns.foreach(|column: Value, value: Value| {
+            let name = parse_string_or_symbol(ruby, column)?.ok_or_else(|| {
+                Error::new(
+                    ruby.exception_type_error(),
+                    "defaults must be keyed by column name",
+                )
+            })?;
+            let value = RString::from_value(value)
+                .ok_or_else(|| {
+                    Error::new(
+                        ruby.exception_type_error(),
+                        "defaults must be a Hash of column names to Strings",
+                    )
+                })?
+                .to_string()?;
+            by_column.push((name, value));
+            Ok(ForEach::Continue)
+        })?;
+    }
+
+    Ok(Some(MissingValues {
+        all,
+        columns: by_column,
+    }))
+}
+
+/// Parses `schema:`, a Hash like `{ columns: { "id" => { required: true } }, ordered: true }`.
+fn parse_schema(ruby: &Ruby, value: Value) -> Result<Schema, Error> {
+    let options = RHash::try_convert(value).map_err(|_| {
+        Error::new(
+            ruby.exception_type_error(),
+            "schema must be a Hash like { columns: { name => constraints } }",
+        )
+    })?;
+
+    let mut schema = Schema::default();
+    options.foreach(|key: Symbol, value: Value| {
+        match key.name()?.as_ref() {
+            "columns" => {
+                let columns = RHash::try_convert(value).map_err(|_| {
+                    Error::new(
+                        ruby.exception_type_error(),
+                        "schema columns must be a Hash of column names to constraints",
+                    )
+                })?;
+                columns.foreach(|name: Value, constraints: Value| {
+                    let name = parse_string_or_symbol(ruby, name)?.ok_or_else(|| {
+                        Error::new(
+                            ruby.exception_type_error(),
+                            "schema columns must be keyed by column name",
+                        )
+                    })?;
+                    schema
+                        .columns
+                        .push(parse_column_schema(ruby, name, constraints)?);
+                    Ok(ForEach::Continue)
+                })?;
+            }
+            "ordered" => schema.ordered = bool::try_convert(value)?,
+            "strict" => schema.strict = bool::try_convert(value)?,
+            other => {
+                return Err(Error::new(
+                    ruby.exception_arg_error(),
+                    format!(
+                        "unknown schema option {other}, expected columns:, ordered: or strict:"
+                    ),
+                ))
+            }
+        }
+        Ok(ForEach::Continue)
+    })?;
+    Ok(schema)
+}
+
+/// Loads `table_schema:`, the path to a Frictionless `tableschema.json`, or its
+/// descriptor already read into a Hash.
+fn load_table_schema(ruby: &Ruby, value: Value) -> Result<TableSchema, Error> {
+    let descriptor = if RHash::from_value(value).is_some() {
+        value
+    } else {
+        ruby.require("json")?;
+        let json: RModule = ruby.class_object().const_get("JSON")?;
+        let contents: RString = ruby.class_file().funcall("read", (value,))?;
+        json.funcall("parse", (contents,))?
+    };
+    serde_magnus::deserialize(descriptor).map_err(|e: Error| {
+        Error::new(
+            ruby.exception_arg_error(),
+            format!("invalid table schema: {e}"),
+        )
+    })
+}
+
+/// Parses the constraints of a schema column, like `{ required: true, max_length: 10 }`.
+fn parse_column_schema(ruby: &Ruby, name: String, value: Value) -> Result<ColumnSchema, Error> {
+    let options = RHash::try_convert(value).map_err(|_| {
+        Error::new(
+            ruby.exception_type_error(),
+            format!("the constraints of schema column {name} must be a Hash"),
+        )
+    })?;
+
+    let mut optional = false;
+    let mut constraints = Constraints::default();
+    options.foreach(|key: Symbol, value: Value| {
+        match key.name()?.as_ref() {
+            "optional" => optional = bool::try_convert(value)?,
+            "required" => constraints.required = bool::try_convert(value)?,
+            "enum" => constraints.enumeration = Some(Vec::<String>::try_convert(value)?),
+            "min_length" => constraints.min_length = Some(usize::try_convert(value)?),
+            "max_length" => constraints.max_length = Some(usize::try_convert(value)?),
+            "format" => {
+                let (source, flags) = if value.is_kind_of(ruby.class_regexp()) {
+                    regexp_source(value)?
+                } else {
+                    (String::try_convert(value)?, RegexFlags::default())
+                };
+                let format = flags
+                    .build(&source)
+                    .map_err(|e| Error::new(ruby.exception_arg_error(), e))?;
+                constraints.format = Some(format);
+            }
+            other => {
+                return Err(Error::new(
+                    ruby.exception_arg_error(),
+                    format!(
+                        "unknown constraint {other} for schema column {name}, expected optional:, required:, enum:, min_length:, max_length: or format:"
+                    ),
+                ))
+            }
+        }
+        Ok(ForEach::Continue)
+    })?;
+
+    Ok(ColumnSchema {
+        name,
+        optional,
+        field_type: None,
+        constraints,
+    })
+}
+
+/// Returns the source and flags of a Ruby `Regexp`.
+fn regexp_source(regexp: Value) -> Result<(String, RegexFlags), Error> {
+    let source: String = regexp.funcall("source", ())?;
+    let options: i64 = regexp.funcall("options", ())?;
+    let flags = RegexFlags {
+        ignore_case: options & 1 != 0,
+        extended: options & 2 != 0,
+        multiline: options & 4 != 0,
+    };
+    Ok((source, flags))
+}
+
+/// Parses a single converter: `:strip`, `:squish`, `:downcase` or
+/// `{ gsub: [pattern, replacement] }`, where the pattern is a Regexp or a String.
+fn parse_converter(ruby: &Ruby, value: Value) -> Result<Converter, Error> {
+    if let Some(options) = RHash::from_value(value) {
+        let gsub: Option<Value> = options.lookup(Symbol::new("gsub"))?;
+        let (Some(gsub), 1) = (gsub, options.len()) else {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                "converter Hashes must be like { gsub: [pattern, replacement] }",
+            ));
+        };
+        let (pattern, replacement) = <(Value, String)>::try_convert(gsub)?;
+
+        let converter = if pattern.is_kind_of(ruby.class_regexp()) {
+            let (source, flags) = regexp_source(pattern)?;
+            Converter::gsub(&source, flags, &replacement)
+        } else if let Some(pattern) = RString::from_value(pattern) {
+            Converter::gsub_literal(&pattern.to_string()?, &replacement)
+        } else {
+            return Err(Error::new(
+                ruby.exception_type_error(),
+                "gsub pattern must be a Regexp or a String",
+            ));
+        };
+        return converter.map_err(|e| Error::new(ruby.exception_arg_error(), e));
+    }
+
+    match parse_string_or_symbol(ruby, value)?.as_deref() {
+        Some("strip") => Ok(Converter::Strip),
+        Some("squish") => Ok(Converter::Squish),
+        Some("downcase") => Ok(Converter::Downcase),
+        other => Err(Error::new(
+            ruby.exception_arg_error(),
+            format!(
+                "unknown converter {}, expected :strip, :squish, :downcase or {{ gsub: [pattern, replacement] }}",
+                other.unwrap_or("nil")
+            ),
+        )),
+    }
+}
+
 #[derive(Debug)]
 pub struct ReadCsvArgs {
     pub to_read: Value,
+    /// A copy of the keyword arguments exactly as they were passed, used to
+    /// replay the call when returning an enumerator.
+    pub kwargs: RHash,
     pub has_headers: bool,
     pub delimiter: u8,
     pub quote_char: u8,
@@ -37,12 +403,533 @@ pub struct ReadCsvArgs {
     pub trim: csv::Trim,
     pub ignore_null_bytes: bool,
     pub lossy: bool,
+    pub offset: usize,
+    pub limit: Option<usize>,
+    pub sample: Option<Sample>,
+    pub unique: Option<Unique>,
+    pub parallel: Option<ParallelOptions>,
+    pub mmap: bool,
+    pub buffer_size: usize,
+    pub fileno: bool,
+    pub glob: bool,
+    pub union_headers: bool,
+    pub source_column: Option<String>,
+    pub entry: Option<String>,
+    pub converters: Option<ColumnConverters>,
+    pub nil_values: Option<NilValues>,
+    pub missing_values: Option<MissingValues>,
+    pub filled_column: Option<String>,
+    pub extra_fields: ExtraFields,
+    pub duplicate_headers: DuplicateHeaders,
+    pub schema: Option<Schema>,
+    pub max_errors: Option<usize>,
 }
 
 /// Parse common arguments for CSV parsing
 pub fn parse_read_csv_args(ruby: &Ruby, args: &[Value]) -> Result<ReadCsvArgs, Error> {
     let parsed_args = scan_args::<(Value,), (), (), (), _, ()>(args)?;
     let (to_read,) = parsed_args.required;
+    parse_read_options(ruby, to_read, parsed_args.keywords)
+}
+
+/// Parse the arguments of methods that write what they read somewhere else, like
+/// `OSV.to_jsonl(input, output, **options)`. Returns the reading options and the
+/// output.
+pub fn parse_write_csv_args(ruby: &Ruby, args: &[Value]) -> Result<(ReadCsvArgs, Value), Error> {
+    let parsed_args = scan_args::<(Value, Value), (), (), (), _, ()>(args)?;
+    let (to_read, output) = parsed_args.required;
+    Ok((
+        parse_read_options(ruby, to_read, parsed_args.keywords)?,
+        output,
+    ))
+}
+
+/// The options of `OSV.transform` deciding what is written, and how.
+#[derive(Debug)]
+pub struct TransformArgs {
+    pub selection: ColumnSelection,
+    pub row_filter: Option<RowFilter>,
+    /// The delimiter of the output, when it differs from the input's.
+    pub delimiter: Option<u8>,
+    /// The quote character of the output, when it differs from the input's.
+    pub quote_char: Option<u8>,
+}
+
+/// Parse the arguments of `OSV.transform(input, output, **options)`: the
+/// reading options, the output and the options shaping what is written.
+pub fn parse_transform_args(
+    ruby: &Ruby,
+    args: &[Value],
+) -> Result<(ReadCsvArgs, Value, TransformArgs), Error> {
+    let parsed_args = scan_args::<(Value, Value), (), (), (), _, ()>(args)?;
+    let (to_read, output) = parsed_args.required;
+
+    let kwargs = get_kwargs::<
+        _,
+        (),
+        (
+            Option<Option<Vec<Value>>>,
+            Option<Option<RHash>>,
+            Option<Option<RHash>>,
+            Option<Option<String>>,
+            Option<Option<String>>,
+        ),
+        RHash,
+    >(
+        parsed_args.keywords,
+        &[],
+        &["select", "rename", "where", "col_sep_out", "quote_char_out"],
+    )?;
+
+    let select = kwargs
+        .optional
+        .0
+        .flatten()
+        .map(|columns| {
+            columns
+                .into_iter()
+                .map(|column| column_name(ruby, column, "select"))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?;
+
+    let mut rename = Vec::new();
+    if let Some(columns) = kwargs.optional.1.flatten() {
+        columns.foreach(|from: Value, to: Value| {
+            rename.push((
+                column_name(ruby, from, "rename")?,
+                column_name(ruby, to, "rename")?,
+            ));
+            Ok(ForEach::Continue)
+        })?;
+    }
+
+    let row_filter = kwargs
+        .optional
+        .2
+        .flatten()
+        .map(|conditions| parse_where(ruby, conditions))
+        .transpose()?;
+
+    let delimiter = kwargs
+        .optional
+        .3
+        .flatten()
+        .map(|col_sep| single_byte(ruby, &col_sep, "col_sep_out"))
+        .transpose()?;
+
+    let quote_char = kwargs
+        .optional
+        .4
+        .flatten()
+        .map(|quote_char| single_byte(ruby, &quote_char, "quote_char_out"))
+        .transpose()?;
+
+    Ok((
+        parse_read_options(ruby, to_read, kwargs.splat)?,
+        output,
+        TransformArgs {
+            selection: ColumnSelection { select, rename },
+            row_filter,
+            delimiter,
+            quote_char,
+        },
+    ))
+}
+
+/// The number of rows gathered into each record batch of a Parquet or Arrow
+/// file by default, which are also the rows column types are inferred from.
+const DEFAULT_BATCH_SIZE: usize = 65_536;
+
+/// The options of `OSV.to_parquet`, `OSV.to_arrow` and `OSV.read_arrow` deciding
+/// how columns are typed and batched.
+#[derive(Debug)]
+pub struct ColumnarArgs {
+    pub types: ColumnTypes,
+    pub batch_size: usize,
+    pub compression: Option<ParquetCompression>,
+}
+
+impl Default for ColumnarArgs {
+    fn default() -> Self {
+        Self {
+            types: ColumnTypes::default(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            compression: None,
+        }
+    }
+}
+
+/// Parse the arguments of `OSV.to_parquet(input, output, **options)` and
+/// `OSV.to_arrow`: the reading options, the output and the options deciding how
+/// columns are written.
+pub fn parse_columnar_args(
+    ruby: &Ruby,
+    args: &[Value],
+) -> Result<(ReadCsvArgs, Value, ColumnarArgs), Error> {
+    let parsed_args = scan_args::<(Value, Value), (), (), (), _, ()>(args)?;
+    let (to_read, output) = parsed_args.required;
+    let (columnar, keywords) = parse_columnar_options(ruby, parsed_args.keywords)?;
+    Ok((
+        parse_read_options(ruby, to_read, keywords)?,
+        output,
+        columnar,
+    ))
+}
+
+/// Parse the arguments of `OSV.read_arrow(input, **options)`: the reading options
+/// and the options deciding how batches are typed and sized.
+pub fn parse_read_arrow_args(
+    ruby: &Ruby,
+    args: &[Value],
+) -> Result<(ReadCsvArgs, ColumnarArgs), Error> {
+    let parsed_args = scan_args::<(Value,), (), (), (), _, ()>(args)?;
+    let (to_read,) = parsed_args.required;
+
+    // The enumerator replays every option, including the ones taken out here
+    let original_kwargs = ruby.hash_new();
+    original_kwargs.update(parsed_args.keywords)?;
+
+    let (columnar, keywords) = parse_columnar_options(ruby, parsed_args.keywords)?;
+    let args = ReadCsvArgs {
+        kwargs: original_kwargs,
+        ..parse_read_options(ruby, to_read, keywords)?
+    };
+    Ok((args, columnar))
+}
+
+/// Takes `types:`, `batch_size:` and `compression:` out of `keywords`, returning
+/// them along with the other keywords.
+fn parse_columnar_options(ruby: &Ruby, keywords: RHash) -> Result<(ColumnarArgs, RHash), Error> {
+    let kwargs = get_kwargs::<
+        _,
+        (),
+        (
+            Option<Option<RHash>>,
+            Option<Option<usize>>,
+            Option<Option<Value>>,
+        ),
+        RHash,
+    >(keywords, &[], &["types", "batch_size", "compression"])?;
+
+    let mut types = Vec::new();
+    if let Some(columns) = kwargs.optional.0.flatten() {
+        columns.foreach(|column: Value, name: Value| {
+            let column = column_name(ruby, column, "types")?;
+            let column_type = parse_string_or_symbol(ruby, name)?
+                .as_deref()
+                .and_then(ColumnType::from_name)
+                .ok_or_else(|| {
+                    Error::new(
+                        ruby.exception_arg_error(),
+                        format!(
+                            "unknown type for column {column}, expected :string, :integer, :float, :boolean, :date or :datetime"
+                        ),
+                    )
+                })?;
+            types.push((column, column_type));
+            Ok(ForEach::Continue)
+        })?;
+    }
+
+    let batch_size = match kwargs.optional.1.flatten() {
+        Some(0) => {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                "batch_size must be a positive number of rows",
+            ))
+        }
+        batch_size => batch_size.unwrap_or(DEFAULT_BATCH_SIZE),
+    };
+
+    let compression = kwargs
+        .optional
+        .2
+        .flatten()
+        .map(|value| {
+            parse_string_or_symbol(ruby, value)?
+                .as_deref()
+                .and_then(ParquetCompression::from_name)
+                .ok_or_else(|| {
+                    Error::new(
+                        ruby.exception_arg_error(),
+                        "compression must be :snappy, :zstd or :none",
+                    )
+                })
+        })
+        .transpose()?;
+
+    Ok((
+        ColumnarArgs {
+            types: ColumnTypes(types),
+            batch_size,
+            compression,
+        },
+        kwargs.splat,
+    ))
+}
+
+/// The number of most frequent values `OSV.profile` returns for each column by default.
+const DEFAULT_TOP: usize = 10;
+
+/// The number of distinct values of a column `OSV.profile` counts exactly by
+/// default, before estimating how many there are.
+const DEFAULT_DISTINCT_LIMIT: usize = 10_000;
+
+/// The options of `OSV.profile` deciding how much is kept of each column.
+#[derive(Debug)]
+pub struct ProfileArgs {
+    pub top: usize,
+    pub distinct_limit: usize,
+}
+
+/// Parse the arguments of `OSV.profile(input, **options)`: the reading options
+/// and the options deciding how much is kept of each column.
+pub fn parse_profile_args(
+    ruby: &Ruby,
+    args: &[Value],
+) -> Result<(ReadCsvArgs, ProfileArgs), Error> {
+    let parsed_args = scan_args::<(Value,), (), (), (), _, ()>(args)?;
+    let (to_read,) = parsed_args.required;
+
+    let kwargs = get_kwargs::<_, (), (Option<Option<usize>>, Option<Option<usize>>), RHash>(
+        parsed_args.keywords,
+        &[],
+        &["top", "distinct_limit"],
+    )?;
+
+    let top = kwargs.optional.0.flatten().unwrap_or(DEFAULT_TOP);
+    let distinct_limit = match kwargs.optional.1.flatten() {
+        Some(0) => {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                "distinct_limit must be a positive number of values",
+            ))
+        }
+        distinct_limit => distinct_limit.unwrap_or(DEFAULT_DISTINCT_LIMIT),
+    };
+
+    Ok((
+        parse_read_options(ruby, to_read, kwargs.splat)?,
+        ProfileArgs {
+            top,
+            distinct_limit,
+        },
+    ))
+}
+
+/// The options of `OSV.aggregate` deciding how rows are grouped and summarized.
+#[derive(Debug)]
+pub struct AggregateArgs {
+    pub aggregates: Aggregates,
+    pub row_filter: Option<RowFilter>,
+}
+
+/// Parse the arguments of `OSV.aggregate(input, **options)`: the reading options
+/// and the query run over the rows.
+pub fn parse_aggregate_args(
+    ruby: &Ruby,
+    args: &[Value],
+) -> Result<(ReadCsvArgs, AggregateArgs), Error> {
+    let parsed_args = scan_args::<(Value,), (), (), (), _, ()>(args)?;
+    let (to_read,) = parsed_args.required;
+
+    let kwargs = get_kwargs::<
+        _,
+        (),
+        (
+            Option<Option<Vec<Value>>>,
+            Option<Option<bool>>,
+            Option<Option<Vec<Value>>>,
+            Option<Option<Vec<Value>>>,
+            Option<Option<Vec<Value>>>,
+            Option<Option<Vec<Value>>>,
+            Option<Option<RHash>>,
+        ),
+        RHash,
+    >(
+        parsed_args.keywords,
+        &[],
+        &["group_by", "count", "sum", "min", "max", "mean", "where"],
+    )?;
+
+    let names = |columns: Option<Option<Vec<Value>>>, option: &str| {
+        columns
+            .flatten()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|column| column_name(ruby, column, option))
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    let group_by = names(kwargs.optional.0, "group_by")?;
+    let count = kwargs.optional.1.flatten().unwrap_or_default();
+
+    let mut columns = Vec::new();
+    for (aggregate, requested) in [
+        (Aggregate::Sum, kwargs.optional.2),
+        (Aggregate::Min, kwargs.optional.3),
+        (Aggregate::Max, kwargs.optional.4),
+        (Aggregate::Mean, kwargs.optional.5),
+    ] {
+        let requested = names(requested, aggregate.name())?;
+        if !requested.is_empty() {
+            columns.push((aggregate, requested));
+        }
+    }
+
+    if !count && columns.is_empty() {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            "OSV.aggregate requires count: true or columns to sum:, min:, max: or mean:",
+        ));
+    }
+
+    let row_filter = kwargs
+        .optional
+        .6
+        .flatten()
+        .map(|conditions| parse_where(ruby, conditions))
+        .transpose()?;
+
+    Ok((
+        parse_read_options(ruby, to_read, kwargs.splat)?,
+        AggregateArgs {
+            aggregates: Aggregates {
+                group_by,
+                count,
+                columns,
+            },
+            row_filter,
+        },
+    ))
+}
+
+/// Parses `unique_by:`, `unique:` and `keep:` into the key rows are deduplicated by.
+fn parse_unique(
+    ruby: &Ruby,
+    unique_by: Option<Vec<Value>>,
+    unique: bool,
+    keep: Option<Value>,
+) -> Result<Option<Unique>, Error> {
+    let columns = match (unique_by, unique) {
+        (Some(_), true) => {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                "unique_by and unique can't be given together",
+            ))
+        }
+        (Some(columns), false) if columns.is_empty() => {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                "unique_by must name at least one column",
+            ))
+        }
+        (Some(columns), false) => Some(
+            columns
+                .into_iter()
+                .map(|column| column_name(ruby, column, "unique_by"))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        (None, true) => None,
+        (None, false) => {
+            if keep.is_some() {
+                return Err(Error::new(
+                    ruby.exception_arg_error(),
+                    "keep only applies with unique_by: or unique: true",
+                ));
+            }
+            return Ok(None);
+        }
+    };
+
+    let keep = match keep
+        .map(|value| parse_string_or_symbol(ruby, value))
+        .transpose()?
+        .flatten()
+        .as_deref()
+    {
+        None | Some("first") => Keep::First,
+        Some("last") => Keep::Last,
+        Some(keep) => {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                format!("keep must be :first or :last but got '{keep}'"),
+            ))
+        }
+    };
+
+    Ok(Some(Unique { columns, keep }))
+}
+
+/// Parses a column name given as a String or Symbol.
+fn column_name(ruby: &Ruby, value: Value, option: &str) -> Result<String, Error> {
+    parse_string_or_symbol(ruby, value)?.ok_or_else(|| {
+        Error::new(
+            ruby.exception_type_error(),
+            format!("{option} must name columns with Strings or Symbols"),
+        )
+    })
+}
+
+/// Returns the first byte of an option that must be a single character.
+fn single_byte(ruby: &Ruby, value: &str, option: &str) -> Result<u8, Error> {
+    value.as_bytes().first().copied().ok_or_else(|| {
+        Error::new(
+            ruby.exception_arg_error(),
+            format!("{option} cannot be empty"),
+        )
+    })
+}
+
+/// Parses `where:`, a Hash of column names to the value they must have: a
+/// String, nil, an Array of the values allowed, or a Regexp they must match.
+fn parse_where(ruby: &Ruby, conditions: RHash) -> Result<RowFilter, Error> {
+    let value_of = |value: Value| -> Result<Option<String>, Error> {
+        if value.is_nil() {
+            Ok(None)
+        } else {
+            parse_string_or_symbol(ruby, value).map_err(|_| {
+                Error::new(
+                    ruby.exception_type_error(),
+                    "where values must be Strings, nil, Arrays of them or Regexps",
+                )
+            })
+        }
+    };
+
+    let mut filter = Vec::new();
+    conditions.foreach(|column: Value, value: Value| {
+        let column = column_name(ruby, column, "where")?;
+        let condition = if value.is_kind_of(ruby.class_regexp()) {
+            let (source, flags) = regexp_source(value)?;
+            Condition::Matches(
+                flags
+                    .build(&source)
+                    .map_err(|e| Error::new(ruby.exception_arg_error(), e))?,
+            )
+        } else if let Some(values) = RArray::from_value(value) {
+            Condition::OneOf(
+                values
+                    .to_vec::<Value>()?
+                    .into_iter()
+                    .map(value_of)
+                    .collect::<Result<_, _>>()?,
+            )
+        } else {
+            Condition::OneOf(vec![value_of(value)?])
+        };
+        filter.push((column, condition));
+        Ok(ForEach::Continue)
+    })?;
+
+    Ok(RowFilter(filter))
+}
+
+/// Parses the keyword arguments shared by every method reading CSV.
+fn parse_read_options(ruby: &Ruby, to_read: Value, keywords: RHash) -> Result<ReadCsvArgs, Error> {
+    let original_kwargs = ruby.hash_new();
+    original_kwargs.update(keywords)?;
 
     let kwargs = get_kwargs::<
         _,
@@ -58,9 +945,9 @@ pub fn parse_read_csv_args(ruby: &Ruby, args: &[Value]) -> Result<ReadCsvArgs, E
             Option<Option<bool>>,
             Option<Option<bool>>,
         ),
-        (),
+        RHash,
     >(
-        parsed_args.keywords,
+        keywords,
         &[],
         &[
             "has_headers",
@@ -114,11 +1001,11 @@ pub fn parse_read_csv_args(ruby: &Ruby, args: &[Value]) -> Result<ReadCsvArgs, E
         .map(|value| parse_string_or_symbol(ruby, value))
     {
         Some(Ok(Some(parsed))) => match parsed.as_str() {
-            "hash" | "array" => parsed,
+            "hash" | "array" | "arrow" => parsed,
             _ => {
                 return Err(Error::new(
                     magnus::exception::runtime_error(),
-                    "result_type must be either 'hash' or 'array'",
+                    "result_type must be 'hash', 'array' or 'arrow'",
                 ))
             }
         },
@@ -168,8 +1055,274 @@ pub fn parse_read_csv_args(ruby: &Ruby, args: &[Value]) -> Result<ReadCsvArgs, E
 
     let lossy = kwargs.optional.8.flatten().unwrap_or_default();
 
+    let values_kwargs = get_kwargs::<
+        _,
+        (),
+        (
+            Option<Option<Value>>,
+            Option<Option<Value>>,
+            Option<Option<Value>>,
+            Option<Option<String>>,
+            Option<Option<Value>>,
+            Option<Option<String>>,
+            Option<Option<Value>>,
+            Option<Option<String>>,
+            Option<Option<Value>>,
+        ),
+        RHash,
+    >(
+        kwargs.splat,
+        &[],
+        &[
+            "converters",
+            "nil_values",
+            "column_nil_values",
+            "missing_value",
+            "defaults",
+            "filled_column",
+            "extra_fields",
+            "extra_key",
+            "duplicate_headers",
+        ],
+    )?;
+
+    let converters = values_kwargs
+        .optional
+        .0
+        .flatten()
+        .map(|value| parse_converters(ruby, value))
+        .transpose()?;
+
+    let mut nil_values = parse_nil_values(
+        ruby,
+        values_kwargs.optional.1.flatten(),
+        values_kwargs.optional.2.flatten(),
+    )?;
+
+    let missing_values = parse_missing_values(
+        ruby,
+        values_kwargs.optional.3.flatten(),
+        values_kwargs.optional.4.flatten(),
+    )?;
+    if missing_values.is_some() && !flexible {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            "missing_value and defaults only apply to short rows, which require flexible: true",
+        ));
+    }
+
+    let filled_column = values_kwargs.optional.5.flatten();
+
+    let extra_fields = match values_kwargs
+        .optional
+        .6
+        .flatten()
+        .map(|value| parse_string_or_symbol(ruby, value))
+        .transpose()?
+        .flatten()
+        .as_deref()
+    {
+        None | Some("drop") => ExtraFields::Drop,
+        Some("raise") => ExtraFields::Raise,
+        Some("collect") => ExtraFields::Collect(
+            values_kwargs
+                .optional
+                .7
+                .flatten()
+                .unwrap_or_else(|| "_extra".to_string()),
+        ),
+        Some(mode) => {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                format!("extra_fields must be :drop, :raise or :collect but got '{mode}'"),
+            ))
+        }
+    };
+    let duplicate_headers = match values_kwargs
+        .optional
+        .8
+        .flatten()
+        .map(|value| parse_string_or_symbol(ruby, value))
+        .transpose()?
+        .flatten()
+        .as_deref()
+    {
+        None | Some("last") => DuplicateHeaders::Last,
+        Some("first") => DuplicateHeaders::First,
+        Some("raise") => DuplicateHeaders::Raise,
+        Some("suffix") => DuplicateHeaders::Suffix,
+        Some("array") => DuplicateHeaders::Array,
+        Some(mode) => {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                format!(
+                    "duplicate_headers must be :last, :first, :raise, :suffix or :array but got '{mode}'"
+                ),
+            ))
+        }
+    };
+
+    if extra_fields != ExtraFields::Drop && !flexible {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            "extra_fields only applies to long rows, which require flexible: true",
+        ));
+    }
+
+    let schema_kwargs = get_kwargs::<
+        _,
+        (),
+        (
+            Option<Option<Value>>,
+            Option<Option<usize>>,
+            Option<Option<Value>>,
+        ),
+        RHash,
+    >(
+        values_kwargs.splat,
+        &[],
+        &["schema", "max_errors", "table_schema"],
+    )?;
+
+    let mut schema = schema_kwargs
+        .optional
+        .0
+        .flatten()
+        .map(|value| parse_schema(ruby, value))
+        .transpose()?;
+
+    // A table schema stands for schema:, and for nil_values: unless they are given
+    if let Some(value) = schema_kwargs.optional.2.flatten() {
+        if schema.is_some() {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                "schema and table_schema can't be given together",
+            ));
+        }
+        let table_schema = load_table_schema(ruby, value)?;
+        schema = Some(
+            table_schema
+                .schema()
+                .map_err(|e| Error::new(ruby.exception_arg_error(), e))?,
+        );
+        nil_values = nil_values.or_else(|| Some(table_schema.nil_values()));
+    }
+
+    // Rows are checked as read, before converters run, so a converted value
+    // wouldn't be the one that was checked
+    if schema.is_some() && converters.is_some() {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            "converters can't be given with schema or table_schema",
+        ));
+    }
+
+    let max_errors = schema_kwargs.optional.1.flatten();
+
+    let selection_kwargs = get_kwargs::<
+        _,
+        (),
+        (
+            Option<Option<usize>>,
+            Option<Option<usize>>,
+            Option<Option<Value>>,
+            Option<Option<Vec<Value>>>,
+            Option<Option<bool>>,
+            Option<Option<Value>>,
+        ),
+        RHash,
+    >(
+        schema_kwargs.splat,
+        &[],
+        &["offset", "limit", "sample", "unique_by", "unique", "keep"],
+    )?;
+
+    let offset = selection_kwargs.optional.0.flatten().unwrap_or_default();
+
+    let limit = selection_kwargs.optional.1.flatten();
+
+    let sample = selection_kwargs
+        .optional
+        .2
+        .flatten()
+        .map(|value| parse_sample(ruby, value))
+        .transpose()?;
+
+    let unique = parse_unique(
+        ruby,
+        selection_kwargs.optional.3.flatten(),
+        selection_kwargs.optional.4.flatten().unwrap_or_default(),
+        selection_kwargs.optional.5.flatten(),
+    )?;
+
+    let files_kwargs = get_kwargs::<
+        _,
+        (),
+        (
+            Option<Option<bool>>,
+            Option<Option<bool>>,
+            Option<Option<String>>,
+            Option<Option<String>>,
+        ),
+        RHash,
+    >(
+        selection_kwargs.splat,
+        &[],
+        &["glob", "union_headers", "source_column", "entry"],
+    )?;
+
+    let glob = files_kwargs.optional.0.flatten().unwrap_or(false);
+
+    let union_headers = files_kwargs.optional.1.flatten().unwrap_or(false);
+
+    let source_column = files_kwargs.optional.2.flatten();
+
+    let entry = files_kwargs.optional.3.flatten();
+
+    let execution_kwargs = get_kwargs::<
+        _,
+        (),
+        (
+            Option<Option<Value>>,
+            Option<Option<bool>>,
+            Option<Option<bool>>,
+            Option<Option<Value>>,
+            Option<Option<bool>>,
+        ),
+        (),
+    >(
+        files_kwargs.splat,
+        &[],
+        &["parallel", "ordered", "mmap", "buffer_size", "fileno"],
+    )?;
+
+    let parallel = execution_kwargs
+        .optional
+        .0
+        .flatten()
+        .map(|value| parse_parallel(ruby, value))
+        .transpose()?
+        .flatten()
+        .map(|threads| ParallelOptions {
+            threads,
+            ordered: execution_kwargs.optional.1.flatten().unwrap_or(true),
+        });
+
+    let mmap = execution_kwargs.optional.2.flatten().unwrap_or(false);
+
+    let buffer_size = execution_kwargs
+        .optional
+        .3
+        .flatten()
+        .map(|value| parse_buffer_size(ruby, value))
+        .transpose()?
+        .unwrap_or(READ_BUFFER_SIZE);
+
+    let fileno = execution_kwargs.optional.4.flatten().unwrap_or(false);
+
     Ok(ReadCsvArgs {
         to_read,
+        kwargs: original_kwargs,
         has_headers,
         delimiter,
         quote_char,
@@ -179,5 +1332,25 @@ pub fn parse_read_csv_args(ruby: &Ruby, args: &[Value]) -> Result<ReadCsvArgs, E
         trim,
         ignore_null_bytes,
         lossy,
+        offset,
+        limit,
+        sample,
+        unique,
+        parallel,
+        mmap,
+        buffer_size,
+        fileno,
+        glob,
+        union_headers,
+        source_column,
+        entry,
+        converters,
+        nil_values,
+        missing_values,
+        filled_column,
+        extra_fields,
+        duplicate_headers,
+        schema,
+        max_errors,
     })
 }
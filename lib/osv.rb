@@ -7,4 +7,26 @@ rescue LoadError
 end
 
 module OSV
+  class << self
+    alias_method :parse_string, :parse
+  end
+
+  # A record batch yielded by `OSV.read_arrow`, exported to Arrow libraries
+  # through the Arrow C data interface.
+  class ArrowBatch
+    # The sizes of the ArrowArray and ArrowSchema structs of the C data interface
+    C_ARRAY_SIZE = 80
+    C_SCHEMA_SIZE = 72
+
+    # Imports the batch as an Arrow::RecordBatch of red-arrow, sharing its buffers.
+    def to_arrow
+      require "arrow"
+      require "fiddle"
+
+      array = Fiddle::Pointer.malloc(C_ARRAY_SIZE, Fiddle::RUBY_FREE)
+      schema = Fiddle::Pointer.malloc(C_SCHEMA_SIZE, Fiddle::RUBY_FREE)
+      export_to_c(array.to_i, schema.to_i)
+      Arrow::RecordBatch.import(array.to_i, Arrow::Schema.import(schema.to_i))
+    end
+  end
 end
@@ -12,20 +12,81 @@ module OSV
   #                   By default, empty strings are interpreted as empty strings.
   #                   If you want to interpret empty strings as nil, set this to
   #                   an empty string.
-  #   - `buffer_size`: Integer specifying the read buffer size
+  #   - `buffer_size`: Integer size in bytes of the read buffer, and the most
+  #                    requested from an IO per `read` call (default: 16384)
   #   - `result_type`: String specifying the output format
-  #                    ("hash" or "array" or :hash or :array)
+  #                    ("hash" or "array" or :hash or :array), or :arrow for
+  #                    `OSV::ArrowBatch` record batches as in `read_arrow`
   #   - `flexible`: Boolean specifying if the parser should be flexible
   #                 (default: false)
+  #   - `missing_value`: String given to the fields missing from short rows in
+  #                      flexible mode (default: `nil`)
+  #   - `defaults`: Hash of column names to the String given to their missing
+  #                 fields instead of `missing_value` (default: `nil`)
+  #   - `filled_column`: String name of a column listing the fields each row was
+  #                      missing, which can't be a column of the header
+  #                      (default: `nil`)
+  #   - `extra_fields`: What to do with the fields of long rows past the last
+  #                     header: `:drop`, `:raise` or `:collect` (default: `:drop`)
+  #   - `extra_key`: String key Hash rows collect extra fields under
+  #                  (default: `"_extra"`)
+  #   - `duplicate_headers`: How Hash rows deal with columns sharing a header:
+  #                          `:last`, `:first`, `:raise`, `:suffix` or `:array`
+  #                          (default: `:last`)
   #   - `trim`: String specifying the trim mode
   #             ("all" or "headers" or "fields" or :all or :headers or :fields)
   #             (default: `nil`)
   #   - `ignore_null_bytes`: Boolean specifying if null bytes should be ignored
   #                         (default: false)
   #   - `lossy`: Boolean specifying if invalid UTF-8 characters should be replaced with a replacement character
+  #   - `converters`: Hash of column names to the converters run over their
+  #                   values: `:strip`, `:squish`, `:downcase` or
+  #                   `{ gsub: [pattern, replacement] }` (default: `nil`)
+  #   - `nil_values`: Array of Strings read as nil when they appear unquoted,
+  #                   so a quoted `""` or `"NULL"` stays a String (default: `nil`)
+  #   - `column_nil_values`: Hash of column names to the Strings read as nil in
+  #                          that column instead of `nil_values` (default: `nil`)
+  #   - `schema`: Hash of `columns`, `ordered` and `strict` rows are checked
+  #               against. Every row is still yielded, and the validation
+  #               report is returned once they all are (default: `nil`)
+  #   - `max_errors`: Integer most errors listed in the report (default: `nil`)
+  #   - `table_schema`: Path to a Frictionless `tableschema.json`, or its
+  #                     descriptor as a Hash, used in place of `schema`. Values
+  #                     are cast to the types of their fields (default: `nil`)
+  #   - `offset`: Integer number of data rows to skip
+  #               (default: 0)
+  #   - `limit`: Integer maximum number of rows to return
+  #              (default: `nil`)
+  #   - `sample`: Hash selecting rows after `offset` is applied, either
+  #               `{ every: n }` or `{ reservoir: n, seed: Integer }`
+  #               (default: `nil`)
+  #   - `unique_by`: Array of column names whose values identify a row.
+  #                  Rows repeating an earlier key are dropped before
+  #                  `offset`, `limit` and `sample` apply (default: `nil`)
+  #   - `unique`: Boolean specifying if rows repeating an earlier row in
+  #               every field are dropped (default: `nil`)
+  #   - `keep`: Which row of each key is kept, `:first` or `:last`. `:last`
+  #             holds the last row of each key in memory until the input
+  #             is read (default: `:first`)
+  #   - `parallel`: `true` or an Integer number of threads used to parse
+  #                 local, uncompressed files (default: false)
+  #   - `ordered`: Boolean specifying if rows parsed in parallel are yielded
+  #                in file order (default: true)
+  #   - `mmap`: Boolean specifying if local, uncompressed files are memory-mapped
+  #             instead of read through a buffer (default: false)
+  #   - `fileno`: Boolean specifying if `File` objects are read straight from
+  #               their file descriptor instead of through Ruby (default: false)
+  #   - `glob`: Boolean specifying if `input` is a pattern matching the files
+  #             to read, in sorted order (default: false)
+  #   - `union_headers`: Boolean specifying if the headers of multiple files are
+  #                      merged by name instead of having to match (default: false)
+  #   - `source_column`: String name of a column holding the path of the file
+  #                      each row came from (default: `nil`)
+  #   - `entry`: String name or glob pattern of the member to read from
+  #              `.zip`, `.tar` and `.tar.gz` archives (default: `nil`)
   sig do
     params(
-      input: T.any(String, StringIO, IO),
+      input: T.any(String, Pathname, T::Array[T.any(String, Pathname)], StringIO, IO),
       has_headers: T.nilable(T::Boolean),
       col_sep: T.nilable(String),
       quote_char: T.nilable(String),
@@ -35,7 +96,34 @@ module OSV
       flexible: T.nilable(T::Boolean),
       ignore_null_bytes: T.nilable(T::Boolean),
       trim: T.nilable(T.any(String, Symbol)),
-      blk: T.nilable(T.proc.params(row: T.any(T::Hash[String, T.nilable(String)], T::Array[T.nilable(String)])).void)
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      filled_column: T.nilable(String),
+      extra_fields: T.nilable(T.any(String, Symbol)),
+      extra_key: T.nilable(String),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      ordered: T.nilable(T::Boolean),
+      mmap: T.nilable(T::Boolean),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      source_column: T.nilable(String),
+      entry: T.nilable(String),
+      blk: T.nilable(T.proc.params(row: T.any(T::Hash[String, T.untyped], T::Array[T.untyped], OSV::ArrowBatch)).void)
     ).returns(T.any(Enumerator, T.untyped))
   end
   def self.for_each(
@@ -50,7 +138,1092 @@ module OSV
     ignore_null_bytes: nil,
     trim: nil,
     lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    filled_column: nil,
+    extra_fields: nil,
+    extra_key: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    ordered: nil,
+    mmap: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    source_column: nil,
+    entry: nil,
     &blk
   )
   end
+
+  # Parses the CSV file at `input`, a path given as a String or Pathname.
+  # Accepts the same options as `for_each`; raises TypeError for other inputs.
+  sig do
+    params(
+      input: T.any(String, Pathname, T::Array[T.any(String, Pathname)]),
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      buffer_size: T.nilable(Integer),
+      result_type: T.nilable(T.any(String, Symbol)),
+      flexible: T.nilable(T::Boolean),
+      ignore_null_bytes: T.nilable(T::Boolean),
+      trim: T.nilable(T.any(String, Symbol)),
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      filled_column: T.nilable(String),
+      extra_fields: T.nilable(T.any(String, Symbol)),
+      extra_key: T.nilable(String),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      ordered: T.nilable(T::Boolean),
+      mmap: T.nilable(T::Boolean),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      source_column: T.nilable(String),
+      entry: T.nilable(String),
+      blk: T.nilable(T.proc.params(row: T.any(T::Hash[String, T.untyped], T::Array[T.untyped], OSV::ArrowBatch)).void)
+    ).returns(T.any(Enumerator, T.untyped))
+  end
+  def self.open(
+    input,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    buffer_size: nil,
+    result_type: nil,
+    flexible: nil,
+    ignore_null_bytes: nil,
+    trim: nil,
+    lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    filled_column: nil,
+    extra_fields: nil,
+    extra_key: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    ordered: nil,
+    mmap: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    source_column: nil,
+    entry: nil,
+    &blk
+  )
+  end
+
+  # Parses CSV read from `input`, an IO or any object responding to `read`.
+  # Accepts the same options as `for_each`; raises TypeError for other inputs.
+  sig do
+    params(
+      input: T.any(IO, StringIO),
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      buffer_size: T.nilable(Integer),
+      result_type: T.nilable(T.any(String, Symbol)),
+      flexible: T.nilable(T::Boolean),
+      ignore_null_bytes: T.nilable(T::Boolean),
+      trim: T.nilable(T.any(String, Symbol)),
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      filled_column: T.nilable(String),
+      extra_fields: T.nilable(T.any(String, Symbol)),
+      extra_key: T.nilable(String),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      ordered: T.nilable(T::Boolean),
+      mmap: T.nilable(T::Boolean),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      source_column: T.nilable(String),
+      entry: T.nilable(String),
+      blk: T.nilable(T.proc.params(row: T.any(T::Hash[String, T.untyped], T::Array[T.untyped], OSV::ArrowBatch)).void)
+    ).returns(T.any(Enumerator, T.untyped))
+  end
+  def self.read(
+    input,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    buffer_size: nil,
+    result_type: nil,
+    flexible: nil,
+    ignore_null_bytes: nil,
+    trim: nil,
+    lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    filled_column: nil,
+    extra_fields: nil,
+    extra_key: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    ordered: nil,
+    mmap: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    source_column: nil,
+    entry: nil,
+    &blk
+  )
+  end
+
+  # Parses CSV content held in a String, reading straight from the string's
+  # bytes. A String passed to `for_each` is always treated as a file path.
+  # Accepts the same options as `for_each`; `parallel` and `mmap` have no effect.
+  sig do
+    params(
+      input: String,
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      buffer_size: T.nilable(Integer),
+      result_type: T.nilable(T.any(String, Symbol)),
+      flexible: T.nilable(T::Boolean),
+      ignore_null_bytes: T.nilable(T::Boolean),
+      trim: T.nilable(T.any(String, Symbol)),
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      filled_column: T.nilable(String),
+      extra_fields: T.nilable(T.any(String, Symbol)),
+      extra_key: T.nilable(String),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      blk: T.nilable(T.proc.params(row: T.any(T::Hash[String, T.untyped], T::Array[T.untyped], OSV::ArrowBatch)).void)
+    ).returns(T.any(Enumerator, T.untyped))
+  end
+  def self.parse(
+    input,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    buffer_size: nil,
+    result_type: nil,
+    flexible: nil,
+    ignore_null_bytes: nil,
+    trim: nil,
+    lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    filled_column: nil,
+    extra_fields: nil,
+    extra_key: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    &blk
+  )
+  end
+
+  # Alias of `parse`.
+  sig do
+    params(
+      input: String,
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      buffer_size: T.nilable(Integer),
+      result_type: T.nilable(T.any(String, Symbol)),
+      flexible: T.nilable(T::Boolean),
+      ignore_null_bytes: T.nilable(T::Boolean),
+      trim: T.nilable(T.any(String, Symbol)),
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      filled_column: T.nilable(String),
+      extra_fields: T.nilable(T.any(String, Symbol)),
+      extra_key: T.nilable(String),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      blk: T.nilable(T.proc.params(row: T.any(T::Hash[String, T.untyped], T::Array[T.untyped], OSV::ArrowBatch)).void)
+    ).returns(T.any(Enumerator, T.untyped))
+  end
+  def self.parse_string(
+    input,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    buffer_size: nil,
+    result_type: nil,
+    flexible: nil,
+    ignore_null_bytes: nil,
+    trim: nil,
+    lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    filled_column: nil,
+    extra_fields: nil,
+    extra_key: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    &blk
+  )
+  end
+
+  # Counts the rows in the input without creating Ruby objects for them.
+  # Accepts the same options as `for_each`, and returns the number of rows
+  # `for_each` would yield.
+  sig do
+    params(
+      input: T.any(String, Pathname, T::Array[T.any(String, Pathname)], StringIO, IO),
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      flexible: T.nilable(T::Boolean),
+      lossy: T.nilable(T::Boolean),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      mmap: T.nilable(T::Boolean),
+      buffer_size: T.nilable(Integer),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      entry: T.nilable(String)
+    ).returns(Integer)
+  end
+  def self.count(
+    input,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    flexible: nil,
+    lossy: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    mmap: nil,
+    buffer_size: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    entry: nil
+  )
+  end
+
+  # Checks the header and every row of the input against `schema`, or
+  # `table_schema`, without creating Ruby objects for the rows. Returns a
+  # report Hash with `valid`, `rows`, `invalid_rows`, `error_count` and
+  # `errors`, each error naming the `row`, `column`, `rule` and `value`.
+  sig do
+    params(
+      input: T.any(String, Pathname, T::Array[T.any(String, Pathname)], StringIO, IO),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      nil_values: T.nilable(T::Array[String]),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      flexible: T.nilable(T::Boolean),
+      lossy: T.nilable(T::Boolean),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      mmap: T.nilable(T::Boolean),
+      buffer_size: T.nilable(Integer),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      entry: T.nilable(String)
+    ).returns(T::Hash[Symbol, T.untyped])
+  end
+  def self.validate(
+    input,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    nil_values: nil,
+    duplicate_headers: nil,
+    flexible: nil,
+    lossy: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    mmap: nil,
+    buffer_size: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    entry: nil
+  )
+  end
+
+  # Converts the input to JSON Lines, writing one JSON object per row to
+  # `output`, a path given as a String or Pathname, or an IO responding to
+  # `write`. Paths ending in `.gz` are gzipped. Rows are serialized without
+  # creating Ruby objects for them, as JSON arrays with `result_type: :array`.
+  # Accepts the same options as `for_each` and returns the number of rows
+  # written.
+  sig do
+    params(
+      input: T.any(String, Pathname, T::Array[T.any(String, Pathname)], StringIO, IO),
+      output: T.any(String, Pathname, StringIO, IO),
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      buffer_size: T.nilable(Integer),
+      result_type: T.nilable(T.any(String, Symbol)),
+      flexible: T.nilable(T::Boolean),
+      ignore_null_bytes: T.nilable(T::Boolean),
+      trim: T.nilable(T.any(String, Symbol)),
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      filled_column: T.nilable(String),
+      extra_fields: T.nilable(T.any(String, Symbol)),
+      extra_key: T.nilable(String),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      ordered: T.nilable(T::Boolean),
+      mmap: T.nilable(T::Boolean),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      source_column: T.nilable(String),
+      entry: T.nilable(String)
+    ).returns(Integer)
+  end
+  def self.to_jsonl(
+    input,
+    output,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    buffer_size: nil,
+    result_type: nil,
+    flexible: nil,
+    ignore_null_bytes: nil,
+    trim: nil,
+    lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    filled_column: nil,
+    extra_fields: nil,
+    extra_key: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    ordered: nil,
+    mmap: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    source_column: nil,
+    entry: nil
+  )
+  end
+
+  # Reshapes the input into CSV written to `output`, a path given as a String
+  # or Pathname, or an IO responding to `write`, without creating Ruby objects
+  # for the rows. Keeps the columns of `select` in that order, writes the ones
+  # in `rename` under their new header, keeps the rows whose values are those
+  # of `where` (a String, nil, an Array of them or a Regexp), and writes with
+  # `col_sep_out` and `quote_char_out`, which default to the input's. Accepts
+  # the same options as `for_each` and returns the number of rows written.
+  # Raises an ArgumentError when `output` is one of the input files.
+  sig do
+    params(
+      input: T.any(String, Pathname, T::Array[T.any(String, Pathname)], StringIO, IO),
+      output: T.any(String, Pathname, StringIO, IO),
+      select: T.nilable(T::Array[T.any(String, Symbol)]),
+      rename: T.nilable(T::Hash[T.any(String, Symbol), T.any(String, Symbol)]),
+      where: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      col_sep_out: T.nilable(String),
+      quote_char_out: T.nilable(String),
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      buffer_size: T.nilable(Integer),
+      flexible: T.nilable(T::Boolean),
+      ignore_null_bytes: T.nilable(T::Boolean),
+      trim: T.nilable(T.any(String, Symbol)),
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      ordered: T.nilable(T::Boolean),
+      mmap: T.nilable(T::Boolean),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      source_column: T.nilable(String),
+      entry: T.nilable(String)
+    ).returns(Integer)
+  end
+  def self.transform(
+    input,
+    output,
+    select: nil,
+    rename: nil,
+    where: nil,
+    col_sep_out: nil,
+    quote_char_out: nil,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    buffer_size: nil,
+    flexible: nil,
+    ignore_null_bytes: nil,
+    trim: nil,
+    lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    ordered: nil,
+    mmap: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    source_column: nil,
+    entry: nil
+  )
+  end
+
+  # Converts the input to a Parquet file written to `output`, a path given as a
+  # String or Pathname, or an IO responding to `write`, without creating Ruby
+  # objects for the rows. Columns have the type given in `types` (`:string`,
+  # `:integer`, `:float`, `:boolean`, `:date` or `:datetime`), or the one
+  # inferred from the first `batch_size` rows. Column chunks are compressed
+  # with `compression`, `:snappy`, `:zstd` or `:none`. Accepts the same options
+  # as `for_each` and returns the number of rows written.
+  sig do
+    params(
+      input: T.any(String, Pathname, T::Array[T.any(String, Pathname)], StringIO, IO),
+      output: T.any(String, Pathname, StringIO, IO),
+      types: T.nilable(T::Hash[T.any(String, Symbol), T.any(String, Symbol)]),
+      batch_size: T.nilable(Integer),
+      compression: T.nilable(T.any(String, Symbol)),
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      buffer_size: T.nilable(Integer),
+      flexible: T.nilable(T::Boolean),
+      ignore_null_bytes: T.nilable(T::Boolean),
+      trim: T.nilable(T.any(String, Symbol)),
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      ordered: T.nilable(T::Boolean),
+      mmap: T.nilable(T::Boolean),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      source_column: T.nilable(String),
+      entry: T.nilable(String)
+    ).returns(Integer)
+  end
+  def self.to_parquet(
+    input,
+    output,
+    types: nil,
+    batch_size: nil,
+    compression: nil,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    buffer_size: nil,
+    flexible: nil,
+    ignore_null_bytes: nil,
+    trim: nil,
+    lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    ordered: nil,
+    mmap: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    source_column: nil,
+    entry: nil
+  )
+  end
+
+  # Converts the input to an Arrow IPC file (Feather version 2) written to
+  # `output`, typing its columns like `to_parquet`. Accepts the same options as
+  # `for_each` and returns the number of rows written.
+  sig do
+    params(
+      input: T.any(String, Pathname, T::Array[T.any(String, Pathname)], StringIO, IO),
+      output: T.any(String, Pathname, StringIO, IO),
+      types: T.nilable(T::Hash[T.any(String, Symbol), T.any(String, Symbol)]),
+      batch_size: T.nilable(Integer),
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      buffer_size: T.nilable(Integer),
+      flexible: T.nilable(T::Boolean),
+      ignore_null_bytes: T.nilable(T::Boolean),
+      trim: T.nilable(T.any(String, Symbol)),
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      ordered: T.nilable(T::Boolean),
+      mmap: T.nilable(T::Boolean),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      source_column: T.nilable(String),
+      entry: T.nilable(String)
+    ).returns(Integer)
+  end
+  def self.to_arrow(
+    input,
+    output,
+    types: nil,
+    batch_size: nil,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    buffer_size: nil,
+    flexible: nil,
+    ignore_null_bytes: nil,
+    trim: nil,
+    lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    ordered: nil,
+    mmap: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    source_column: nil,
+    entry: nil
+  )
+  end
+
+  # Reads the input as Arrow record batches of `batch_size` rows, yielding each
+  # as an `OSV::ArrowBatch` typed like `to_parquet` types its columns. Returns
+  # an Enumerator when no block is given. Accepts the same options as
+  # `for_each`.
+  sig do
+    params(
+      input: T.any(String, Pathname, T::Array[T.any(String, Pathname)], StringIO, IO),
+      types: T.nilable(T::Hash[T.any(String, Symbol), T.any(String, Symbol)]),
+      batch_size: T.nilable(Integer),
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      buffer_size: T.nilable(Integer),
+      flexible: T.nilable(T::Boolean),
+      ignore_null_bytes: T.nilable(T::Boolean),
+      trim: T.nilable(T.any(String, Symbol)),
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      ordered: T.nilable(T::Boolean),
+      mmap: T.nilable(T::Boolean),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      source_column: T.nilable(String),
+      entry: T.nilable(String),
+      blk: T.nilable(T.proc.params(batch: OSV::ArrowBatch).void)
+    ).returns(T.untyped)
+  end
+  def self.read_arrow(
+    input,
+    types: nil,
+    batch_size: nil,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    buffer_size: nil,
+    flexible: nil,
+    ignore_null_bytes: nil,
+    trim: nil,
+    lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    ordered: nil,
+    mmap: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    source_column: nil,
+    entry: nil,
+    &blk
+  )
+  end
+
+  # Returns the number of rows of the input and statistics of each of its
+  # columns: their inferred type, nulls, distinct values, shortest and longest
+  # value, least and greatest value, and the `top` most frequent values.
+  # Distinct values are estimated past `distinct_limit` of them. Accepts the
+  # same options as `for_each`.
+  sig do
+    params(
+      input: T.any(String, Pathname, T::Array[T.any(String, Pathname)], StringIO, IO),
+      top: T.nilable(Integer),
+      distinct_limit: T.nilable(Integer),
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      buffer_size: T.nilable(Integer),
+      flexible: T.nilable(T::Boolean),
+      ignore_null_bytes: T.nilable(T::Boolean),
+      trim: T.nilable(T.any(String, Symbol)),
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      ordered: T.nilable(T::Boolean),
+      mmap: T.nilable(T::Boolean),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      source_column: T.nilable(String),
+      entry: T.nilable(String)
+    ).returns(T::Hash[Symbol, T.untyped])
+  end
+  def self.profile(
+    input,
+    top: nil,
+    distinct_limit: nil,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    buffer_size: nil,
+    flexible: nil,
+    ignore_null_bytes: nil,
+    trim: nil,
+    lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    ordered: nil,
+    mmap: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    source_column: nil,
+    entry: nil
+  )
+  end
+
+  # Groups the rows of the input by the `group_by` columns and summarizes each
+  # group: its row `count`, and the `sum`, `min`, `max` and `mean` of numeric
+  # columns. Returns a Hash of group values to results, or the results alone
+  # without `group_by`. Accepts the same options as `for_each`.
+  sig do
+    params(
+      input: T.any(String, Pathname, T::Array[T.any(String, Pathname)], StringIO, IO),
+      group_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      count: T.nilable(T::Boolean),
+      sum: T.nilable(T::Array[T.any(String, Symbol)]),
+      min: T.nilable(T::Array[T.any(String, Symbol)]),
+      max: T.nilable(T::Array[T.any(String, Symbol)]),
+      mean: T.nilable(T::Array[T.any(String, Symbol)]),
+      where: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      has_headers: T.nilable(T::Boolean),
+      col_sep: T.nilable(String),
+      quote_char: T.nilable(String),
+      nil_string: T.nilable(String),
+      buffer_size: T.nilable(Integer),
+      flexible: T.nilable(T::Boolean),
+      ignore_null_bytes: T.nilable(T::Boolean),
+      trim: T.nilable(T.any(String, Symbol)),
+      lossy: T.nilable(T::Boolean),
+      converters: T.nilable(T::Hash[T.any(String, Symbol), T.untyped]),
+      nil_values: T.nilable(T::Array[String]),
+      column_nil_values: T.nilable(T::Hash[T.any(String, Symbol), T::Array[String]]),
+      missing_value: T.nilable(String),
+      defaults: T.nilable(T::Hash[T.any(String, Symbol), String]),
+      duplicate_headers: T.nilable(T.any(String, Symbol)),
+      schema: T.nilable(T::Hash[Symbol, T.untyped]),
+      max_errors: T.nilable(Integer),
+      table_schema: T.nilable(T.any(String, Pathname, T::Hash[String, T.untyped])),
+      offset: T.nilable(Integer),
+      limit: T.nilable(Integer),
+      sample: T.nilable(T::Hash[Symbol, Integer]),
+      unique_by: T.nilable(T::Array[T.any(String, Symbol)]),
+      unique: T.nilable(T::Boolean),
+      keep: T.nilable(T.any(String, Symbol)),
+      parallel: T.nilable(T.any(T::Boolean, Integer)),
+      ordered: T.nilable(T::Boolean),
+      mmap: T.nilable(T::Boolean),
+      fileno: T.nilable(T::Boolean),
+      glob: T.nilable(T::Boolean),
+      union_headers: T.nilable(T::Boolean),
+      source_column: T.nilable(String),
+      entry: T.nilable(String)
+    ).returns(T::Hash[T.untyped, T.untyped])
+  end
+  def self.aggregate(
+    input,
+    group_by: nil,
+    count: nil,
+    sum: nil,
+    min: nil,
+    max: nil,
+    mean: nil,
+    where: nil,
+    has_headers: true,
+    col_sep: nil,
+    quote_char: nil,
+    nil_string: nil,
+    buffer_size: nil,
+    flexible: nil,
+    ignore_null_bytes: nil,
+    trim: nil,
+    lossy: nil,
+    converters: nil,
+    nil_values: nil,
+    column_nil_values: nil,
+    missing_value: nil,
+    defaults: nil,
+    duplicate_headers: nil,
+    schema: nil,
+    max_errors: nil,
+    table_schema: nil,
+    offset: nil,
+    limit: nil,
+    sample: nil,
+    unique_by: nil,
+    unique: nil,
+    keep: nil,
+    parallel: nil,
+    ordered: nil,
+    mmap: nil,
+    fileno: nil,
+    glob: nil,
+    union_headers: nil,
+    source_column: nil,
+    entry: nil
+  )
+  end
+
+
+  # A record batch yielded by `read_arrow`, exported through the Arrow C data
+  # interface.
+  class ArrowBatch
+    C_ARRAY_SIZE = T.let(80, Integer)
+    C_SCHEMA_SIZE = T.let(72, Integer)
+
+    sig { returns(Integer) }
+    def num_rows; end
+
+    sig { returns(Integer) }
+    def num_columns; end
+
+    sig { returns(T::Array[String]) }
+    def column_names; end
+
+    # Imports the batch as an Arrow::RecordBatch of red-arrow.
+    sig { returns(T.untyped) }
+    def to_arrow; end
+  end
 end
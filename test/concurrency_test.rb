@@ -1,6 +1,8 @@
 # frozen_string_literal: true
 
 require "osv"
+require "zlib"
+require "tempfile"
 require "minitest/autorun"
 
 # Tests focused on concurrency and thread-safety
@@ -108,4 +110,154 @@ class ConcurrencyTest < Minitest::Test
       file2.unlink
     end
   end
-end
\ No newline at end of file
+
+  def test_native_file_reading_releases_gvl
+    file = Tempfile.new(%w[gvl .csv])
+    begin
+      file.write("id,name,value\n")
+      200_000.times { |i| file.write("#{i},name#{i},#{i * 2}\n") }
+      file.flush
+
+      ticks = 0
+      running = true
+      ticker =
+        Thread.new do
+          while running
+            ticks += 1
+            Thread.pass
+          end
+        end
+      Thread.pass until ticks.positive?
+
+      before = ticks
+      assert_equal 200_000, OSV.count(file.path)
+      after = ticks
+
+      running = false
+      ticker.join
+
+      assert after > before, "expected another thread to run while the file was being parsed"
+    ensure
+      file.close
+      file.unlink
+    end
+  end
+
+  def test_parse_native_files_in_multiple_threads
+    file = Tempfile.new(%w[gvl_threads .csv])
+    begin
+      file.write("id,name\n")
+      10_000.times { |i| file.write("#{i},name#{i}\n") }
+      file.flush
+
+      expected = OSV.for_each(file.path, result_type: :array).to_a
+      threads = 8.times.map { Thread.new { OSV.for_each(file.path, result_type: :array).to_a } }
+      threads.each { |thread| assert_equal expected, thread.value }
+    ensure
+      file.close
+      file.unlink
+    end
+  end
+
+  # Tests for parsing local files on worker threads
+  def test_parallel_matches_sequential
+    with_parallel_file do |path|
+      expected = OSV.for_each(path).to_a
+      actual = OSV.for_each(path, parallel: 4).to_a
+      assert_equal 50_000, actual.size
+      assert_equal expected, actual
+    end
+  end
+
+  def test_parallel_true_uses_available_threads
+    with_parallel_file do |path|
+      expected = OSV.for_each(path, result_type: :array).to_a
+      actual = []
+      OSV.for_each(path, parallel: true, result_type: :array) { |row| actual << row }
+      assert_equal expected, actual
+    end
+  end
+
+  def test_parallel_unordered
+    with_parallel_file do |path|
+      expected = OSV.for_each(path, result_type: :array).to_a
+      actual = OSV.for_each(path, parallel: 4, ordered: false, result_type: :array).to_a
+      assert_equal expected.sort, actual.sort
+    end
+  end
+
+  def test_parallel_without_headers
+    with_parallel_file do |path|
+      expected = OSV.for_each(path, has_headers: false, result_type: :array).to_a
+      actual = OSV.for_each(path, has_headers: false, parallel: 3, result_type: :array).to_a
+      assert_equal expected, actual
+    end
+  end
+
+  def test_parallel_with_limit_and_offset
+    with_parallel_file do |path|
+      expected = OSV.for_each(path, offset: 100, limit: 10).to_a
+      assert_equal expected, OSV.for_each(path, parallel: 4, offset: 100, limit: 10).to_a
+    end
+  end
+
+  def test_parallel_count
+    with_parallel_file do |path|
+      assert_equal 50_000, OSV.count(path, parallel: 4)
+    end
+  end
+
+  def test_parallel_reports_unequal_lengths
+    with_parallel_file do |path|
+      File.open(path, "a") { |file| file.write("1,2\n") }
+      rows = 0
+      error = assert_raises(RuntimeError) { OSV.for_each(path, parallel: 4) { |_row| rows += 1 } }
+      assert_includes error.message, "found record with 2 fields, but the previous record has 3 fields"
+      assert_equal 50_000, rows
+    end
+  end
+
+  def test_parallel_falls_back_for_unsplittable_inputs
+    File.open("test/parallel.csv.gz", "wb") do |gz_file|
+      gz = Zlib::GzipWriter.new(gz_file)
+      gz.write(File.read("test/test.csv"))
+      gz.close
+    end
+    expected = OSV.for_each("test/test.csv").to_a
+    assert_equal expected, OSV.for_each("test/parallel.csv.gz", parallel: 4).to_a
+    File.open("test/test.csv") { |file| assert_equal expected, OSV.for_each(file, parallel: 4).to_a }
+  ensure
+    FileUtils.rm_f("test/parallel.csv.gz")
+  end
+
+  def test_parallel_stops_early
+    with_parallel_file do |path|
+      first = nil
+      OSV.for_each(path, parallel: 4) do |row|
+        first = row
+        break
+      end
+      assert_equal({ "id" => "0", "name" => "name, 0", "notes" => "multi\nline \"quoted\"\r\n0" }, first)
+    end
+  end
+
+  def test_invalid_parallel
+    assert_raises(ArgumentError) { OSV.for_each("test/test.csv", parallel: 0).to_a }
+    assert_raises(TypeError) { OSV.for_each("test/test.csv", parallel: "yes").to_a }
+  end
+
+  def with_parallel_file
+    Tempfile.create(%w[parallel .csv]) do |file|
+      file.write("id,name,notes\n")
+      50_000.times do |i|
+        if (i % 7).zero?
+          file.write("#{i},\"name, #{i}\",\"multi\nline \"\"quoted\"\"\r\n#{i}\"\n")
+        else
+          file.write("#{i},name#{i},plain\n")
+        end
+      end
+      file.close
+      yield file.path
+    end
+  end
+end
@@ -2,6 +2,11 @@
 
 require "osv"
 require "zlib"
+require "stringio"
+require "tempfile"
+require "date"
+require "json"
+require "pathname"
 require "minitest/autorun"
 
 # Core functionality tests for the OSV CSV parser
@@ -117,4 +122,839 @@ class CoreFunctionalityTest < Minitest::Test
     end
     assert_equal expected, actual
   end
+
+  # Tests for limit, offset and sampling applied inside the native reader
+  def numbered_csv(rows)
+    "id,name\n" + (1..rows).map { |i| "#{i},name#{i}\n" }.join
+  end
+
+  def test_limit
+    actual = []
+    OSV.for_each(StringIO.new(numbered_csv(10)), limit: 3) { |row| actual << row }
+    assert_equal [
+                   { "id" => "1", "name" => "name1" },
+                   { "id" => "2", "name" => "name2" },
+                   { "id" => "3", "name" => "name3" }
+                 ],
+                 actual
+  end
+
+  def test_limit_zero
+    assert_equal [], OSV.for_each(StringIO.new(numbered_csv(10)), limit: 0).to_a
+  end
+
+  def test_offset
+    ids = OSV.for_each(StringIO.new(numbered_csv(10)), offset: 7, result_type: :array).map(&:first)
+    assert_equal %w[8 9 10], ids
+  end
+
+  def test_offset_past_end
+    assert_equal [], OSV.for_each("test/test.csv", offset: 10).to_a
+  end
+
+  def test_offset_and_limit
+    ids = OSV.for_each(StringIO.new(numbered_csv(10)), offset: 2, limit: 3, result_type: :array).map(&:first)
+    assert_equal %w[3 4 5], ids
+  end
+
+  def test_offset_without_headers
+    rows = OSV.for_each("test/test.csv", has_headers: false, offset: 1, limit: 1, result_type: :array).to_a
+    assert_equal [%w[1 John 25]], rows
+  end
+
+  def test_limit_stops_reading_early
+    io = StringIO.new(numbered_csv(100_000))
+    OSV.for_each(io, limit: 1) { |_row| }
+    assert io.pos < io.size, "expected the reader to stop before consuming the whole input"
+  end
+
+  def test_sample_every
+    ids = OSV.for_each(StringIO.new(numbered_csv(10)), sample: { every: 3 }, result_type: :array).map(&:first)
+    assert_equal %w[1 4 7 10], ids
+  end
+
+  def test_sample_every_with_offset_and_limit
+    ids =
+      OSV
+        .for_each(StringIO.new(numbered_csv(20)), offset: 1, sample: { every: 5 }, limit: 2, result_type: :array)
+        .map(&:first)
+    assert_equal %w[2 7], ids
+  end
+
+  def test_sample_reservoir
+    ids = OSV.for_each(StringIO.new(numbered_csv(1000)), sample: { reservoir: 10 }, result_type: :array).map(&:first)
+    assert_equal 10, ids.size
+    assert_equal ids.uniq, ids
+    assert_equal ids.sort_by(&:to_i), ids, "reservoir rows should come back in file order"
+  end
+
+  def test_sample_reservoir_with_seed_is_reproducible
+    first = OSV.for_each(StringIO.new(numbered_csv(1000)), sample: { reservoir: 5, seed: 42 }).to_a
+    second = OSV.for_each(StringIO.new(numbered_csv(1000)), sample: { reservoir: 5, seed: 42 }).to_a
+    assert_equal first, second
+  end
+
+  def test_sample_reservoir_larger_than_input
+    rows = OSV.for_each("test/test.csv", sample: { reservoir: 10 }, result_type: :array).to_a
+    assert_equal [%w[1 John 25], %w[2 Jane 30], %w[3 Jim 35]], rows
+  end
+
+  def test_sample_reservoir_with_limit
+    rows = OSV.for_each(StringIO.new(numbered_csv(100)), sample: { reservoir: 10, seed: 1 }, limit: 4).to_a
+    assert_equal 4, rows.size
+  end
+
+  def test_invalid_sample
+    assert_raises(ArgumentError) { OSV.for_each("test/test.csv", sample: { every: 0 }).to_a }
+    assert_raises(ArgumentError) { OSV.for_each("test/test.csv", sample: { every: 2, reservoir: 2 }).to_a }
+    assert_raises(ArgumentError) { OSV.for_each("test/test.csv", sample: {}).to_a }
+    assert_raises(TypeError) { OSV.for_each("test/test.csv", sample: 3).to_a }
+  end
+
+  def test_negative_limit_raises
+    assert_raises(RangeError) { OSV.for_each("test/test.csv", limit: -1).to_a }
+  end
+
+  def test_enumerator_honours_selection_options
+    enum = OSV.for_each(StringIO.new(numbered_csv(10)), offset: 4, limit: 2, result_type: :array)
+    assert_instance_of Enumerator, enum
+    assert_equal [%w[5 name5], %w[6 name6]], enum.to_a
+  end
+
+  # Tests for native row counting
+  def test_count_with_headers
+    assert_equal 3, OSV.count("test/test.csv")
+  end
+
+  def test_count_without_headers
+    assert_equal 4, OSV.count("test/test.csv", has_headers: false)
+  end
+
+  def test_count_io
+    File.open("test/test.csv") { |file| assert_equal 3, OSV.count(file) }
+    assert_equal 3, OSV.count(StringIO.new(File.read("test/test.csv")))
+  end
+
+  def test_count_gzip
+    File.open("test/count.csv.gz", "wb") do |gz_file|
+      gz = Zlib::GzipWriter.new(gz_file)
+      gz.write(File.read("test/test.csv"))
+      gz.close
+    end
+    assert_equal 3, OSV.count("test/count.csv.gz")
+  ensure
+    FileUtils.rm_f("test/count.csv.gz")
+  end
+
+  def test_count_with_dialect_options
+    assert_equal 3, OSV.count("test/test.tsv", col_sep: "\t")
+
+    data = "id;note\n1;'a;b'\n2;'multi\nline'\n"
+    assert_equal 2, OSV.count(StringIO.new(data), col_sep: ";", quote_char: "'")
+  end
+
+  def test_count_empty_input
+    assert_equal 0, OSV.count(StringIO.new(""))
+    assert_equal 0, OSV.count(StringIO.new("id,name\n"))
+  end
+
+  def test_count_matches_for_each
+    data = "id,name\n" + (1..5000).map { |i| "#{i},\"name, #{i}\"\n" }.join
+    assert_equal OSV.for_each(StringIO.new(data)).count, OSV.count(StringIO.new(data))
+  end
+
+  def test_count_honours_row_selection
+    data = "id\n" + (1..100).map { |i| "#{i}\n" }.join
+    assert_equal 10, OSV.count(StringIO.new(data), limit: 10)
+    assert_equal 40, OSV.count(StringIO.new(data), offset: 60)
+    assert_equal 20, OSV.count(StringIO.new(data), sample: { every: 5 })
+  end
+
+  def test_count_uneven_rows
+    data = "a,b,c\n1,2,3\n4,5\n"
+    assert_raises(RuntimeError) { OSV.count(StringIO.new(data)) }
+    assert_equal 2, OSV.count(StringIO.new(data), flexible: true)
+  end
+
+  def test_count_includes_rows_breaking_the_schema
+    data = "id,status\n1,active\n2,deleted\n3,active\n"
+    schema = { columns: { "status" => { enum: %w[active] } } }
+    assert_equal 3, OSV.count(StringIO.new(data), schema: schema)
+    assert_equal OSV.for_each(StringIO.new(data), schema: schema).count, OSV.count(StringIO.new(data), schema: schema)
+  end
+
+  def test_count_honours_reading_options
+    assert_raises(RuntimeError) { OSV.count(StringIO.new("a\n1,2\n"), flexible: true, extra_fields: :raise) }
+    assert_raises(RuntimeError) { OSV.count(StringIO.new("a,a\n1,2\n"), duplicate_headers: :raise) }
+    assert_raises(ArgumentError) { OSV.count(StringIO.new("a\n1\n"), converters: { "missing" => :strip }) }
+  end
+
+  # Tests for parsing CSV content held in a String
+  PARSE_STRING_CONTENT = "id,name,notes\n1,John,\"multi\nline\"\n2,Jane,\"say \"\"hi\"\"\"\n3,Jim,\n"
+
+  def test_parse_string_matches_io_parsing
+    expected = OSV.for_each(StringIO.new(PARSE_STRING_CONTENT)).to_a
+    actual = []
+    OSV.parse_string(PARSE_STRING_CONTENT) { |row| actual << row }
+    assert_equal expected, actual
+    assert_equal({ "id" => "2", "name" => "Jane", "notes" => "say \"hi\"" }, actual[1])
+  end
+
+  def test_parse_string_enumerator_with_options
+    enum = OSV.parse_string(PARSE_STRING_CONTENT, result_type: :array, nil_string: "", offset: 1)
+    assert_instance_of Enumerator, enum
+    assert_equal [["2", "Jane", "say \"hi\""], ["3", "Jim", nil]], enum.to_a
+  end
+
+  def test_parse_string_is_an_alias_of_parse
+    assert_equal :parse, OSV.method(:parse_string).original_name
+  end
+
+  def test_parse_string_is_not_a_path
+    assert_equal [{ "a" => "1" }], OSV.parse_string("a\n1\n").to_a
+    assert_equal [], OSV.parse_string("test/test.csv").to_a
+  end
+
+  def test_parse_string_leaves_mutable_string_usable
+    content = +"a,b\n1,2\n"
+    rows = []
+    OSV.parse_string(content) do |row|
+      rows << row
+      content << "3,4\n"
+    end
+    assert_equal [{ "a" => "1", "b" => "2" }], rows
+    assert_equal "a,b\n1,2\n3,4\n", content
+    refute content.frozen?
+  end
+
+  def test_parse_string_survives_gc
+    content = (+"id,value\n") << (1..20_000).map { |i| "#{i},#{"x" * (i % 50)}" }.join("\n")
+    count = 0
+    OSV.parse_string(content) do |row|
+      count += 1
+      GC.start if (count % 5000).zero?
+      assert_equal "x" * (row["id"].to_i % 50), row["value"]
+    end
+    assert_equal 20_000, count
+  end
+
+  def test_parse_string_lossy
+    rows = OSV.parse_string("a\ncaf\xFF\n".b, lossy: true).to_a
+    assert_equal [{ "a" => "caf�" }], rows
+  end
+
+  def test_parse_string_requires_a_string
+    assert_raises(TypeError) { OSV.parse_string(42) { |row| } }
+  end
+
+  # Tests for checking headers and rows against a schema
+  SCHEMA_CONTENT = <<~CSV
+    id,status,name
+    1,active,John
+    x,deleted,Jane
+    3,inactive,
+    4,active,Jim
+  CSV
+
+  VALIDATION_SCHEMA = {
+    columns: {
+      "id" => { required: true, format: /\A\d+\z/ },
+      "status" => { enum: %w[active inactive] },
+      "name" => { required: true, max_length: 3 }
+    }
+  }.freeze
+
+  def test_validate_reports_every_problem
+    report = OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: VALIDATION_SCHEMA)
+    refute report[:valid]
+    assert_equal 4, report[:rows]
+    assert_equal 2, report[:invalid_rows]
+    assert_equal 4, report[:error_count]
+    assert_equal [
+      { row: 2, column: "id", rule: :format, value: "x" },
+      { row: 2, column: "status", rule: :enum, value: "deleted" },
+      { row: 2, column: "name", rule: :max_length, value: "Jane" },
+      { row: 3, column: "name", rule: :required, value: "" }
+    ], report[:errors]
+  end
+
+  def test_valid_input
+    report = OSV.validate(StringIO.new("id\n1\n2\n"), schema: { columns: { id: { required: true } } })
+    assert_equal({ valid: true, rows: 2, invalid_rows: 0, error_count: 0, errors: [] }, report)
+  end
+
+  def test_lengths_count_characters
+    schema = { columns: { "name" => { min_length: 2, max_length: 3 } } }
+    report = OSV.validate(StringIO.new("name\né\nééé\néééé\n"), schema: schema)
+    assert_equal [[1, :min_length], [3, :max_length]], report[:errors].map { |error| error.values_at(:row, :rule) }
+  end
+
+  def test_nil_and_empty_values_only_break_required
+    schema = { columns: { "name" => { enum: ["a"], min_length: 2, format: "b" } } }
+    assert OSV.validate(StringIO.new("name\n\n\"\"\n"), schema: schema)[:valid]
+
+    report = OSV.validate(StringIO.new("name\nNULL\n\"NULL\"\n"), schema: { columns: { "name" => { required: true } } },
+                                                                nil_values: ["NULL"])
+    assert_equal [{ row: 1, column: "name", rule: :required, value: nil }], report[:errors]
+  end
+
+  def test_missing_and_optional_columns
+    schema = { columns: { "id" => {}, "email" => {}, "notes" => { optional: true } } }
+    report = OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: schema)
+    assert_equal [{ row: nil, column: "email", rule: :missing_column, value: nil }], report[:errors]
+    assert_equal 4, report[:rows]
+  end
+
+  def test_strict_and_ordered_headers
+    schema = { columns: { "status" => {}, "id" => {} }, strict: true, ordered: true }
+    report = OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: schema)
+    assert_equal [%w[name unexpected_column], %w[id column_order]],
+                 report[:errors].map { |error| [error[:column], error[:rule].to_s] }
+
+    assert OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: { columns: { "id" => {}, "name" => {} }, ordered: true })[:valid]
+  end
+
+  def test_max_errors
+    report = OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: VALIDATION_SCHEMA, max_errors: 1)
+    assert_equal 1, report[:errors].size
+    assert_equal 4, report[:error_count]
+  end
+
+  def test_for_each_yields_every_row_and_returns_the_report
+    rows = []
+    report = OSV.parse(SCHEMA_CONTENT, schema: VALIDATION_SCHEMA) { |row| rows << row }
+    assert_equal %w[1 x 3 4], rows.map { |row| row["id"] }
+    assert_equal 2, report[:invalid_rows]
+    assert_equal OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: VALIDATION_SCHEMA), report
+  end
+
+  def test_selection_applies_after_validation
+    assert_equal [%w[3 inactive], %w[4 active]],
+                 OSV.parse(SCHEMA_CONTENT, schema: VALIDATION_SCHEMA, result_type: :array, offset: 2).map { |row| row.first(2) }
+
+    report = OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: VALIDATION_SCHEMA, limit: 1)
+    assert_equal 1, report[:rows]
+    assert report[:valid]
+  end
+
+  def test_converters_cannot_be_given_with_a_schema
+    schema = { columns: { "name" => { max_length: 3 } } }
+    assert_raises(ArgumentError) { OSV.parse("name\n Jim \n", schema: schema, converters: { "name" => :strip }).to_a }
+    assert_raises(ArgumentError) { OSV.validate(StringIO.new("name\n Jim \n"), schema: schema, converters: { "name" => :strip }) }
+    descriptor = { "fields" => [{ "name" => "name" }] }
+    assert_raises(ArgumentError) { OSV.parse("name\n Jim \n", table_schema: descriptor, converters: { "name" => :strip }).to_a }
+  end
+
+  def test_validate_reads_like_for_each
+    content = "id,status\n1,active\n1,active\n2,deleted\n"
+    options = { schema: { columns: { "status" => { enum: %w[active] } } }, unique_by: ["id"] }
+    report = OSV.validate(StringIO.new(content), **options)
+    assert_equal OSV.for_each(StringIO.new(content), **options) { |_row| }, report
+    assert_equal 1, report[:duplicates]
+    assert_equal 1, report[:invalid_rows]
+
+    assert_raises(ArgumentError) { OSV.validate(StringIO.new(content), **options, unique_by: ["missing"]) }
+  end
+
+  def test_header_failure_still_yields_rows
+    rows = []
+    report = OSV.parse(SCHEMA_CONTENT, schema: { columns: { "email" => {} } }) { |row| rows << row }
+    assert_equal %w[1 x 3 4], rows.map { |row| row["id"] }
+    assert_equal [:missing_column], report[:errors].map { |error| error[:rule] }
+  end
+
+  def test_validate_files
+    Tempfile.create(%w[schema .csv]) do |file|
+      file.write(SCHEMA_CONTENT)
+      file.flush
+      [{}, { mmap: true }, { parallel: 2 }].each do |options|
+        report = OSV.validate(file.path, schema: VALIDATION_SCHEMA, **options)
+        assert_equal 4, report[:error_count]
+        assert_equal [2, 2, 2, 3], report[:errors].map { |error| error[:row] }
+      end
+    end
+  end
+
+  def test_requires_schema
+    assert_raises(ArgumentError) { OSV.validate(StringIO.new(SCHEMA_CONTENT)) }
+  end
+
+  def test_invalid_schemas
+    assert_raises(TypeError) { OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: ["id"]) }
+    assert_raises(TypeError) { OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: { columns: ["id"] }) }
+    assert_raises(ArgumentError) { OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: { columns: {}, unique: true }) }
+    error = assert_raises(ArgumentError) do
+      OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: { columns: { "id" => { pattern: "x" } } })
+    end
+    assert_match(/pattern/, error.message)
+    assert_raises(ArgumentError) { OSV.validate(StringIO.new(SCHEMA_CONTENT), schema: { columns: { "id" => { format: "(" } } }) }
+  end
+
+  # Tests for Frictionless Table Schema descriptors
+  TABLE_SCHEMA_CONTENT = <<~CSV
+    id,price,active,created_on,updated_at,code
+    1,9.99,true,2024-01-31,2024-01-31T10:00:00Z,AB
+    2,,0,2024-02-29,2024-02-01T09:30:00.5+02:00,CD
+  CSV
+
+  TABLE_SCHEMA_DESCRIPTOR = {
+    "fields" => [
+      { "name" => "id", "type" => "integer", "constraints" => { "required" => true, "unique" => true } },
+      { "name" => "price", "type" => "number", "constraints" => { "minimum" => 0 } },
+      { "name" => "active", "type" => "boolean" },
+      { "name" => "created_on", "type" => "date" },
+      { "name" => "updated_at", "type" => "datetime" },
+      { "name" => "code", "type" => "string", "constraints" => { "pattern" => "[A-Z]{2}" } }
+    ]
+  }.freeze
+
+  def test_types_drive_conversion
+    rows = OSV.parse(TABLE_SCHEMA_CONTENT, table_schema: TABLE_SCHEMA_DESCRIPTOR).to_a
+    assert_equal [1, 2], rows.map { |row| row["id"] }
+    assert_equal [9.99, nil], rows.map { |row| row["price"] }
+    assert_equal [true, false], rows.map { |row| row["active"] }
+    assert_equal [Date.new(2024, 1, 31), Date.new(2024, 2, 29)], rows.map { |row| row["created_on"] }
+    assert_equal "AB", rows[0]["code"]
+
+    first, second = rows.map { |row| row["updated_at"] }
+    assert_equal Time.utc(2024, 1, 31, 10), first
+    assert first.utc?
+    assert_equal Time.new(2024, 2, 1, 9, 30, 0.5r, "+02:00"), second
+    assert_equal 7200, second.utc_offset
+  end
+
+  def test_array_rows_are_converted
+    rows = OSV.parse(TABLE_SCHEMA_CONTENT, table_schema: TABLE_SCHEMA_DESCRIPTOR, result_type: :array).to_a
+    assert_equal [1, 9.99, true], rows[0].first(3)
+  end
+
+  def test_loads_descriptor_files
+    Tempfile.create(%w[tableschema .json]) do |file|
+      file.write(JSON.generate(TABLE_SCHEMA_DESCRIPTOR))
+      file.flush
+      assert_equal [1, 2], OSV.parse(TABLE_SCHEMA_CONTENT, table_schema: file.path).map { |row| row["id"] }
+      assert_equal [1, 2], OSV.parse(TABLE_SCHEMA_CONTENT, table_schema: Pathname.new(file.path)).map { |row| row["id"] }
+    end
+  end
+
+  def test_constraints_drive_validation
+    content = <<~CSV
+      id,price,active,created_on,updated_at,code
+      1,-1,yes,2024-02-30,2024-01-31 10:00,abc
+      1,1,1,2024-01-01,2024-01-01T00:00:00,XY
+    CSV
+    report = OSV.validate(StringIO.new(content), table_schema: TABLE_SCHEMA_DESCRIPTOR)
+    assert_equal [
+      [1, "price", :minimum],
+      [1, "active", :type],
+      [1, "created_on", :type],
+      [1, "updated_at", :type],
+      [1, "code", :format],
+      [2, "id", :unique]
+    ], report[:errors].map { |error| error.values_at(:row, :column, :rule) }
+  end
+
+  def test_enum_values_are_compared_once_cast
+    descriptor = { "fields" => [{ "name" => "size", "type" => "number", "constraints" => { "enum" => [1, 2.5] } }] }
+    report = OSV.validate(StringIO.new("size\n1.0\n2.50\n3\n"), table_schema: descriptor)
+    assert_equal [[3, "3"]], report[:errors].map { |error| error.values_at(:row, :value) }
+  end
+
+  def test_missing_values_drive_null_handling
+    descriptor = {
+      "missingValues" => ["", "NA"],
+      "fields" => [{ "name" => "id" }, { "name" => "note", "missingValues" => ["-"] }]
+    }
+    rows = OSV.parse("id,note\nNA,NA\n\"NA\",-\n", table_schema: descriptor, result_type: :array).to_a
+    assert_equal [[nil, "NA"], ["NA", nil]], rows
+
+    rows = OSV.parse("id,note\n,x\n", table_schema: descriptor, nil_values: []).to_a
+    assert_equal [{ "id" => "", "note" => "x" }], rows
+  end
+
+  def test_number_formats
+    descriptor = {
+      "fields" => [
+        { "name" => "amount", "type" => "number", "bareNumber" => false, "decimalChar" => ",", "groupChar" => "." },
+        { "name" => "count", "type" => "integer", "groupChar" => "," },
+        { "name" => "ok", "type" => "boolean", "trueValues" => ["y"], "falseValues" => ["n"] }
+      ]
+    }
+    rows = OSV.parse("amount,count,ok\n\"€1.234,5\",\"1,000\",y\n", table_schema: descriptor).to_a
+    assert_equal [{ "amount" => 1234.5, "count" => 1000, "ok" => true }], rows
+  end
+
+  def test_fields_must_match_the_header_exactly
+    descriptor = { "fields" => [{ "name" => "price" }, { "name" => "id" }] }
+    report = OSV.validate(StringIO.new("id,price\n1,2\n"), table_schema: descriptor)
+    assert_equal [:column_order], report[:errors].map { |error| error[:rule] }
+
+    report = OSV.validate(StringIO.new("id,price,extra\n1,2,3\n"),
+                          table_schema: descriptor.merge("fieldsMatch" => "subset"))
+    assert report[:valid]
+  end
+
+  def test_subset_requires_every_field
+    descriptor = { "fields" => [{ "name" => "price" }, { "name" => "id" }], "fieldsMatch" => "subset" }
+    report = OSV.validate(StringIO.new("id,extra\n1,2\n"), table_schema: descriptor)
+    assert_equal [["price", :missing_column]], report[:errors].map { |error| [error[:column], error[:rule]] }
+  end
+
+  def test_superset_only_allows_fields
+    descriptor = { "fields" => [{ "name" => "price" }, { "name" => "id" }], "fieldsMatch" => "superset" }
+    assert OSV.validate(StringIO.new("id\n1\n"), table_schema: descriptor)[:valid]
+
+    report = OSV.validate(StringIO.new("id,price,extra\n1,2,3\n"), table_schema: descriptor)
+    assert_equal [["extra", :unexpected_column]], report[:errors].map { |error| [error[:column], error[:rule]] }
+  end
+
+  def test_partial_requires_one_field
+    descriptor = { "fields" => [{ "name" => "price" }, { "name" => "id" }], "fieldsMatch" => "partial" }
+    assert OSV.validate(StringIO.new("id,extra\n1,2\n"), table_schema: descriptor)[:valid]
+
+    report = OSV.validate(StringIO.new("extra\n1\n"), table_schema: descriptor)
+    assert_equal [["price", :missing_column], ["id", :missing_column]],
+                 report[:errors].map { |error| [error[:column], error[:rule]] }
+  end
+
+  def test_invalid_values_are_yielded_uncast
+    content = "id\n1\nx\n3\n"
+    descriptor = { "fields" => [{ "name" => "id", "type" => "integer" }] }
+    assert_equal [1, "x", 3], OSV.parse(content, table_schema: descriptor).map { |row| row["id"] }
+  end
+
+  def test_invalid_descriptors
+    assert_raises(ArgumentError) { OSV.parse(TABLE_SCHEMA_CONTENT, table_schema: { "fields" => "id" }).to_a }
+    assert_raises(ArgumentError) { OSV.parse(TABLE_SCHEMA_CONTENT, table_schema: TABLE_SCHEMA_DESCRIPTOR, schema: { columns: {} }).to_a }
+    error = assert_raises(ArgumentError) do
+      OSV.parse(TABLE_SCHEMA_CONTENT, table_schema: { "fields" => [{ "name" => "id", "type" => "date", "format" => "%d/%m/%Y" }] }).to_a
+    end
+    assert_match(/format/, error.message)
+    assert_raises(ArgumentError) do
+      OSV.parse(TABLE_SCHEMA_CONTENT, table_schema: { "fields" => [{ "name" => "id", "constraints" => { "minimum" => 1 } }] }).to_a
+    end
+  end
+
+  # Tests for profiling the columns of an input
+  PROFILE_CONTENT = <<~CSV
+    id,name,score,active,joined_on,note
+    1,Alice,9.5,true,2024-01-31,
+    2,Bob,,false,2024-02-29,
+    3,Carol,7,TRUE,2023-12-01,
+    4,Bob,10,false,,
+  CSV
+
+  def profile(content = PROFILE_CONTENT, **options)
+    OSV.profile(StringIO.new(content), **options)
+  end
+
+  def test_counts_rows_and_nulls
+    result = profile
+    assert_equal 4, result[:rows]
+    assert_equal %w[id name score active joined_on note], result[:columns].keys
+    assert_equal [0, 0, 1, 0, 1, 4], result[:columns].values.map { |column| column[:nulls] }
+  end
+
+  def test_infers_types
+    types = profile[:columns].transform_values { |column| column[:type] }
+    assert_equal(
+      { "id" => :integer, "name" => :string, "score" => :float, "active" => :boolean,
+        "joined_on" => :date, "note" => :string },
+      types
+    )
+  end
+
+  def test_min_and_max_of_the_inferred_type
+    columns = profile[:columns]
+    assert_equal [1, 4], columns["id"].values_at(:min, :max)
+    assert_equal [7.0, 10.0], columns["score"].values_at(:min, :max)
+    assert_equal %w[Alice Carol], columns["name"].values_at(:min, :max)
+    assert_equal [false, true], columns["active"].values_at(:min, :max)
+    assert_equal [Date.new(2023, 12, 1), Date.new(2024, 2, 29)], columns["joined_on"].values_at(:min, :max)
+    assert_equal [nil, nil], columns["note"].values_at(:min, :max)
+  end
+
+  def test_lengths
+    columns = profile[:columns]
+    assert_equal [3, 5], columns["name"].values_at(:min_length, :max_length)
+    assert_equal [nil, nil], columns["note"].values_at(:min_length, :max_length)
+  end
+
+  def test_distinct_and_top_values
+    name = profile[:columns]["name"]
+    assert_equal 3, name[:distinct]
+    refute name[:distinct_estimated]
+    assert_equal [["Bob", 2], ["Alice", 1], ["Carol", 1]], name[:top]
+
+    assert_equal [["Bob", 2]], profile(top: 1)[:columns]["name"][:top]
+    assert_equal [], profile(top: 0)[:columns]["name"][:top]
+  end
+
+  def test_estimates_distinct_values_past_the_limit
+    content = "n\n" + (1..5000).map { |i| "#{i}\n" }.join + "1\n"
+    column = profile(content, distinct_limit: 100)[:columns]["n"]
+    assert column[:distinct_estimated]
+    assert_in_delta 5000, column[:distinct], 150
+
+    # Values counted before the limit keep their exact counts
+    assert_equal ["1", 2], column[:top].first
+
+    column = profile(content)[:columns]["n"]
+    refute column[:distinct_estimated]
+    assert_equal 5000, column[:distinct]
+  end
+
+  def test_reading_options_apply
+    result = profile(limit: 2, nil_values: ["Bob"])
+    assert_equal 2, result[:rows]
+    assert_equal 1, result[:columns]["name"][:nulls]
+
+    descriptor = { "fields" => [{ "name" => "n", "type" => "integer", "groupChar" => "," }] }
+    column = profile("n\n\"1,000\"\n2\n", table_schema: descriptor)[:columns]["n"]
+    assert_equal [2, 1000], column.values_at(:min, :max)
+    assert_equal [["1000", 1], ["2", 1]], column[:top]
+  end
+
+  def test_reads_files
+    Tempfile.create(%w[rows .csv]) do |file|
+      file.write(PROFILE_CONTENT)
+      file.flush
+      [{}, { mmap: true }, { parallel: 2 }].each do |options|
+        assert_equal 4, OSV.profile(file.path, **options)[:rows]
+      end
+    end
+  end
+
+  def test_invalid_options
+    assert_raises(ArgumentError) { profile(distinct_limit: 0) }
+    assert_raises(ArgumentError) { profile(unknown: true) }
+  end
+
+  # Tests for grouping and summarizing rows
+  AGGREGATE_CONTENT = <<~CSV
+    customer,region,amount,quantity
+    alice,north,10.5,1
+    bob,south,3,2
+    alice,south,4,
+    carol,north,,5
+    bob,south,7,1
+  CSV
+
+  def aggregate(content = AGGREGATE_CONTENT, **options)
+    OSV.aggregate(StringIO.new(content), **options)
+  end
+
+  def test_sum_and_count_by_column
+    result = aggregate(group_by: ["customer"], sum: ["amount"], count: true)
+    assert_equal(
+      {
+        "alice" => { count: 2, sum: { "amount" => 14.5 } },
+        "bob" => { count: 2, sum: { "amount" => 10 } },
+        "carol" => { count: 1, sum: { "amount" => nil } }
+      },
+      result
+    )
+    assert_equal %w[alice bob carol], result.keys
+    assert_kind_of Integer, result["bob"][:sum]["amount"]
+  end
+
+  def test_min_max_and_mean
+    result = aggregate(group_by: [:region], min: %w[amount quantity], max: ["amount"], mean: ["quantity"])
+    assert_equal({ "amount" => 3, "quantity" => 1 }, result["south"][:min])
+    assert_equal({ "amount" => 10.5 }, result["north"][:max])
+    assert_equal({ "quantity" => 1.5 }, result["south"][:mean])
+    assert_equal({ "quantity" => 3.0 }, result["north"][:mean])
+  end
+
+  def test_several_group_columns
+    result = aggregate(group_by: %w[region customer], count: true)
+    assert_equal [%w[north alice], %w[south bob], %w[south alice], %w[north carol]], result.keys
+    assert_equal 2, result[%w[south bob]][:count]
+  end
+
+  def test_without_group_columns
+    assert_equal({ count: 5, sum: { "quantity" => 9 } }, aggregate(sum: ["quantity"], count: true))
+    assert_equal({ count: 0, sum: { "quantity" => nil } }, aggregate("quantity\n", sum: ["quantity"], count: true))
+  end
+
+  def test_nil_group_values
+    result = aggregate("k,v\n,1\na,2\n,3\n", group_by: ["k"], sum: ["v"], nil_values: [""])
+    assert_equal({ nil => { sum: { "v" => 4 } }, "a" => { sum: { "v" => 2 } } }, result)
+  end
+
+  def test_where_and_reading_options
+    result = aggregate(group_by: ["customer"], count: true, where: { "region" => "south" })
+    assert_equal({ "bob" => { count: 2 }, "alice" => { count: 1 } }, result)
+
+    result = aggregate(group_by: ["customer"], count: true, limit: 2)
+    assert_equal({ "alice" => { count: 1 }, "bob" => { count: 1 } }, result)
+  end
+
+  def test_table_schema_values
+    descriptor = { "fields" => [{ "name" => "n", "type" => "integer", "groupChar" => "," }] }
+    assert_equal({ sum: { "n" => 1002 } }, aggregate("n\n\"1,000\"\n2\n", sum: ["n"], table_schema: descriptor))
+  end
+
+  def test_non_numeric_values
+    error = assert_raises(RuntimeError) { aggregate(sum: ["customer"]) }
+    assert_match(/"alice" of column customer isn't of type number/, error.message)
+  end
+
+  def test_aggregate_reads_files
+    Tempfile.create(%w[rows .csv]) do |file|
+      file.write(AGGREGATE_CONTENT)
+      file.flush
+      [{}, { mmap: true }, { parallel: 2 }].each do |options|
+        assert_equal({ count: 5 }, OSV.aggregate(file.path, count: true, **options))
+      end
+    end
+  end
+
+  def test_aggregate_invalid_options
+    assert_raises(ArgumentError) { aggregate(group_by: ["customer"]) }
+    assert_raises(ArgumentError) { aggregate(group_by: ["email"], count: true) }
+    assert_raises(ArgumentError) { aggregate(sum: ["email"]) }
+    assert_raises(TypeError) { aggregate(sum: [1]) }
+  end
+
+  # Tests for dropping repeated rows inside the native reader
+  UNIQUE_CONTENT = <<~CSV
+    id,name,status
+    1,alice,new
+    2,bob,new
+    1,alice,paid
+    3,carol,new
+    2,bob,new
+    1,alice,shipped
+  CSV
+
+  def read_unique(**options)
+    OSV.for_each(StringIO.new(UNIQUE_CONTENT), result_type: :array, **options).to_a
+  end
+
+  def test_unique_by_keeps_first
+    assert_equal [%w[1 alice new], %w[2 bob new], %w[3 carol new]], read_unique(unique_by: ["id"])
+  end
+
+  def test_unique_by_symbols
+    assert_equal %w[1 2 3], read_unique(unique_by: [:id]).map(&:first)
+  end
+
+  def test_unique_by_several_columns
+    rows = read_unique(unique_by: %w[id status])
+    assert_equal [%w[1 alice new], %w[2 bob new], %w[1 alice paid], %w[3 carol new], %w[1 alice shipped]], rows
+  end
+
+  def test_keep_last
+    assert_equal [%w[3 carol new], %w[2 bob new], %w[1 alice shipped]], read_unique(unique_by: ["id"], keep: :last)
+  end
+
+  def test_keep_first_explicitly
+    assert_equal read_unique(unique_by: ["id"]), read(unique_by: ["id"], keep: "first")
+  end
+
+  def test_unique_whole_row
+    rows = read_unique(unique: true)
+    assert_equal [%w[1 alice new], %w[2 bob new], %w[1 alice paid], %w[3 carol new], %w[1 alice shipped]], rows
+  end
+
+  def test_returns_number_of_duplicates
+    report = OSV.for_each(StringIO.new(UNIQUE_CONTENT), unique_by: ["id"]) { |_row| }
+    assert_equal({ duplicates: 3 }, report)
+
+    report = OSV.for_each(StringIO.new(UNIQUE_CONTENT), unique_by: ["id"], keep: :last) { |_row| }
+    assert_equal({ duplicates: 3 }, report)
+
+    report = OSV.for_each(StringIO.new(UNIQUE_CONTENT), unique: true) { |_row| }
+    assert_equal({ duplicates: 1 }, report)
+  end
+
+  def test_returns_nil_without_deduplication
+    assert_nil OSV.for_each(StringIO.new(UNIQUE_CONTENT)) { |_row| }
+  end
+
+  def test_duplicates_added_to_validation_report
+    schema = { columns: { "status" => { enum: %w[new paid] } } }
+    report = OSV.for_each(StringIO.new(UNIQUE_CONTENT), schema: schema, unique_by: ["id"]) { |_row| }
+    assert_equal 1, report[:invalid_rows]
+    assert_equal 2, report[:duplicates]
+  end
+
+  def test_applies_before_offset_and_limit
+    assert_equal %w[2 3], read_unique(unique_by: ["id"], offset: 1, limit: 2).map(&:first)
+    assert_equal %w[3 2], read_unique(unique_by: ["id"], keep: :last, limit: 2).map(&:first)
+  end
+
+  def test_count
+    assert_equal 3, OSV.count(StringIO.new(UNIQUE_CONTENT), unique_by: ["id"])
+  end
+
+  def test_applies_after_where
+    Tempfile.create(%w[unique .csv]) do |output|
+      count = OSV.transform(StringIO.new(UNIQUE_CONTENT), output.path, select: %w[id status], where: { "status" => %w[paid shipped] }, unique_by: ["id"])
+      assert_equal 1, count
+      assert_equal "id,status\n1,paid\n", File.read(output.path)
+    end
+  end
+
+  def test_aggregate
+    result = OSV.aggregate(StringIO.new(UNIQUE_CONTENT), group_by: ["status"], count: true, unique: true)
+    assert_equal({ "new" => { count: 3 }, "paid" => { count: 1 }, "shipped" => { count: 1 } }, result)
+  end
+
+  def test_nil_values_are_compared_as_nil
+    csv = "id,name\n\"\",a\n,b\nNULL,c\n"
+    rows = OSV.for_each(StringIO.new(csv), result_type: :array, nil_values: ["", "NULL"], unique_by: ["id"]).to_a
+    assert_equal [["", "a"], [nil, "b"]], rows
+  end
+
+  def test_missing_fields_are_nil
+    csv = "id,name\n1\n1,\n1\n"
+    rows = OSV.for_each(StringIO.new(csv), result_type: :array, flexible: true, nil_values: [""], unique: true).to_a
+    assert_equal [["1"]], rows
+  end
+
+  def test_invalid_bytes_are_compared_raw
+    csv = "id,name\n\xFF,a\n\xFE,b\n\xFF,c\n".b
+    rows = OSV.for_each(StringIO.new(csv), result_type: :array, lossy: true, unique_by: ["id"]).to_a
+    assert_equal %w[a b], rows.map(&:last)
+  end
+
+  def test_keep_last_with_many_repeats
+    csv = "id,value\n" + (1..10_000).map { |i| "#{i % 3},#{i}\n" }.join
+    rows = OSV.for_each(StringIO.new(csv), result_type: :array, unique_by: ["id"], keep: :last).to_a
+    assert_equal [%w[2 9998], %w[0 9999], %w[1 10000]], rows
+  end
+
+  def test_hash_rows
+    rows = OSV.for_each(StringIO.new(UNIQUE_CONTENT), unique_by: ["name"], keep: :last).to_a
+    assert_equal %w[carol bob alice], rows.map { |row| row["name"] }
+  end
+
+  def test_files_with_mmap_and_parallel
+    Tempfile.create(%w[unique .csv]) do |file|
+      file.write("id,value\n")
+      10_000.times { |i| file.write("#{i % 100},#{i}\n") }
+      file.close
+
+      [{}, { mmap: true }, { parallel: 2 }].each do |options|
+        first = OSV.for_each(file.path, unique_by: ["id"], result_type: :array, **options).to_a
+        assert_equal (0...100).map { |i| [i.to_s, i.to_s] }, first
+
+        last = OSV.for_each(file.path, unique_by: ["id"], keep: :last, result_type: :array, **options).to_a
+        assert_equal (0...100).map { |i| [i.to_s, (9900 + i).to_s] }, last
+      end
+    end
+  end
+
+  def test_unknown_column
+    assert_raises(ArgumentError) { read_unique(unique_by: ["missing"]) }
+  end
+
+  def test_unique_invalid_options
+    assert_raises(ArgumentError) { read_unique(unique_by: ["id"], unique: true) }
+    assert_raises(ArgumentError) { read_unique(unique_by: []) }
+    assert_raises(ArgumentError) { read_unique(keep: :last) }
+    assert_raises(ArgumentError) { read_unique(unique_by: ["id"], keep: :middle) }
+  end
 end
\ No newline at end of file
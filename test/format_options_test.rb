@@ -1,6 +1,7 @@
 # frozen_string_literal: true
 
 require "osv"
+require "tempfile"
 require "minitest/autorun"
 
 # Tests focused on parsing options and formatting
@@ -278,4 +279,444 @@ class FormatOptionsTest < Minitest::Test
       end
     assert_equal expected, actual
   end
+
+  # Tests for per-column value converters
+  CONVERTERS_CONTENT = <<~CSV
+    name,email,phone
+    "  John   Smith "," John@Example.COM ",(555) 123-4567
+    Jane,jane@example.com,555.765.4321
+  CSV
+
+  def test_strip_and_downcase
+    rows = OSV.parse(CONVERTERS_CONTENT, converters: { "email" => %i[strip downcase] }).to_a
+    assert_equal %w[john@example.com jane@example.com], rows.map { |row| row["email"] }
+    assert_equal "  John   Smith ", rows[0]["name"]
+  end
+
+  def test_squish
+    rows = OSV.parse(CONVERTERS_CONTENT, converters: { name: :squish }).to_a
+    assert_equal ["John Smith", "Jane"], rows.map { |row| row["name"] }
+  end
+
+  def test_gsub_with_regexp
+    rows = OSV.parse(CONVERTERS_CONTENT, converters: { "phone" => [{ gsub: [/\D/, ""] }] }).to_a
+    assert_equal %w[5551234567 5557654321], rows.map { |row| row["phone"] }
+  end
+
+  def test_gsub_with_groups_and_flags
+    converters = { "email" => [:strip, { gsub: [/(?<user>\w+)@EXAMPLE\.com/i, "\\k<user> at \\0"] }] }
+    rows = OSV.parse(CONVERTERS_CONTENT, converters: converters).to_a
+    assert_equal ["John at John@Example.COM", "jane at jane@example.com"], rows.map { |row| row["email"] }
+
+    rows = OSV.parse(CONVERTERS_CONTENT, converters: { "phone" => { gsub: [/(\d{3})\D(\d{4})/, "\\1-\\2"] } }).to_a
+    assert_equal ["(555) 123-4567", "555.765-4321"], rows.map { |row| row["phone"] }
+  end
+
+  def test_gsub_with_literal_string
+    rows = OSV.parse(CONVERTERS_CONTENT, converters: { "phone" => { gsub: [".", "-"] } }).to_a
+    assert_equal ["(555) 123-4567", "555-765-4321"], rows.map { |row| row["phone"] }
+  end
+
+  def test_converters_run_in_order
+    rows = OSV.parse(CONVERTERS_CONTENT, converters: { "name" => [{ gsub: [/ +/, "_"] }, :squish] }).to_a
+    assert_equal "_John_Smith_", rows[0]["name"]
+  end
+
+  def test_array_results_and_headerless_input
+    rows = OSV.parse(CONVERTERS_CONTENT, result_type: :array, converters: { "email" => :strip }).to_a
+    assert_equal "John@Example.COM", rows[0][1]
+
+    rows = OSV.parse(CONVERTERS_CONTENT, has_headers: false, result_type: :array, converters: { "c0" => :downcase }).to_a
+    assert_equal %w[name jane], [rows[0][0], rows[2][0]]
+  end
+
+  def test_nil_values_are_not_converted
+    rows = OSV.parse("a,b\nNULL,x\n", nil_string: "NULL", converters: { "a" => :downcase }).to_a
+    assert_equal [{ "a" => nil, "b" => "x" }], rows
+  end
+
+  def test_converters_with_files
+    Tempfile.create(%w[converters .csv]) do |file|
+      file.write(CONVERTERS_CONTENT)
+      file.flush
+      rows = OSV.for_each(file.path, mmap: true, converters: { "email" => %i[strip downcase] }).to_a
+      assert_equal "john@example.com", rows[0]["email"]
+    end
+  end
+
+  def test_unknown_column
+    error = assert_raises(ArgumentError) { OSV.parse(CONVERTERS_CONTENT, converters: { "fax" => :strip }).to_a }
+    assert_match(/fax/, error.message)
+  end
+
+  def test_invalid_converters
+    assert_raises(ArgumentError) { OSV.parse(CONVERTERS_CONTENT, converters: { "name" => :titleize }).to_a }
+    assert_raises(ArgumentError) { OSV.parse(CONVERTERS_CONTENT, converters: { "name" => { sub: ["a", "b"] } }).to_a }
+    assert_raises(ArgumentError) { OSV.parse(CONVERTERS_CONTENT, converters: { "name" => { gsub: [/(?<=a)b/, ""] } }).to_a }
+    assert_raises(TypeError) { OSV.parse(CONVERTERS_CONTENT, converters: [:strip]).to_a }
+  end
+
+  # Tests for nil markers that only match unquoted fields
+  NIL_VALUES_MARKERS = ["", "NULL", "\\N"].freeze
+
+  NIL_VALUES_CONTENT = <<~'CSV'
+    id,name,notes
+    1,,""
+    2,NULL,"NULL"
+    3,\N,"\N"
+    4,John,Smith
+  CSV
+
+  NIL_VALUES_EXPECTED = [
+    { "id" => "1", "name" => nil, "notes" => "" },
+    { "id" => "2", "name" => nil, "notes" => "NULL" },
+    { "id" => "3", "name" => nil, "notes" => "\\N" },
+    { "id" => "4", "name" => "John", "notes" => "Smith" }
+  ].freeze
+
+  def test_markers_only_match_unquoted_fields
+    assert_equal NIL_VALUES_EXPECTED, OSV.parse(NIL_VALUES_CONTENT, nil_values: NIL_VALUES_MARKERS).to_a
+  end
+
+  def test_default_keeps_empty_fields_as_strings
+    rows = OSV.parse(NIL_VALUES_CONTENT).to_a
+    assert_equal ["", ""], rows[0].values_at("name", "notes")
+    assert_equal "NULL", rows[1]["name"]
+  end
+
+  def test_nil_string_still_matches_quoted_fields
+    rows = OSV.parse(NIL_VALUES_CONTENT, nil_string: "NULL", nil_values: [""]).to_a
+    assert_equal [nil, nil], rows[1].values_at("name", "notes")
+    assert_equal [nil, ""], rows[0].values_at("name", "notes")
+  end
+
+  def test_column_nil_values_replace_nil_values
+    rows = OSV.parse(NIL_VALUES_CONTENT, nil_values: NIL_VALUES_MARKERS, column_nil_values: { name: ["NULL"] }).to_a
+    assert_equal ["", nil, "\\N", "John"], rows.map { |row| row["name"] }
+
+    rows = OSV.parse(NIL_VALUES_CONTENT, column_nil_values: { "name" => [""] }).to_a
+    assert_equal [nil, "NULL", "\\N", "John"], rows.map { |row| row["name"] }
+    assert_equal "", rows[0]["notes"]
+  end
+
+  def test_empty_column_markers_keep_every_value
+    rows = OSV.parse(NIL_VALUES_CONTENT, nil_values: NIL_VALUES_MARKERS, column_nil_values: { "name" => [] }).to_a
+    assert_equal ["", "NULL", "\\N", "John"], rows.map { |row| row["name"] }
+  end
+
+  def test_quotes_inside_and_across_fields
+    content = "a,b,c\n\"x,\"\"NULL\"\"\",NULL,\"line\nbreak\"\r\n\"\",,\"\"\n"
+    rows = OSV.parse(content, nil_values: NIL_VALUES_MARKERS, result_type: :array).to_a
+    assert_equal [["x,\"NULL\"", nil, "line\nbreak"], ["", nil, ""]], rows
+  end
+
+  def test_headerless_input_and_last_field_without_newline
+    rows = OSV.parse("NULL,\"NULL\"\n\"\",", has_headers: false, nil_values: NIL_VALUES_MARKERS, result_type: :array).to_a
+    assert_equal [[nil, "NULL"], ["", nil]], rows
+  end
+
+  def test_custom_quote_char_and_delimiter
+    rows = OSV.parse("a|b\n'NULL'|NULL\n", col_sep: "|", quote_char: "'", nil_values: ["NULL"]).to_a
+    assert_equal [{ "a" => "NULL", "b" => nil }], rows
+  end
+
+  def test_markers_are_not_converted
+    rows = OSV.parse(NIL_VALUES_CONTENT, nil_values: NIL_VALUES_MARKERS, converters: { "notes" => :downcase }).to_a
+    assert_equal ["", "null", "\\n", "smith"], rows.map { |row| row["notes"] }
+  end
+
+  def test_files_mmap_and_parallel
+    Tempfile.create(%w[nil_values .csv]) do |file|
+      file.write(NIL_VALUES_CONTENT)
+      file.flush
+      assert_equal NIL_VALUES_EXPECTED, OSV.for_each(file.path, nil_values: NIL_VALUES_MARKERS).to_a
+      assert_equal NIL_VALUES_EXPECTED, OSV.for_each(file.path, nil_values: NIL_VALUES_MARKERS, mmap: true).to_a
+      assert_equal NIL_VALUES_EXPECTED, OSV.for_each(file.path, nil_values: NIL_VALUES_MARKERS, parallel: 2).to_a
+      assert_equal NIL_VALUES_EXPECTED, File.open(file.path) { |io| OSV.for_each(io, nil_values: NIL_VALUES_MARKERS).to_a }
+    end
+  end
+
+  def test_sampled_and_offset_rows
+    rows = OSV.parse(NIL_VALUES_CONTENT, nil_values: NIL_VALUES_MARKERS, sample: { reservoir: 10, seed: 1 }).to_a
+    assert_equal NIL_VALUES_EXPECTED, rows
+    assert_equal NIL_VALUES_EXPECTED.drop(1).take(2), OSV.parse(NIL_VALUES_CONTENT, nil_values: NIL_VALUES_MARKERS, offset: 1, limit: 2).to_a
+  end
+
+  def test_multiple_files
+    Tempfile.create(%w[first .csv]) do |first|
+      Tempfile.create(%w[second .csv]) do |second|
+        first.write(NIL_VALUES_CONTENT)
+        first.flush
+        second.write("notes,id\n\"\",5\nNULL,6\n")
+        second.flush
+
+        rows = OSV.for_each([first.path, second.path], nil_values: NIL_VALUES_MARKERS, union_headers: true).to_a
+        assert_equal NIL_VALUES_EXPECTED, rows.take(4)
+        assert_equal [{ "id" => "5", "name" => nil, "notes" => "" }, { "id" => "6", "name" => nil, "notes" => nil }],
+                     rows.drop(4)
+
+        rows = OSV.for_each([first.path, first.path], nil_values: NIL_VALUES_MARKERS, source_column: "file").to_a
+        assert_equal NIL_VALUES_EXPECTED * 2, rows.map { |row| row.except("file") }
+      end
+    end
+  end
+
+  def test_nil_values_unknown_column
+    error = assert_raises(ArgumentError) { OSV.parse(NIL_VALUES_CONTENT, column_nil_values: { "fax" => [""] }).to_a }
+    assert_match(/fax/, error.message)
+  end
+
+  def test_invalid_markers
+    assert_raises(TypeError) { OSV.parse(NIL_VALUES_CONTENT, nil_values: "NULL").to_a }
+    assert_raises(TypeError) { OSV.parse(NIL_VALUES_CONTENT, nil_values: [1]).to_a }
+    assert_raises(TypeError) { OSV.parse(NIL_VALUES_CONTENT, column_nil_values: ["name"]).to_a }
+  end
+
+  # Tests for the values given to fields missing from short rows
+  DEFAULTS_CONTENT = <<~CSV
+    name,city,country
+    John,Paris,FR
+    Jane,Boston
+    Jim
+  CSV
+
+  def test_missing_value
+    rows = OSV.parse(DEFAULTS_CONTENT, flexible: true, missing_value: "?").to_a
+    assert_equal ["FR", "?", "?"], rows.map { |row| row["country"] }
+    assert_equal %w[Paris Boston ?], rows.map { |row| row["city"] }
+  end
+
+  def test_defaults_override_missing_value
+    rows = OSV.parse(DEFAULTS_CONTENT, flexible: true, missing_value: "?", defaults: { country: "US" }).to_a
+    assert_equal [%w[John Paris FR], %w[Jane Boston US], %w[Jim ? US]],
+                 rows.map { |row| row.values_at("name", "city", "country") }
+
+    rows = OSV.parse(DEFAULTS_CONTENT, flexible: true, defaults: { "country" => "US" }).to_a
+    assert_equal [{ "name" => "Jim", "city" => nil, "country" => "US" }], rows.drop(2)
+  end
+
+  def test_present_fields_are_kept
+    rows = OSV.parse("name,country\nJohn,\n", flexible: true, defaults: { "country" => "US" }).to_a
+    assert_equal [{ "name" => "John", "country" => "" }], rows
+  end
+
+  def test_array_rows_are_padded
+    rows = OSV.parse(DEFAULTS_CONTENT, flexible: true, result_type: :array, defaults: { "country" => "US" }).to_a
+    assert_equal [%w[John Paris FR], %w[Jane Boston US], ["Jim", nil, "US"]], rows
+
+    rows = OSV.parse(DEFAULTS_CONTENT, flexible: true, result_type: :array).to_a
+    assert_equal [["Jane", "Boston"], ["Jim"]], rows.drop(1)
+  end
+
+  def test_headerless_input
+    rows = OSV.parse("a,b,c\nd\n", has_headers: false, flexible: true, result_type: :array,
+                                   defaults: { "c2" => "z" }).to_a
+    assert_equal [%w[a b c], ["d", nil, "z"]], rows
+  end
+
+  def test_filled_column_with_hashes
+    rows = OSV.parse(DEFAULTS_CONTENT, flexible: true, defaults: { "country" => "US" }, filled_column: "filled").to_a
+    assert_equal [[], ["country"], %w[city country]], rows.map { |row| row["filled"] }
+    assert_equal "US", rows[2]["country"]
+  end
+
+  def test_filled_column_with_arrays
+    rows = OSV.parse(DEFAULTS_CONTENT, flexible: true, result_type: :array, filled_column: "filled").to_a
+    assert_equal [["John", "Paris", "FR", []], ["Jane", "Boston", nil, ["country"]], ["Jim", nil, nil, %w[city country]]],
+                 rows
+  end
+
+  def test_filled_column_cannot_name_a_header
+    error = assert_raises(ArgumentError) { OSV.parse(DEFAULTS_CONTENT, flexible: true, filled_column: "city").to_a }
+    assert_match(/city/, error.message)
+  end
+
+  def test_defaults_are_not_converted
+    rows = OSV.parse(DEFAULTS_CONTENT, flexible: true, defaults: { "city" => " Unknown " }, converters: { "city" => :strip }).to_a
+    assert_equal ["Paris", "Boston", " Unknown "], rows.map { |row| row["city"] }
+  end
+
+  def test_defaults_with_files
+    Tempfile.create(%w[defaults .csv]) do |file|
+      file.write(DEFAULTS_CONTENT)
+      file.flush
+      [{}, { mmap: true }, { parallel: 2 }].each do |options|
+        rows = OSV.for_each(file.path, flexible: true, missing_value: "-", filled_column: "filled", **options).to_a
+        assert_equal %w[FR - -], rows.map { |row| row["country"] }
+        assert_equal [[], ["country"], %w[city country]], rows.map { |row| row["filled"] }
+      end
+    end
+  end
+
+  def test_sampled_rows
+    rows = OSV.parse(DEFAULTS_CONTENT, flexible: true, filled_column: "filled", sample: { reservoir: 5, seed: 3 }).to_a
+    assert_equal [[], ["country"], %w[city country]], rows.map { |row| row["filled"] }
+  end
+
+  def test_requires_flexible
+    assert_raises(ArgumentError) { OSV.parse(DEFAULTS_CONTENT, missing_value: "?").to_a }
+    assert_raises(ArgumentError) { OSV.parse(DEFAULTS_CONTENT, defaults: { "country" => "US" }).to_a }
+  end
+
+  def test_defaults_unknown_column
+    error = assert_raises(ArgumentError) { OSV.parse(DEFAULTS_CONTENT, flexible: true, defaults: { "zip" => "0" }).to_a }
+    assert_match(/zip/, error.message)
+  end
+
+  def test_invalid_defaults
+    assert_raises(TypeError) { OSV.parse(DEFAULTS_CONTENT, flexible: true, defaults: { "country" => 1 }).to_a }
+    assert_raises(TypeError) { OSV.parse(DEFAULTS_CONTENT, flexible: true, defaults: ["US"]).to_a }
+  end
+
+  # Tests for fields past the last header in flexible mode
+  EXTRA_FIELDS_CONTENT = <<~CSV
+    id,name
+    1,John
+    2,Jane,admin,"x,y"
+    3
+  CSV
+
+  def test_drop_by_default
+    rows = OSV.parse(EXTRA_FIELDS_CONTENT, flexible: true).to_a
+    assert_equal [{ "id" => "2", "name" => "Jane" }], [rows[1]]
+    assert_equal rows, OSV.parse(EXTRA_FIELDS_CONTENT, flexible: true, extra_fields: :drop).to_a
+  end
+
+  def test_raise
+    error = assert_raises(RuntimeError) { OSV.parse(EXTRA_FIELDS_CONTENT, flexible: true, extra_fields: :raise).to_a }
+    assert_match(/4 fields.*2 fields/, error.message)
+
+    assert_raises(RuntimeError) do
+      OSV.parse(EXTRA_FIELDS_CONTENT, flexible: true, extra_fields: "raise", result_type: :array).to_a
+    end
+  end
+
+  def test_raise_yields_rows_before_the_long_one
+    rows = []
+    assert_raises(RuntimeError) do
+      OSV.parse(EXTRA_FIELDS_CONTENT, flexible: true, extra_fields: :raise) { |row| rows << row }
+    end
+    assert_equal [{ "id" => "1", "name" => "John" }], rows
+  end
+
+  def test_collect
+    rows = OSV.parse(EXTRA_FIELDS_CONTENT, flexible: true, extra_fields: :collect).to_a
+    assert_equal({ "id" => "2", "name" => "Jane", "_extra" => ["admin", "x,y"] }, rows[1])
+    refute rows[0].key?("_extra")
+    assert_equal({ "id" => "3", "name" => nil }, rows[2])
+  end
+
+  def test_collect_under_custom_key
+    rows = OSV.parse(EXTRA_FIELDS_CONTENT, flexible: true, extra_fields: :collect, extra_key: "rest").to_a
+    assert_equal ["admin", "x,y"], rows[1]["rest"]
+  end
+
+  def test_collected_fields_go_through_nil_values
+    content = "id,name\n1,John,NULL,\"NULL\",\n"
+    rows = OSV.parse(content, flexible: true, extra_fields: :collect, nil_values: ["", "NULL"]).to_a
+    assert_equal [nil, "NULL", nil], rows[0]["_extra"]
+  end
+
+  def test_array_rows_keep_extra_fields
+    rows = OSV.parse(EXTRA_FIELDS_CONTENT, flexible: true, extra_fields: :collect, result_type: :array).to_a
+    assert_equal ["2", "Jane", "admin", "x,y"], rows[1]
+  end
+
+  def test_collect_with_files
+    Tempfile.create(%w[extra .csv]) do |file|
+      file.write(EXTRA_FIELDS_CONTENT)
+      file.flush
+      [{}, { mmap: true }, { parallel: 2 }].each do |options|
+        rows = OSV.for_each(file.path, flexible: true, extra_fields: :collect, **options).to_a
+        assert_equal ["admin", "x,y"], rows[1]["_extra"]
+      end
+    end
+  end
+
+  def test_extra_fields_requires_flexible
+    assert_raises(ArgumentError) { OSV.parse(EXTRA_FIELDS_CONTENT, extra_fields: :collect).to_a }
+  end
+
+  def test_invalid_options
+    assert_raises(ArgumentError) { OSV.parse(EXTRA_FIELDS_CONTENT, flexible: true, extra_fields: :keep).to_a }
+    error = assert_raises(ArgumentError) do
+      OSV.parse(EXTRA_FIELDS_CONTENT, flexible: true, extra_fields: :collect, extra_key: "name").to_a
+    end
+    assert_match(/name/, error.message)
+  end
+
+  # Tests for the ways of dealing with columns that share a header
+  DUPLICATE_HEADERS_CONTENT = <<~CSV
+    id,name,id,name
+    1,John,A,Johnny
+    2,Jane,B,
+  CSV
+
+  def test_last_by_default
+    expected = [{ "id" => "A", "name" => "Johnny" }, { "id" => "B", "name" => "" }]
+    assert_equal expected, OSV.parse(DUPLICATE_HEADERS_CONTENT).to_a
+    assert_equal expected, OSV.parse(DUPLICATE_HEADERS_CONTENT, duplicate_headers: :last).to_a
+  end
+
+  def test_first
+    rows = OSV.parse(DUPLICATE_HEADERS_CONTENT, duplicate_headers: :first).to_a
+    assert_equal [{ "id" => "1", "name" => "John" }, { "id" => "2", "name" => "Jane" }], rows
+  end
+
+  def test_duplicate_headers_raise
+    error = assert_raises(RuntimeError) { OSV.parse(DUPLICATE_HEADERS_CONTENT, duplicate_headers: :raise).to_a }
+    assert_match(/id/, error.message)
+    assert_raises(RuntimeError) { OSV.parse(DUPLICATE_HEADERS_CONTENT, duplicate_headers: "raise", result_type: :array).to_a }
+    assert_equal 2, OSV.parse("a,b\n1,2\n3,4\n", duplicate_headers: :raise).to_a.size
+  end
+
+  def test_suffix
+    rows = OSV.parse(DUPLICATE_HEADERS_CONTENT, duplicate_headers: :suffix).to_a
+    assert_equal({ "id" => "1", "name" => "John", "id_2" => "A", "name_2" => "Johnny" }, rows[0])
+  end
+
+  def test_suffix_skips_names_in_use
+    rows = OSV.parse("a,a,a_2,a\n1,2,3,4\n", duplicate_headers: :suffix).to_a
+    assert_equal({ "a" => "1", "a_3" => "2", "a_2" => "3", "a_4" => "4" }, rows[0])
+  end
+
+  def test_suffixed_names_in_column_options
+    rows = OSV.parse(DUPLICATE_HEADERS_CONTENT, duplicate_headers: :suffix, converters: { "name_2" => :downcase },
+                              column_nil_values: { "name_2" => [""] }).to_a
+    assert_equal ["johnny", nil], rows.map { |row| row["name_2"] }
+    assert_equal %w[John Jane], rows.map { |row| row["name"] }
+  end
+
+  def test_array
+    rows = OSV.parse(DUPLICATE_HEADERS_CONTENT, duplicate_headers: :array).to_a
+    assert_equal({ "id" => %w[1 A], "name" => %w[John Johnny] }, rows[0])
+    assert_equal({ "id" => %w[2 B], "name" => ["Jane", ""] }, rows[1])
+  end
+
+  def test_array_keeps_unique_columns_as_strings
+    rows = OSV.parse("id,email,email\n1,a@example.com,\n", duplicate_headers: :array, nil_values: [""]).to_a
+    assert_equal [{ "id" => "1", "email" => ["a@example.com", nil] }], rows
+  end
+
+  def test_array_rows_are_unaffected
+    %i[first suffix array].each do |mode|
+      rows = OSV.parse(DUPLICATE_HEADERS_CONTENT, duplicate_headers: mode, result_type: :array).to_a
+      assert_equal [%w[1 John A Johnny], ["2", "Jane", "B", ""]], rows
+    end
+  end
+
+  def test_with_files
+    Tempfile.create(%w[duplicates .csv]) do |file|
+      file.write(DUPLICATE_HEADERS_CONTENT)
+      file.flush
+      [{}, { mmap: true }, { parallel: 2 }].each do |options|
+        rows = OSV.for_each(file.path, duplicate_headers: :array, **options).to_a
+        assert_equal %w[1 A], rows[0]["id"]
+      end
+    end
+  end
+
+  def test_invalid_mode
+    assert_raises(ArgumentError) { OSV.parse(DUPLICATE_HEADERS_CONTENT, duplicate_headers: :merge).to_a }
+  end
 end
\ No newline at end of file
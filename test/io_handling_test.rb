@@ -2,6 +2,14 @@
 
 require "osv"
 require "zlib"
+require "tempfile"
+require "pathname"
+require "stringio"
+require "socket"
+require "tmpdir"
+require "rubygems/package"
+require "json"
+require "fiddle"
 require "minitest/autorun"
 
 # Tests focused on IO handling capabilities
@@ -213,4 +221,976 @@ class IoHandlingTest < Minitest::Test
     StringIO.new(csv_content).tap { |io| OSV.for_each(io, result_type: :array) { |row| actual << row } }
     assert_equal expected, actual
   end
-end
\ No newline at end of file
+
+  def test_buffer_size_limits_io_reads
+    io = StringIO.new("id,name\n" + (1..500).map { |i| "#{i},name#{i}\n" }.join)
+    read_sizes = []
+    io.define_singleton_method(:read) do |length = nil, *args|
+      read_sizes << length
+      super(length, *args)
+    end
+
+    rows = OSV.for_each(io, buffer_size: 64).to_a
+    assert_equal 500, rows.size
+    assert_equal({ "id" => "500", "name" => "name500" }, rows.last)
+    assert read_sizes.all? { |length| length <= 64 }, "expected reads of at most 64 bytes, got #{read_sizes.max}"
+  end
+
+  def test_buffer_size_with_tiny_and_large_buffers
+    expected = OSV.for_each("test/test.csv").to_a
+    assert_equal expected, OSV.for_each("test/test.csv", buffer_size: 1).to_a
+    assert_equal expected, OSV.for_each("test/test.csv", buffer_size: 1 << 20).to_a
+  end
+
+  def test_buffer_size_must_be_positive
+    assert_raises(ArgumentError) { OSV.for_each("test/test.csv", buffer_size: 0) { |row| } }
+  end
+
+  # Tests for reading memory-mapped files
+  def test_mmap_matches_buffered_reading
+    with_mmap_file do |path|
+      expected = OSV.for_each(path).to_a
+      actual = OSV.for_each(path, mmap: true).to_a
+      assert_equal 10_000, actual.size
+      assert_equal expected, actual
+    end
+  end
+
+  def test_mmap_array_result
+    with_mmap_file do |path|
+      expected = OSV.for_each(path, result_type: :array).to_a
+      actual = []
+      OSV.for_each(path, mmap: true, result_type: :array) { |row| actual << row }
+      assert_equal expected, actual
+    end
+  end
+
+  def test_mmap_with_options
+    with_mmap_file do |path|
+      options = { has_headers: false, nil_string: "", trim: :all, offset: 10, limit: 100 }
+      expected = OSV.for_each(path, **options).to_a
+      assert_equal expected, OSV.for_each(path, mmap: true, **options).to_a
+    end
+  end
+
+  def test_mmap_lossy
+    Tempfile.create(%w[mmap_lossy .csv]) do |file|
+      file.binmode
+      file.write("a,b\nvalid,caf\xFF\nplain,text")
+      file.close
+
+      rows = OSV.for_each(file.path, mmap: true, lossy: true).to_a
+      assert_equal [{ "a" => "valid", "b" => "caf�" }, { "a" => "plain", "b" => "text" }], rows
+    end
+  end
+
+  def test_mmap_ignore_null_bytes
+    Tempfile.create(%w[mmap_null .csv]) do |file|
+      file.write("a,b\nx\0y,z\n")
+      file.close
+
+      rows = OSV.for_each(file.path, mmap: true, ignore_null_bytes: true).to_a
+      assert_equal [{ "a" => "xy", "b" => "z" }], rows
+    end
+  end
+
+  def test_mmap_empty_file
+    Tempfile.create(%w[mmap_empty .csv]) do |file|
+      file.close
+      assert_equal [], OSV.for_each(file.path, mmap: true).to_a
+    end
+  end
+
+  def test_mmap_count
+    with_mmap_file do |path|
+      assert_equal 10_000, OSV.count(path, mmap: true)
+    end
+  end
+
+  def test_mmap_ignored_for_gzip_and_io
+    Tempfile.create(%w[mmap_gz .csv.gz]) do |file|
+      Zlib::GzipWriter.open(file.path) { |gz| gz.write("a,b\n1,2\n") }
+      assert_equal [{ "a" => "1", "b" => "2" }], OSV.for_each(file.path, mmap: true).to_a
+    end
+
+    rows = OSV.for_each(StringIO.new("a,b\n1,2\n"), mmap: true).to_a
+    assert_equal [{ "a" => "1", "b" => "2" }], rows
+  end
+
+  def test_mmap_missing_file
+    assert_raises(RuntimeError) { OSV.for_each("does/not/exist.csv", mmap: true).to_a }
+  end
+
+  def with_mmap_file
+    Tempfile.create(%w[mmap .csv]) do |file|
+      file.write("id,name,notes\r\n")
+      10_000.times do |i|
+        if (i % 5).zero?
+          file.write("#{i},\"name, #{i}\",\"multi\nline \"\"quoted\"\"\"\r\n")
+        else
+          file.write("#{i},name#{i},\r\n")
+        end
+      end
+      file.close
+      yield file.path
+    end
+  end
+
+  # Tests for how each entry point interprets its input
+  INPUT_KIND_EXPECTED = [
+    { "id" => "1", "age" => "25", "name" => "John" },
+    { "name" => "Jane", "id" => "2", "age" => "30" },
+    { "name" => "Jim", "age" => "35", "id" => "3" }
+  ].freeze
+
+  def test_for_each_reads_pathname_as_path
+    assert_equal INPUT_KIND_EXPECTED, OSV.for_each(Pathname.new("test/test.csv")).to_a
+    assert_equal 3, OSV.count(Pathname.new("test/test.csv"))
+  end
+
+  def test_pathname_with_parallel_and_mmap
+    path = Pathname.new("test/test.csv")
+    assert_equal INPUT_KIND_EXPECTED, OSV.for_each(path, parallel: 2).to_a
+    assert_equal INPUT_KIND_EXPECTED, OSV.for_each(path, mmap: true).to_a
+  end
+
+  def test_for_each_rejects_ambiguous_input
+    error = assert_raises(TypeError) { OSV.for_each(42) { |row| } }
+    assert_includes error.message, "Integer"
+    assert_includes error.message, "OSV.parse"
+    assert_raises(TypeError) { OSV.for_each(:"test/test.csv").to_a }
+  end
+
+  def test_open
+    assert_equal INPUT_KIND_EXPECTED, OSV.open("test/test.csv").to_a
+    rows = []
+    OSV.open(Pathname.new("test/test.csv"), result_type: :array) { |row| rows << row }
+    assert_equal [%w[1 John 25], %w[2 Jane 30], %w[3 Jim 35]], rows
+  end
+
+  def test_open_rejects_io
+    error = assert_raises(TypeError) { OSV.open(StringIO.new("a\n1\n")) { |row| } }
+    assert_includes error.message, "OSV.open expects a file path"
+  end
+
+  def test_read
+    File.open("test/test.csv") { |file| assert_equal INPUT_KIND_EXPECTED, OSV.read(file).to_a }
+    assert_equal [{ "a" => "1" }], OSV.read(StringIO.new("a\n1\n")).to_a
+  end
+
+  def test_read_rejects_path
+    error = assert_raises(TypeError) { OSV.read("test/test.csv") { |row| } }
+    assert_includes error.message, "OSV.read expects an IO"
+    assert_raises(TypeError) { OSV.read(Pathname.new("test/test.csv")).to_a }
+  end
+
+  def test_parse
+    assert_equal [{ "a" => "1", "b" => "2" }], OSV.parse("a,b\n1,2\n").to_a
+    assert_equal [%w[1 2]], OSV.parse("1,2\n", has_headers: false, result_type: :array).to_a
+  end
+
+  def test_parse_rejects_non_strings
+    assert_raises(TypeError) { OSV.parse(StringIO.new("a\n1\n")) { |row| } }
+  end
+
+  # Tests for how IO objects are read: output buffer reuse, readpartial and the
+  # opt-in file descriptor fast path
+  IO_READING_CONTENT = "id,name\n" + (1..2_000).map { |i| "#{i},name#{i}\n" }.join
+
+  def test_read_reuses_output_buffer
+    io = StringIO.new(IO_READING_CONTENT)
+    buffers = []
+    io.define_singleton_method(:read) do |length = nil, outbuf = nil|
+      buffers << outbuf
+      super(length, outbuf)
+    end
+
+    rows = OSV.for_each(io, buffer_size: 256).to_a
+    assert_equal 2_000, rows.size
+    refute_nil buffers.first
+    assert_equal 1, buffers.map(&:object_id).uniq.size
+  end
+
+  def test_read_with_length_only
+    io = Object.new
+    source = StringIO.new(IO_READING_CONTENT)
+    io.define_singleton_method(:read) { |length| source.read(length) }
+
+    rows = OSV.for_each(io).to_a
+    assert_equal 2_000, rows.size
+    assert_equal({ "id" => "2000", "name" => "name2000" }, rows.last)
+  end
+
+  def test_pipe_rows_are_parsed_as_they_arrive
+    reader, writer = IO.pipe
+    writer.write("id,name\n1,first\n")
+    writer.flush
+
+    first_row = nil
+    OSV.read(reader) do |row|
+      first_row ||= row
+      # The second row is only written once the first one has been yielded
+      if row["id"] == "1"
+        writer.write("2,second\n")
+        writer.close
+      end
+    end
+    assert_equal({ "id" => "1", "name" => "first" }, first_row)
+  ensure
+    reader&.close
+    writer&.close unless writer&.closed?
+  end
+
+  def test_fileno_reads_file_from_current_position
+    Tempfile.create(%w[fileno .csv]) do |file|
+      file.write(IO_READING_CONTENT)
+      file.flush
+      file.rewind
+
+      assert_equal 2_000, OSV.for_each(file, fileno: true).to_a.size
+
+      file.rewind
+      file.gets # skip the header through Ruby's buffered reader
+      rows = OSV.for_each(file, fileno: true, has_headers: false, result_type: :array).to_a
+      assert_equal %w[1 name1], rows.first
+      assert_equal 2_000, rows.size
+    end
+  end
+
+  def test_fileno_respects_custom_read
+    Tempfile.create(%w[fileno_custom .csv]) do |file|
+      file.write(IO_READING_CONTENT)
+      file.flush
+      file.rewind
+
+      calls = 0
+      file.define_singleton_method(:read) do |*args|
+        calls += 1
+        super(*args)
+      end
+
+      assert_equal 2_000, OSV.for_each(file, fileno: true).to_a.size
+      assert_operator calls, :>, 0
+    end
+  end
+
+  def test_io_converting_encodings
+    Tempfile.create(%w[encoded .csv]) do |file|
+      file.write(IO_READING_CONTENT)
+      file.flush
+
+      File.open(file.path, "r:iso-8859-1:utf-8") do |io|
+        rows = OSV.for_each(io).to_a
+        assert_equal 2_000, rows.size
+        assert_equal({ "id" => "2000", "name" => "name2000" }, rows.last)
+      end
+    end
+  end
+
+  def test_fileno_reads_sockets_and_pipes_through_ruby
+    [UNIXSocket.pair, IO.pipe].each do |reader, writer|
+      writer.write(IO_READING_CONTENT)
+      writer.close
+      reader.gets # leaves the rest of the data in Ruby's buffer
+      rows = OSV.for_each(reader, fileno: true, has_headers: false, result_type: :array).to_a
+      assert_equal %w[1 name1], rows.first
+      assert_equal 2_000, rows.size
+    ensure
+      reader&.close
+    end
+  end
+
+  def test_fileno_ignored_for_other_inputs
+    assert_equal 2_000, OSV.for_each(StringIO.new(IO_READING_CONTENT), fileno: true).to_a.size
+    assert_equal 3, OSV.count("test/test.csv", fileno: true)
+  end
+
+  # Tests for reading several files as a single stream
+  def test_array_of_files
+    with_part_files do |dir|
+      rows = OSV.for_each(part_paths(dir, "part-0001.csv", "part-0002.csv.gz", "part-0003.csv")).to_a
+      assert_equal %w[1 2 3 4 5], rows.map { |row| row["id"] }
+      assert_equal({ "id" => "3", "name" => "Jim" }, rows[2])
+    end
+  end
+
+  def test_glob
+    with_part_files do |dir|
+      rows = OSV.for_each(File.join(dir, "part-*"), glob: true, result_type: :array).to_a
+      assert_equal [%w[1 John], %w[2 Jane], %w[3 Jim], %w[4 Jill], %w[5 Jack]], rows
+      assert_equal 5, OSV.count(File.join(dir, "part-*"), glob: true)
+    end
+  end
+
+  def test_glob_without_matches
+    with_part_files do |dir|
+      assert_raises(TypeError) { OSV.for_each(File.join(dir, "nothing-*"), glob: true).to_a }
+    end
+  end
+
+  def test_open_accepts_files_and_pathnames
+    with_part_files do |dir|
+      files = [Pathname.new(File.join(dir, "part-0001.csv")), File.join(dir, "part-0003.csv")]
+      assert_equal %w[1 2 4 5], OSV.open(files).map { |row| row["id"] }
+    end
+  end
+
+  def test_source_column
+    with_part_files do |dir|
+      rows = OSV.for_each(part_paths(dir, "part-0001.csv", "part-0002.csv.gz"), source_column: "file").to_a
+      assert_equal File.join(dir, "part-0001.csv"), rows[0]["file"]
+      assert_equal File.join(dir, "part-0002.csv.gz"), rows[2]["file"]
+      assert_equal "Jim", rows[2]["name"]
+
+      arrays = OSV.for_each(part_paths(dir, "part-0002.csv.gz"), source_column: "file", result_type: :array).to_a
+      assert_equal [[File.join(dir, "part-0002.csv.gz"), "3", "Jim"]], arrays
+    end
+  end
+
+  def test_mismatched_headers_raise
+    with_part_files do |dir|
+      write_part(dir, "other.csv", "id,email\n6,x@example.com\n")
+      rows = []
+      error = assert_raises(RuntimeError) do
+        OSV.for_each(part_paths(dir, "part-0001.csv", "other.csv")) { |row| rows << row }
+      end
+      assert_includes error.message, "other.csv"
+      assert_equal 2, rows.size
+    end
+  end
+
+  def test_union_headers
+    with_part_files do |dir|
+      write_part(dir, "other.csv", "email,id\nx@example.com,6\n")
+      rows = OSV.for_each(part_paths(dir, "part-0002.csv.gz", "other.csv"), union_headers: true).to_a
+      assert_equal [
+        { "id" => "3", "name" => "Jim", "email" => nil },
+        { "id" => "6", "name" => nil, "email" => "x@example.com" }
+      ], rows
+
+      arrays = OSV.for_each(part_paths(dir, "part-0002.csv.gz", "other.csv"), union_headers: true, result_type: :array).to_a
+      assert_equal [["3", "Jim", nil], ["6", nil, "x@example.com"]], arrays
+    end
+  end
+
+  def test_union_headers_keep_empty_fields_apart_from_missing_columns
+    with_part_files do |dir|
+      write_part(dir, "other.csv", "email,id\n,6\n")
+      rows = OSV.for_each(part_paths(dir, "part-0002.csv.gz", "other.csv"), union_headers: true, source_column: "file").to_a
+      assert_equal [nil, ""], rows.map { |row| row["email"] }
+      assert_equal ["Jim", nil], rows.map { |row| row["name"] }
+      assert_equal File.join(dir, "other.csv"), rows[1]["file"]
+    end
+  end
+
+  def test_selection_spans_files
+    with_part_files do |dir|
+      rows = OSV.for_each(part_paths(dir, "part-0001.csv", "part-0002.csv.gz", "part-0003.csv"), offset: 1, limit: 3).to_a
+      assert_equal %w[2 3 4], rows.map { |row| row["id"] }
+    end
+  end
+
+  def test_empty_files_are_skipped
+    with_part_files do |dir|
+      write_part(dir, "empty.csv", "")
+      rows = OSV.for_each(part_paths(dir, "empty.csv", "part-0002.csv.gz")).to_a
+      assert_equal [{ "id" => "3", "name" => "Jim" }], rows
+    end
+  end
+
+  def test_files_must_be_paths
+    assert_raises(TypeError) { OSV.for_each([StringIO.new("a\n1\n")]).to_a }
+  end
+
+  def with_part_files
+    Dir.mktmpdir("osv_multi") do |dir|
+      write_part(dir, "part-0001.csv", "id,name\n1,John\n2,Jane\n")
+      write_part_gz(dir, "part-0002.csv.gz", "id,name\n3,Jim\n")
+      write_part(dir, "part-0003.csv", "id,name\n4,Jill\n5,Jack\n")
+      yield dir
+    end
+  end
+
+  def part_paths(dir, *names)
+    names.map { |name| File.join(dir, name) }
+  end
+
+  def write_part(dir, name, content)
+    File.write(File.join(dir, name), content)
+  end
+
+  def write_part_gz(dir, name, content)
+    Zlib::GzipWriter.open(File.join(dir, name)) { |gz| gz.write(content) }
+  end
+
+  # Tests for reading CSV members out of zip and tar archives
+  ARCHIVE_ORDERS = "id,total\n1,9.99\n2,19.99\n"
+  ARCHIVE_CUSTOMERS = "id,name\n1,John\n"
+
+  def test_zip_member_by_name
+    Dir.mktmpdir("osv_archive") do |dir|
+      path = write_zip(dir, "export.zip", "readme.txt" => "hello", "export/orders.csv" => ARCHIVE_ORDERS)
+      rows = OSV.for_each(path, entry: "orders.csv").to_a
+      assert_equal [{ "id" => "1", "total" => "9.99" }, { "id" => "2", "total" => "19.99" }], rows
+    end
+  end
+
+  def test_zip_member_by_pattern
+    Dir.mktmpdir("osv_archive") do |dir|
+      path = write_zip(dir, "export.zip", "notes.txt" => "hello", "orders.csv" => ARCHIVE_ORDERS, "customers.csv" => ARCHIVE_CUSTOMERS)
+      assert_equal [%w[1 9.99], %w[2 19.99]], OSV.for_each(path, entry: "*.csv", result_type: :array).to_a
+      assert_equal [%w[1 John]], OSV.for_each(path, entry: "cust*", result_type: :array).to_a
+    end
+  end
+
+  def test_zip_member_by_full_path
+    Dir.mktmpdir("osv_archive") do |dir|
+      path = write_zip(dir, "export.zip", "2024/orders.csv" => ARCHIVE_CUSTOMERS, "2025/orders.csv" => ARCHIVE_ORDERS)
+      assert_equal %w[1 2], OSV.for_each(path, entry: "2025/orders.csv").map { |row| row["id"] }
+      assert_equal %w[1], OSV.for_each(path, entry: "2024/*.csv").map { |row| row["id"] }
+    end
+  end
+
+  def test_stored_and_deflated_zip_members
+    Dir.mktmpdir("osv_archive") do |dir|
+      stored = write_zip(dir, "stored.zip", { "orders.csv" => ARCHIVE_ORDERS }, deflate: false)
+      deflated = write_zip(dir, "deflated.zip", { "orders.csv" => ARCHIVE_ORDERS * 100 }, deflate: true)
+      assert_equal 2, OSV.count(stored)
+      assert_equal 2, OSV.count(deflated, limit: 2)
+      assert_equal ARCHIVE_ORDERS.lines.size - 1, OSV.for_each(stored, entry: "orders.csv").to_a.size
+    end
+  end
+
+  def test_zip_with_a_single_file_needs_no_entry
+    Dir.mktmpdir("osv_archive") do |dir|
+      path = write_zip(dir, "export.zip", "orders.csv" => ARCHIVE_ORDERS)
+      assert_equal %w[1 2], OSV.for_each(path).map { |row| row["id"] }
+    end
+  end
+
+  def test_several_files_need_an_entry
+    Dir.mktmpdir("osv_archive") do |dir|
+      paths = [
+        write_zip(dir, "export.zip", "orders.csv" => ARCHIVE_ORDERS, "customers.csv" => ARCHIVE_CUSTOMERS),
+        write_tar(dir, "export.tar", "orders.csv" => ARCHIVE_ORDERS, "customers.csv" => ARCHIVE_CUSTOMERS),
+        write_tar(dir, "export.tgz", { "orders.csv" => ARCHIVE_ORDERS, "customers.csv" => ARCHIVE_CUSTOMERS }, gzip: true)
+      ]
+      paths.each do |path|
+        error = assert_raises(RuntimeError) { OSV.for_each(path).to_a }
+        assert_match(/entry/, error.message)
+        assert_includes error.message, "orders.csv, customers.csv"
+      end
+    end
+  end
+
+  def test_macos_metadata_is_not_counted
+    Dir.mktmpdir("osv_archive") do |dir|
+      members = { "orders.csv" => ARCHIVE_ORDERS, "._orders.csv" => "\0\5\26\7", "__MACOSX/._orders.csv" => "\0\5\26\7" }
+      paths = [write_zip(dir, "export.zip", members), write_tar(dir, "export.tar", members)]
+      paths.each do |path|
+        assert_equal %w[1 2], OSV.for_each(path).map { |row| row["id"] }
+      end
+    end
+  end
+
+  def test_missing_member
+    Dir.mktmpdir("osv_archive") do |dir|
+      path = write_zip(dir, "export.zip", "orders.csv" => ARCHIVE_ORDERS)
+      error = assert_raises(RuntimeError) { OSV.for_each(path, entry: "refunds.csv").to_a }
+      assert_match(/refunds\.csv/, error.message)
+    end
+  end
+
+  def test_tar_member
+    Dir.mktmpdir("osv_archive") do |dir|
+      path = write_tar(dir, "export.tar", "./readme.txt" => "hello" * 200, "data/orders.csv" => ARCHIVE_ORDERS)
+      assert_equal %w[1 2], OSV.for_each(path, entry: "orders.csv").map { |row| row["id"] }
+    end
+  end
+
+  def test_gzipped_tar_member
+    Dir.mktmpdir("osv_archive") do |dir|
+      %w[export.tar.gz export.tgz].each do |name|
+        path = write_tar(dir, name, { "customers.csv" => ARCHIVE_CUSTOMERS, "orders.csv" => ARCHIVE_ORDERS }, gzip: true)
+        assert_equal [%w[1 9.99], %w[2 19.99]], OSV.for_each(path, entry: "orders.csv", result_type: :array).to_a
+      end
+    end
+  end
+
+  def test_tar_with_a_single_file_needs_no_entry
+    Dir.mktmpdir("osv_archive") do |dir|
+      path = write_tar(dir, "export.tar", "orders.csv" => ARCHIVE_ORDERS)
+      assert_equal %w[1 2], OSV.for_each(path).map { |row| row["id"] }
+      assert_equal 2, OSV.count(write_tar(dir, "export.tgz", { "orders.csv" => ARCHIVE_ORDERS }, gzip: true))
+    end
+  end
+
+  def test_tar_with_long_member_names
+    Dir.mktmpdir("osv_archive") do |dir|
+      name = "#{"nested/" * 20}orders.csv"
+      path = write_tar(dir, "export.tar", name => ARCHIVE_ORDERS)
+      assert_equal %w[1 2], OSV.for_each(path, entry: "orders.csv").map { |row| row["id"] }
+    end
+  end
+
+  def test_entry_with_multiple_archives
+    Dir.mktmpdir("osv_archive") do |dir|
+      first = write_zip(dir, "first.zip", "orders.csv" => ARCHIVE_ORDERS, "notes.txt" => "hello")
+      second = write_tar(dir, "second.tar.gz", { "orders.csv" => "id,total\n3,29.99\n" }, gzip: true)
+      assert_equal %w[1 2 3], OSV.for_each([first, second], entry: "orders.csv").map { |row| row["id"] }
+    end
+  end
+
+  def test_mmap_and_parallel_fall_back_for_archives
+    Dir.mktmpdir("osv_archive") do |dir|
+      path = write_zip(dir, "export.zip", "orders.csv" => ARCHIVE_ORDERS)
+      assert_equal 2, OSV.for_each(path, mmap: true).to_a.size
+      assert_equal 2, OSV.for_each(path, parallel: 2).to_a.size
+    end
+  end
+
+  def write_tar(dir, name, members, gzip: false)
+    io = StringIO.new(+"")
+    Gem::Package::TarWriter.new(io) do |tar|
+      members.each do |member, content|
+        tar.add_file_simple(member, 0o644, content.bytesize) { |file| file.write(content) }
+      end
+    end
+    data = gzip ? Zlib.gzip(io.string) : io.string
+    File.join(dir, name).tap { |path| File.binwrite(path, data) }
+  end
+
+  # Writes a minimal zip archive, with each member stored or raw-deflated.
+  def write_zip(dir, name, members, deflate: true)
+    body = +""
+    directory = +""
+    members.each do |member, content|
+      data = deflate ? Zlib::Deflate.new(Zlib::DEFAULT_COMPRESSION, -Zlib::MAX_WBITS).deflate(content, Zlib::FINISH) : content
+      method = deflate ? 8 : 0
+      fields = [20, 0, method, 0, 0, Zlib.crc32(content), data.bytesize, content.bytesize, member.bytesize, 0]
+      offset = body.bytesize
+      body << [0x04034b50, *fields].pack("VvvvvvVVVvv") << member << data
+      directory << [0x02014b50, 20, *fields, 0, 0, 0, 0, offset].pack("VvvvvvvVVVvvvvvVV") << member
+    end
+    trailer = [0x06054b50, 0, 0, members.size, members.size, directory.bytesize, body.bytesize, 0].pack("VvvvvVVv")
+    File.join(dir, name).tap { |path| File.binwrite(path, body + directory + trailer) }
+  end
+
+  # Tests for converting CSV to JSON Lines
+  JSONL_CONTENT = <<~CSV
+    name,age,city
+    Alice,30,Paris
+    Bob,,"New ""York"""
+  CSV
+
+  def test_writes_to_a_path
+    Tempfile.create(%w[rows .jsonl]) do |file|
+      assert_equal 2, OSV.to_jsonl(StringIO.new(JSONL_CONTENT), file.path)
+      assert_equal <<~JSONL, File.read(file.path)
+        {"name":"Alice","age":"30","city":"Paris"}
+        {"name":"Bob","age":"","city":"New \\"York\\""}
+      JSONL
+
+      OSV.to_jsonl(StringIO.new(JSONL_CONTENT), Pathname.new(file.path), limit: 1)
+      assert_equal 1, File.readlines(file.path).size
+    end
+  end
+
+  def test_writes_gzipped_files
+    Tempfile.create(%w[rows .jsonl.gz]) do |file|
+      OSV.to_jsonl(StringIO.new(JSONL_CONTENT), file.path)
+      lines = Zlib::GzipReader.open(file.path, &:readlines)
+      assert_equal "Alice", JSON.parse(lines.first)["name"]
+    end
+  end
+
+  def test_writes_to_an_io_left_open
+    output = StringIO.new
+    assert_equal 2, OSV.to_jsonl(StringIO.new(JSONL_CONTENT), output)
+    refute output.closed?
+    rows = output.string.lines.map { |line| JSON.parse(line) }
+    assert_equal OSV.parse(JSONL_CONTENT).to_a, rows
+  end
+
+  def test_keys_follow_the_header_order
+    output = StringIO.new
+    header = ("a".."t").to_a.reverse
+    OSV.to_jsonl(StringIO.new("#{header.join(",")}\n#{(1..20).to_a.join(",")}\n"), output)
+    assert_equal header, JSON.parse(output.string).keys
+  end
+
+  def test_array_rows
+    output = StringIO.new
+    OSV.to_jsonl(StringIO.new(JSONL_CONTENT), output, result_type: :array, nil_values: [""])
+    assert_equal <<~JSONL, output.string
+      ["Alice","30","Paris"]
+      ["Bob",null,"New \\"York\\""]
+    JSONL
+  end
+
+  def test_nil_fields_are_null
+    output = StringIO.new
+    OSV.to_jsonl(StringIO.new(JSONL_CONTENT), output, nil_values: [""])
+    assert_nil JSON.parse(output.string.lines.last).fetch("age")
+  end
+
+  def test_table_schema_types
+    descriptor = {
+      "fields" => [
+        { "name" => "id", "type" => "integer" },
+        { "name" => "price", "type" => "number" },
+        { "name" => "active", "type" => "boolean" },
+        { "name" => "created_on", "type" => "date" },
+        { "name" => "updated_at", "type" => "datetime" }
+      ]
+    }
+    content = <<~CSV
+      id,price,active,created_on,updated_at
+      1,9.5,true,2024-01-31,2024-01-31T10:00:00Z
+      2,,0,1969-12-31,2024-02-01T09:30:00.250+02:00
+    CSV
+    output = StringIO.new
+    OSV.to_jsonl(StringIO.new(content), output, table_schema: descriptor)
+    assert_equal <<~JSONL, output.string
+      {"id":1,"price":9.5,"active":true,"created_on":"2024-01-31","updated_at":"2024-01-31T10:00:00Z"}
+      {"id":2,"price":null,"active":false,"created_on":"1969-12-31","updated_at":"2024-02-01T09:30:00.25+02:00"}
+    JSONL
+  end
+
+  def test_collected_extra_fields_and_filled_columns
+    output = StringIO.new
+    OSV.to_jsonl(StringIO.new("a,b\n1,2,3\n4\n"), output, flexible: true, extra_fields: :collect,
+                                                          filled_column: "_filled")
+    assert_equal <<~JSONL, output.string
+      {"a":"1","b":"2","_extra":["3"],"_filled":[]}
+      {"a":"4","b":null,"_filled":["b"]}
+    JSONL
+  end
+
+  def test_rows_breaking_the_schema_are_written
+    output = StringIO.new
+    count = OSV.to_jsonl(StringIO.new("id\n1\nx\n"), output, schema: { columns: { "id" => { format: /\A\d+\z/ } } })
+    assert_equal 2, count
+    assert_equal %({"id":"1"}\n{"id":"x"}\n), output.string
+  end
+
+  def test_reads_files
+    Tempfile.create(%w[rows .csv]) do |file|
+      file.write(JSONL_CONTENT)
+      file.flush
+      [{}, { mmap: true }, { parallel: 2 }].each do |options|
+        output = StringIO.new
+        assert_equal 2, OSV.to_jsonl(file.path, output, **options)
+        assert_equal %w[Alice Bob], output.string.lines.map { |line| JSON.parse(line)["name"] }
+      end
+    end
+  end
+
+  def test_invalid_outputs
+    assert_raises(ArgumentError) { OSV.to_jsonl(StringIO.new(JSONL_CONTENT)) }
+    assert_raises(TypeError) { OSV.to_jsonl(StringIO.new(JSONL_CONTENT), 42) }
+  end
+
+  # Tests for reshaping CSV into CSV
+  TRANSFORM_CONTENT = <<~CSV
+    id;name;status;note
+    1;John;active;"said ""hi"""
+    2;Jane;deleted;
+    3;Jim;active;a,b
+  CSV
+
+  def transform(content = TRANSFORM_CONTENT, **options)
+    output = StringIO.new
+    count = OSV.transform(StringIO.new(content), output, col_sep: ";", **options)
+    [count, output.string]
+  end
+
+  def test_changes_the_delimiter
+    count, csv = transform(col_sep_out: ",")
+    assert_equal 3, count
+    assert_equal <<~CSV, csv
+      id,name,status,note
+      1,John,active,"said ""hi"""
+      2,Jane,deleted,
+      3,Jim,active,"a,b"
+    CSV
+  end
+
+  def test_keeps_the_input_dialect_by_default
+    _, csv = transform
+    assert_equal <<~CSV, csv
+      id;name;status;note
+      1;John;active;"said ""hi"""
+      2;Jane;deleted;
+      3;Jim;active;a,b
+    CSV
+  end
+
+  def test_select_and_rename
+    _, csv = transform(select: %w[name id], rename: { "name" => "full_name", id: :key }, col_sep_out: ",")
+    assert_equal "full_name,key\nJohn,1\nJane,2\nJim,3\n", csv
+  end
+
+  def test_where
+    count, csv = transform(select: %w[id], where: { "status" => "active" })
+    assert_equal 2, count
+    assert_equal "id\n1\n3\n", csv
+
+    _, csv = transform(select: %w[id], where: { "status" => %w[active deleted], "name" => /\AJ[ai]/ })
+    assert_equal "id\n2\n3\n", csv
+
+    _, csv = transform(select: %w[id], where: { "note" => nil }, nil_values: [""])
+    assert_equal "id\n2\n", csv
+  end
+
+  def test_where_applies_before_limit
+    _, csv = transform(select: %w[id], where: { "status" => "active" }, offset: 1, limit: 1)
+    assert_equal "id\n3\n", csv
+  end
+
+  def test_where_compares_values_before_converters
+    _, csv = transform(" x \n a \n", col_sep: ",", where: { " x " => " a " }, converters: { " x " => :strip })
+    assert_equal " x \na\n", csv
+  end
+
+  def test_refuses_to_write_over_its_input
+    Dir.mktmpdir("osv_transform") do |dir|
+      path = File.join(dir, "in.csv")
+      File.write(path, TRANSFORM_CONTENT)
+      File.symlink(path, File.join(dir, "link.csv"))
+
+      [[path, path], [path, File.join(dir, ".", "link.csv")], [[path], Pathname(path)]].each do |input, output|
+        error = assert_raises(ArgumentError) { OSV.transform(input, output, col_sep: ";") }
+        assert_match(/truncated/, error.message)
+      end
+      assert_raises(ArgumentError) { OSV.transform(File.join(dir, "*.csv"), path, glob: true, col_sep: ";") }
+      assert_equal TRANSFORM_CONTENT, File.read(path)
+    end
+  end
+
+  def test_quote_char_out
+    _, csv = transform(select: %w[note], quote_char_out: "'", col_sep_out: ",")
+    # A lone empty field is quoted, so the row isn't mistaken for a blank line
+    assert_equal "note\nsaid \"hi\"\n''\n'a,b'\n", csv
+  end
+
+  def test_nil_fields_are_written_empty
+    _, csv = transform("a,b\n1\n", col_sep: ",", flexible: true)
+    assert_equal "a,b\n1,\n", csv
+  end
+
+  def test_table_schema_values_are_written_as_displayed
+    descriptor = { "fields" => [{ "name" => "n", "type" => "integer", "groupChar" => "," }] }
+    _, csv = transform("n\n\"1,000\"\n", col_sep: ",", table_schema: descriptor)
+    assert_equal "n\n1000\n", csv
+  end
+
+  def test_inputs_without_headers
+    _, csv = transform("1;2\n3;4\n", has_headers: false, select: %w[c1])
+    assert_equal "2\n4\n", csv
+  end
+
+  def test_writes_files
+    Tempfile.create(%w[transform .csv]) do |input|
+      input.write(TRANSFORM_CONTENT)
+      input.flush
+      Tempfile.create(%w[transformed .csv]) do |output|
+        [{}, { mmap: true }, { parallel: 2 }].each do |options|
+          assert_equal 3, OSV.transform(input.path, output.path, col_sep: ";", select: %w[id], **options)
+          assert_equal "id\n1\n2\n3\n", File.read(output.path)
+        end
+      end
+    end
+  end
+
+  def test_unknown_columns
+    assert_raises(ArgumentError) { transform(select: %w[email]) }
+    assert_raises(ArgumentError) { transform(rename: { "email" => "mail" }) }
+    assert_raises(ArgumentError) { transform(where: { "email" => "x" }) }
+  end
+
+  def test_invalid_options
+    assert_raises(TypeError) { transform(where: { "id" => 1 }) }
+    assert_raises(ArgumentError) { transform(col_sep_out: "") }
+    assert_raises(TypeError) { OSV.transform(StringIO.new(TRANSFORM_CONTENT), Object.new) }
+  end
+
+  # Tests for writing Parquet and Arrow IPC files
+  COLUMNAR_CONTENT = <<~CSV
+    id,name,score,active,joined_on,seen_at
+    1,Alice,9.5,true,2024-01-31,2024-01-31T10:00:00Z
+    2,Bob,,false,2024-02-29,2024-02-01T09:30:00+02:00
+    3,Carol,7,TRUE,,
+  CSV
+
+  def test_writes_parquet_files
+    Tempfile.create(%w[rows .parquet]) do |file|
+      assert_equal 3, OSV.to_parquet(StringIO.new(COLUMNAR_CONTENT), file.path, compression: :none)
+      data = File.binread(file.path)
+      assert data.start_with?("PAR1")
+      assert data.end_with?("PAR1")
+      %w[id name score active joined_on seen_at Alice Carol].each { |text| assert_includes data, text }
+    end
+  end
+
+  def test_writes_arrow_files
+    Tempfile.create(%w[rows .arrow]) do |file|
+      assert_equal 3, OSV.to_arrow(StringIO.new(COLUMNAR_CONTENT), file.path)
+      data = File.binread(file.path)
+      assert data.start_with?("ARROW1")
+      assert data.end_with?("ARROW1")
+      %w[id name Alice Carol].each { |text| assert_includes data, text }
+    end
+  end
+
+  def test_writes_to_an_io
+    output = StringIO.new("".b)
+    assert_equal 3, OSV.to_parquet(StringIO.new(COLUMNAR_CONTENT), output)
+    refute output.closed?
+    assert output.string.start_with?("PAR1")
+    assert output.string.end_with?("PAR1")
+  end
+
+  def test_compressions
+    %i[snappy zstd none].each do |compression|
+      output = StringIO.new("".b)
+      OSV.to_parquet(StringIO.new(COLUMNAR_CONTENT), output, compression: compression)
+      assert output.string.end_with?("PAR1")
+    end
+  end
+
+  def test_values_that_stop_fitting_the_inferred_type
+    content = "id\n1\n2\nx\n"
+    error = assert_raises(RuntimeError) { OSV.to_arrow(StringIO.new(content), StringIO.new("".b), batch_size: 2) }
+    assert_match(/"x" of column id in row 3 isn't of type integer/, error.message)
+
+    # Types inferred from every row, or given, fit them all
+    assert_equal 3, OSV.to_arrow(StringIO.new(content), StringIO.new("".b))
+    assert_equal 3, OSV.to_arrow(StringIO.new(content), StringIO.new("".b), batch_size: 2, types: { id: :string })
+  end
+
+  def test_explicit_types
+    error = assert_raises(RuntimeError) do
+      OSV.to_parquet(StringIO.new(COLUMNAR_CONTENT), StringIO.new("".b), types: { "name" => :integer })
+    end
+    assert_match(/Alice/, error.message)
+  end
+
+  def test_table_schema_types_are_kept
+    descriptor = { "fields" => [{ "name" => "n", "type" => "integer", "groupChar" => "," }] }
+    assert_equal 1, OSV.to_arrow(StringIO.new("n\n\"1,000\"\n"), StringIO.new("".b), table_schema: descriptor)
+  end
+
+  def test_columnar_reads_files
+    Tempfile.create(%w[rows .csv]) do |file|
+      file.write(COLUMNAR_CONTENT)
+      file.flush
+      [{}, { mmap: true }, { parallel: 2 }].each do |options|
+        assert_equal 3, OSV.to_parquet(file.path, StringIO.new("".b), **options)
+      end
+    end
+  end
+
+  def test_columnar_invalid_options
+    output = StringIO.new("".b)
+    assert_raises(ArgumentError) { OSV.to_parquet(StringIO.new(COLUMNAR_CONTENT), output, types: { "id" => :decimal }) }
+    assert_raises(ArgumentError) { OSV.to_parquet(StringIO.new(COLUMNAR_CONTENT), output, types: { "email" => :string }) }
+    assert_raises(ArgumentError) { OSV.to_parquet(StringIO.new(COLUMNAR_CONTENT), output, compression: :lz4) }
+    assert_raises(ArgumentError) { OSV.to_parquet(StringIO.new(COLUMNAR_CONTENT), output, batch_size: 0) }
+    assert_raises(ArgumentError) { OSV.to_arrow(StringIO.new(COLUMNAR_CONTENT), output, compression: :zstd) }
+  end
+
+  # Tests for reading Arrow record batches
+  ARROW_BATCH_CONTENT = <<~CSV
+    id,name,score
+    1,Alice,9.5
+    2,Bob,
+    3,Carol,7
+  CSV
+
+  def test_yields_batches_of_batch_size_rows
+    batches = OSV.read_arrow(StringIO.new(ARROW_BATCH_CONTENT), batch_size: 2).to_a
+    assert_equal [2, 1], batches.map(&:num_rows)
+    batches.each do |batch|
+      assert_instance_of OSV::ArrowBatch, batch
+      assert_equal 3, batch.num_columns
+      assert_equal %w[id name score], batch.column_names
+    end
+  end
+
+  def test_with_a_block
+    rows = 0
+    assert_nil(OSV.read_arrow(StringIO.new(ARROW_BATCH_CONTENT)) { |batch| rows += batch.num_rows })
+    assert_equal 3, rows
+  end
+
+  def test_result_type_arrow
+    batches = []
+    OSV.for_each(StringIO.new(ARROW_BATCH_CONTENT), result_type: :arrow) { |batch| batches << batch }
+    assert_equal [3], batches.map(&:num_rows)
+  end
+
+  def test_export_to_c
+    batch = OSV.read_arrow(StringIO.new(ARROW_BATCH_CONTENT)).first
+    array = Fiddle::Pointer.malloc(OSV::ArrowBatch::C_ARRAY_SIZE, Fiddle::RUBY_FREE)
+    schema = Fiddle::Pointer.malloc(OSV::ArrowBatch::C_SCHEMA_SIZE, Fiddle::RUBY_FREE)
+    batch.send(:export_to_c, array.to_i, schema.to_i)
+
+    # The batch is exported as a struct array with one child per column
+    assert_equal 3, array[0, 8].unpack1("q")
+    assert_equal 3, array[32, 8].unpack1("q")
+    assert_equal "+s", Fiddle::Pointer.new(schema[0, 8].unpack1("J")).to_s
+
+    # The consumer releases what it was handed
+    release(array, 64)
+    release(schema, 56)
+  end
+
+  def test_export_to_c_is_private
+    batch = OSV.read_arrow(StringIO.new(ARROW_BATCH_CONTENT)).first
+    refute_respond_to batch, :export_to_c
+    assert_raises(NoMethodError) { batch.export_to_c(0, 0) }
+    assert_raises(ArgumentError) { batch.send(:export_to_c, 0, 0) }
+  end
+
+  def test_to_arrow
+    begin
+      require "arrow"
+    rescue LoadError
+      skip "red-arrow isn't installed"
+    end
+
+    record_batch = OSV.read_arrow(StringIO.new(ARROW_BATCH_CONTENT)).first.to_arrow
+    assert_equal 3, record_batch.n_rows
+    assert_equal [1, 2, 3], record_batch["id"].data.to_a
+    assert_equal [9.5, nil, 7.0], record_batch["score"].data.to_a
+  end
+
+  def test_types_and_options
+    batch = OSV.read_arrow(StringIO.new(ARROW_BATCH_CONTENT), types: { id: :string }, limit: 1).first
+    assert_equal 1, batch.num_rows
+
+    error = assert_raises(RuntimeError) { OSV.read_arrow(StringIO.new("n\n1\nx\n"), batch_size: 1).to_a }
+    assert_match(/"x" of column n in row 2 isn't of type integer/, error.message)
+
+    assert_raises(ArgumentError) { OSV.read_arrow(StringIO.new(ARROW_BATCH_CONTENT), batch_size: 0) {} }
+    assert_raises(ArgumentError) { OSV.read_arrow(StringIO.new(ARROW_BATCH_CONTENT), compression: :zstd) {} }
+    assert_raises(ArgumentError) { OSV.read_arrow(StringIO.new(ARROW_BATCH_CONTENT), types: { "email" => :string }) {} }
+  end
+
+  def test_arrow_batch_reads_files
+    Tempfile.create(%w[rows .csv]) do |file|
+      file.write(ARROW_BATCH_CONTENT)
+      file.flush
+      [{}, { mmap: true }, { parallel: 2 }].each do |options|
+        assert_equal [3], OSV.read_arrow(file.path, **options).map(&:num_rows)
+      end
+    end
+  end
+
+
+  def release(struct, offset)
+    function = Fiddle::Function.new(struct[offset, 8].unpack1("J"), [Fiddle::TYPE_VOIDP], Fiddle::TYPE_VOID)
+    function.call(struct)
+  end
+end